@@ -9,6 +9,12 @@ use std::path::Path;
 use std::{collections::HashMap, env};
 use xmltree::{Element, XMLNode};
 
+/// The specification version the types are generated from.
+const SPEC_VERSION: &str = "1.10";
+
+/// Versions whose element tables are available at runtime, used to write older files.
+const TABLE_VERSIONS: &[&str] = &["1.6", "1.8", "1.9", "1.10"];
+
 fn read_spec(filename: &str) -> Result<Element, Box<dyn Error>> {
     let addr = Element::parse(Cursor::new(fs::read(filename)?)).unwrap();
     Ok(addr)
@@ -98,15 +104,6 @@ impl SDFAttribute {
             reference: None,
         }
     }
-    fn get_field_string(&self) -> String {
-        format!(
-            "  #[yaserde(attribute = true, rename = \"{}\")]\n  pub {}: {},\n",
-            self.name,
-            sanitize_field(&self.name),
-            self.required
-                .wrap_type(get_storage_type(self.rtype.as_str()))
-        )
-    }
 }
 
 fn prefix_type(name: &str) -> String {
@@ -124,6 +121,7 @@ struct SDFElement {
     child_includes: Vec<SDFIncludes>,
     source_file: String,
     top_level: bool,
+    copy_data: bool,
 }
 
 impl SDFElement {
@@ -142,6 +140,7 @@ impl SDFElement {
             child_includes: vec![],
             source_file: "".to_string(),
             top_level: false,
+            copy_data: false,
         }
     }
 
@@ -155,84 +154,267 @@ impl SDFElement {
         }
     }
 
-    fn code_gen(&self, prefix: &str, file_map: &HashMap<String, SDFElement>) -> String {
-        let mut out = "".to_string();
-        out += format!("// Generated from {}\n", self.source_file).as_str();
-        if !self.properties.description.is_empty() {
-            for line in self.properties.description.split('\n') {
-                out += &("/// ".to_string() + line);
-                out += "\n";
-            }
-        }
-        out += "#[derive(Default, PartialEq, Clone, Debug, YaSerialize, YaDeserialize)]\n";
-        out += format!("#[yaserde(rename = \"{}\")]\n", self.properties.name).as_str();
-        out += format!("pub struct {}{} {{\n", prefix_type(prefix), self.typename()).as_str();
-        for child in &self.child_attrs {
-            out += child.get_field_string().as_str();
+    /// Returns true if the element carries no data other than its presence.
+    fn is_empty(&self) -> bool {
+        self.child_elems.is_empty()
+            && self.child_attrs.is_empty()
+            && self.child_includes.is_empty()
+            && self.properties.rtype.is_empty()
+            && self.properties.reference.is_none()
+    }
+
+    fn code_gen(&self, prefix: &str, path: &str, file_map: &HashMap<String, SDFElement>) -> String {
+        let typename = prefix_type(prefix) + self.typename().as_str();
+        let choice = CHOICES.iter().find(|choice| choice.path == path);
+        let is_alternative =
+            |name: &str| choice.is_some_and(|choice| choice.alternatives.contains(&name));
+
+        let mut fields = vec![];
+        for attr in &self.child_attrs {
+            // Required attributes with a default in the spec hold it when they are missing,
+            // `__default__` marks the ones without any
+            let default = attr
+                .default
+                .clone()
+                .filter(|default| default != "__default__");
+            let defaulted = matches!(attr.required, RequiredStatus::One) && default.is_some();
+            fields.push(Field {
+                xml_name: attr.name.clone(),
+                ident: sanitize_field(&attr.name),
+                storage: get_storage_type(&attr.rtype).to_string(),
+                // Attributes can't be repeated
+                required: match attr.required {
+                    RequiredStatus::Many => RequiredStatus::Optional,
+                    required => required,
+                },
+                kind: FieldKind::Attribute {
+                    default: default.filter(|_| defaulted),
+                },
+            });
         }
 
+        // Elements made of alternatives are enums, which keep no unknown content of their own
+        let is_enum = |path: &str| {
+            CHOICES
+                .iter()
+                .any(|choice| choice.path == path && choice.field.is_none())
+        };
+
         let mut child_gen = "".to_string();
         let name = prefix.to_string().to_case(Case::Pascal) + self.typename().as_str();
         for child in &self.child_elems {
-            if child.properties.rtype.is_empty() {
-                // TODO(arjo): Handle includes
-                if let Some(reference) = &child.properties.reference {
-                    out += format!(
-                        "  #[yaserde(rename = \"{}\")]\n  pub {}: Vec<Boxed<Sdf{}>>,\n",
-                        reference,
-                        reference,
-                        self.typename()
-                    )
-                    .as_str();
-                    continue;
-                }
+            if child.copy_data {
+                // Arbitrary children are kept as they are, any element that is not part of the
+                // spec ends up here.
+                fields.push(Field {
+                    xml_name: "".to_string(),
+                    ident: "elements".to_string(),
+                    storage: "ElementMap".to_string(),
+                    required: RequiredStatus::One,
+                    kind: FieldKind::CopyData,
+                });
+                continue;
+            }
+            let mut field = Field {
+                xml_name: child.properties.name.clone(),
+                ident: sanitize_field(&child.properties.name),
+                storage: "".to_string(),
+                required: child.properties.required,
+                kind: FieldKind::Struct { unit: false },
+            };
+            // Values with attributes get their own type, holding the value as `data`
+            if !child.properties.rtype.is_empty() && child.child_attrs.is_empty() {
+                field.storage = get_storage_type(child.properties.rtype.as_str()).to_string();
+                field.kind = FieldKind::Value;
+            } else if let Some(reference) = &child.properties.reference {
+                field.storage = self.reference_type(reference, file_map);
+            } else if is_alternative(&child.properties.name) && child.is_empty() {
+                field.kind = FieldKind::Struct { unit: true };
+            } else {
                 let prefix = prefix_type(&name);
-                child_gen += child.code_gen(prefix.as_str(), file_map).as_str();
+                let child_path = format!("{}/{}", path, child.properties.name);
+                if is_enum(&child_path) {
+                    field.kind = FieldKind::Enum;
+                }
+                child_gen += child
+                    .code_gen(prefix.as_str(), &child_path, file_map)
+                    .as_str();
                 let typename = prefix + child.properties.name.to_case(Case::Pascal).as_str();
-                out += format!(
-                    "  #[yaserde(rename = \"{}\")]\n  pub {}: {},\n",
-                    child.properties.name,
-                    &sanitize_field(&child.properties.name),
-                    child
-                        .properties
-                        .required
-                        .wrap_type(typename.to_case(Case::Pascal).as_str()),
-                )
-                .as_str();
-            } else {
-                let typename = get_storage_type(child.properties.rtype.as_str());
-                out += format!(
-                    "  #[yaserde(rename = \"{}\")]\n  pub {}: {},\n",
-                    child.properties.name,
-                    &sanitize_field(&child.properties.name),
-                    child.properties.required.wrap_type(typename)
-                )
-                .as_str();
+                field.storage = typename.to_case(Case::Pascal);
             }
+            fields.push(field);
         }
         for child in &self.child_includes {
             if let Some(element) = file_map.get(&child.filename.to_string()) {
-                let typename = child
-                    .required
-                    .wrap_type(&("Sdf".to_string() + element.typename().as_str()));
-                out += format!(
-                    "  #[yaserde(rename = \"{}\")]\n  pub {} : {} /*{:?}*/,\n",
-                    element.properties.name.to_case(Case::Snake),
-                    &sanitize_field(&element.properties.name.to_case(Case::Snake)),
-                    typename,
-                    child.required
-                )
-                .as_str();
+                fields.push(Field {
+                    xml_name: element.properties.name.clone(),
+                    ident: sanitize_field(&element.properties.name.to_case(Case::Snake)),
+                    storage: "Sdf".to_string() + element.typename().as_str(),
+                    required: child.required,
+                    kind: match is_enum(&element.properties.name) {
+                        true => FieldKind::Enum,
+                        false => FieldKind::Struct { unit: false },
+                    },
+                });
             } else {
                 panic!("Unable to find element for file: {}", child.filename);
             }
         }
         if !self.properties.rtype.is_empty() {
-            out += "  #[yaserde(text = true)]\n   pub data: String\n";
+            fields.push(Field {
+                xml_name: "".to_string(),
+                ident: "data".to_string(),
+                storage: get_storage_type(&self.properties.rtype).to_string(),
+                required: RequiredStatus::One,
+                kind: FieldKind::Text,
+            });
+        }
+        // Keep vendor extensions and any other content that is not part of the spec
+        if !fields
+            .iter()
+            .any(|field| matches!(field.kind, FieldKind::CopyData))
+        {
+            fields.push(Field {
+                xml_name: "".to_string(),
+                ident: "extra".to_string(),
+                storage: "ElementMap".to_string(),
+                required: RequiredStatus::One,
+                kind: FieldKind::CopyData,
+            });
+        }
+        fields.push(Field {
+            xml_name: "".to_string(),
+            ident: "extra_attributes".to_string(),
+            storage: "HashMap<String, String>".to_string(),
+            required: RequiredStatus::One,
+            kind: FieldKind::UnknownAttributes,
+        });
+        if fields
+            .iter()
+            .any(|field| matches!(field.kind, FieldKind::Value | FieldKind::Enum))
+        {
+            fields.push(Field {
+                xml_name: "".to_string(),
+                ident: "child_extras".to_string(),
+                storage: "ElementMap".to_string(),
+                required: RequiredStatus::One,
+                kind: FieldKind::ChildExtras,
+            });
+        }
+
+        let mut out = format!("// Generated from {}\n", self.source_file);
+        if !self.properties.description.is_empty() {
+            for line in self.properties.description.split('\n') {
+                out += &("/// ".to_string() + line);
+                out += "\n";
+            }
+        }
+        let Some(choice) = choice else {
+            out += &struct_code(&typename, &self.properties.name, &fields);
+            return out + child_gen.as_str();
+        };
+
+        // Pull the alternatives out of the fields
+        let position = fields
+            .iter()
+            .position(|field| choice.alternatives.contains(&field.xml_name.as_str()));
+        let (mut alternatives, mut fields): (Vec<_>, Vec<_>) =
+            fields.into_iter().partition(|field| {
+                choice.alternatives.contains(&field.xml_name.as_str())
+                    && matches!(field.kind, FieldKind::Struct { .. } | FieldKind::Value)
+            });
+        for alternative in alternatives.iter_mut() {
+            if choice.repeated.contains(&alternative.xml_name.as_str()) {
+                alternative.required = RequiredStatus::Many;
+            }
+        }
+        for alternative in choice.alternatives {
+            if !alternatives
+                .iter()
+                .any(|field| field.xml_name == *alternative)
+            {
+                panic!("Unable to find alternative {} in {}", alternative, path);
+            }
+        }
+        match choice.field {
+            None => {
+                out += &enum_code(&typename, Some(&self.properties.name), &alternatives);
+            }
+            Some(field) => {
+                let choice_typename = typename.clone() + field.to_case(Case::Pascal).as_str();
+                fields.insert(
+                    position.unwrap_or(fields.len()),
+                    Field {
+                        xml_name: "".to_string(),
+                        ident: sanitize_field(field),
+                        storage: choice_typename.clone(),
+                        required: RequiredStatus::Optional,
+                        kind: FieldKind::Choice { choice },
+                    },
+                );
+                out += &struct_code(&typename, &self.properties.name, &fields);
+                out += &format!("// Generated from {}\n", self.source_file);
+                for line in choice.description.split('\n') {
+                    out += &("/// ".to_string() + line);
+                    out += "\n";
+                }
+                out += &enum_code(&choice_typename, None, &alternatives);
+            }
+        }
+        out + child_gen.as_str()
+    }
+
+    /// Resolves a `ref` attribute to the type generated for the referenced spec file.
+    /// References that lead back to the file being generated are wrapped in `Boxed`, since the
+    /// resulting type would otherwise be recursive.
+    fn reference_type(&self, reference: &str, file_map: &HashMap<String, SDFElement>) -> String {
+        let filename = reference_filename(reference);
+        let Some(element) = file_map.get(&filename) else {
+            panic!("Unable to find element for reference: {}", reference);
+        };
+        let typename = prefix_type(&element.typename());
+        if element.depends_on(&self.source_file, file_map, &mut HashSet::new()) {
+            format!("Boxed<{}>", typename)
+        } else {
+            typename
+        }
+    }
+
+    /// Returns true if the type generated for this element contains, through its children,
+    /// includes or references, the type generated from `filename`.
+    fn depends_on(
+        &self,
+        filename: &str,
+        file_map: &HashMap<String, SDFElement>,
+        visited: &mut HashSet<String>,
+    ) -> bool {
+        let dependencies = self
+            .child_includes
+            .iter()
+            .map(|include| include.filename.clone())
+            .chain(
+                self.child_elems
+                    .iter()
+                    .filter_map(|child| child.properties.reference.as_deref())
+                    .map(reference_filename),
+            );
+        for dependency in dependencies {
+            if dependency == filename {
+                return true;
+            }
+            if !visited.insert(dependency.clone()) {
+                continue;
+            }
+            if let Some(element) = file_map.get(&dependency) {
+                if element.depends_on(filename, file_map, visited) {
+                    return true;
+                }
+            }
         }
-        out += "}\n\n";
-        out += child_gen.as_str();
-        out
+        self.child_elems
+            .iter()
+            .filter(|child| child.properties.reference.is_none())
+            .any(|child| child.depends_on(filename, file_map, visited))
     }
 
     fn set_source(&mut self, filename: &str) {
@@ -243,6 +425,709 @@ impl SDFElement {
     }
 }
 
+/// The alternatives allowed by each value of an attribute.
+type AllowedAlternatives = &'static [(&'static str, &'static [&'static str])];
+
+/// Elements that hold exactly one of a set of children. They are generated as enums so that
+/// invalid combinations can't be represented. When `field` is `None` the alternatives make up the
+/// whole element, otherwise they are held by an optional field with that name that sits next to
+/// the other children of the element.
+struct Choice {
+    path: &'static str,
+    field: Option<&'static str>,
+    description: &'static str,
+    alternatives: &'static [&'static str],
+    /// Alternatives that may be repeated, held as lists.
+    repeated: &'static [&'static str],
+    /// An attribute of the element that tells which alternative it holds, along with the
+    /// alternatives allowed by each of its values. Other values allow any alternative.
+    selected_by: Option<(&'static str, AllowedAlternatives)>,
+}
+
+const CHOICES: &[Choice] = &[
+    Choice {
+        path: "geometry",
+        field: None,
+        description: "",
+        alternatives: &[
+            "empty",
+            "box",
+            "capsule",
+            "cylinder",
+            "ellipsoid",
+            "heightmap",
+            "image",
+            "mesh",
+            "plane",
+            "polyline",
+            "sphere",
+        ],
+        // libsdformat reads every polyline of a geometry, the ones inside another one are holes
+        repeated: &["polyline"],
+        selected_by: None,
+    },
+    Choice {
+        path: "material/pbr",
+        field: None,
+        description: "",
+        alternatives: &["metal", "specular"],
+        repeated: &[],
+        selected_by: None,
+    },
+    Choice {
+        path: "sensor",
+        field: Some("kind"),
+        description: "Configuration specific to the type of the sensor.",
+        alternatives: &[
+            "air_pressure",
+            "altimeter",
+            "camera",
+            "contact",
+            "force_torque",
+            "gps",
+            "imu",
+            "lidar",
+            "logical_camera",
+            "magnetometer",
+            "navsat",
+            "ray",
+            "rfid",
+            "rfidtag",
+            "sonar",
+            "transceiver",
+        ],
+        repeated: &[],
+        selected_by: Some((
+            "type",
+            &[
+                ("air_pressure", &["air_pressure"]),
+                ("altimeter", &["altimeter"]),
+                ("boundingbox", &["camera"]),
+                ("boundingbox_camera", &["camera"]),
+                ("camera", &["camera"]),
+                ("contact", &["contact"]),
+                ("depth", &["camera"]),
+                ("depth_camera", &["camera"]),
+                ("force_torque", &["force_torque"]),
+                ("gps", &["gps", "navsat"]),
+                ("gpu_lidar", &["lidar", "ray"]),
+                ("gpu_ray", &["lidar", "ray"]),
+                ("imu", &["imu"]),
+                ("lidar", &["lidar", "ray"]),
+                ("logical_camera", &["logical_camera"]),
+                ("magnetometer", &["magnetometer"]),
+                ("multicamera", &["camera"]),
+                ("navsat", &["gps", "navsat"]),
+                ("ray", &["lidar", "ray"]),
+                ("rfid", &["rfid"]),
+                ("rfidtag", &["rfidtag"]),
+                ("rgbd", &["camera"]),
+                ("rgbd_camera", &["camera"]),
+                ("segmentation", &["camera"]),
+                ("segmentation_camera", &["camera"]),
+                ("sonar", &["sonar"]),
+                ("thermal", &["camera"]),
+                ("thermal_camera", &["camera"]),
+                ("wireless_receiver", &["transceiver"]),
+                ("wireless_transmitter", &["transceiver"]),
+            ],
+        )),
+    },
+    Choice {
+        path: "population",
+        field: Some("region"),
+        description: "The region in which the models are distributed.",
+        alternatives: &["box", "cylinder"],
+        repeated: &[],
+        selected_by: None,
+    },
+];
+
+enum FieldKind {
+    /// An attribute of the element. Required attributes with a default hold it when they are
+    /// missing.
+    Attribute { default: Option<String> },
+    /// The text of the element.
+    Text,
+    /// A child element that only holds a value.
+    Value,
+    /// A child element with its own generated type. Unit alternatives of a choice carry no data.
+    Struct { unit: bool },
+    /// A child element generated as an enum of its alternatives.
+    Enum,
+    /// A choice between the alternatives of a child element.
+    Choice { choice: &'static Choice },
+    /// Any child element that is not part of the spec.
+    CopyData,
+    /// Any attribute that is not part of the spec.
+    UnknownAttributes,
+    /// Content that is not part of the spec found in children whose types can't hold it, values
+    /// and enums.
+    ChildExtras,
+}
+
+/// A field of a generated struct or a variant of a generated enum.
+struct Field {
+    xml_name: String,
+    ident: String,
+    storage: String,
+    required: RequiredStatus,
+    kind: FieldKind,
+}
+
+impl Field {
+    fn declared_type(&self) -> String {
+        match self.kind {
+            FieldKind::Text
+            | FieldKind::CopyData
+            | FieldKind::UnknownAttributes
+            | FieldKind::ChildExtras => self.storage.clone(),
+            _ => self.required.wrap_type(&self.storage),
+        }
+    }
+
+    /// Returns the spec default that required attributes hold when they are missing.
+    fn default(&self) -> Option<&str> {
+        match &self.kind {
+            FieldKind::Attribute { default } => default.as_deref(),
+            _ => None,
+        }
+    }
+
+    /// Returns the expression giving the position of the next occurrence of the field among the
+    /// children of the same name, while deserializing.
+    fn position(&self) -> String {
+        match self.required {
+            RequiredStatus::Many => format!("{}.len()", self.ident),
+            _ => "0".to_string(),
+        }
+    }
+}
+
+/// Generates a struct along with its `YaDeserialize` and `YaSerialize` implementations.
+fn struct_code(typename: &str, xml_name: &str, fields: &[Field]) -> String {
+    let mut out = "#[derive(Default, PartialEq, Clone, Debug)]\n".to_string();
+    out += &format!("pub struct {} {{\n", typename);
+    for field in fields {
+        out += &format!("  pub {}: {},\n", field.ident, field.declared_type());
+    }
+    out += "}\n\n";
+
+    // Deserialization
+    let attributes: Vec<_> = fields
+        .iter()
+        .filter(|field| matches!(field.kind, FieldKind::Attribute { .. }))
+        .collect();
+    out += &format!("impl YaDeserialize for {} {{\n", typename);
+    out += "  fn deserialize<R: Read>(reader: &mut yaserde::de::Deserializer<R>) -> Result<Self, String> {\n";
+    out += &format!(
+        "    let attributes = field_io::read_start(reader, \"{}\")?;\n",
+        xml_name
+    );
+    for field in fields {
+        let declaration = match (&field.kind, field.required) {
+            (FieldKind::Text, _) => "String::new()".to_string(),
+            (FieldKind::CopyData | FieldKind::UnknownAttributes | FieldKind::ChildExtras, _) => {
+                "Default::default()".to_string()
+            }
+            (_, RequiredStatus::Many) => "Vec::new()".to_string(),
+            _ => "None".to_string(),
+        };
+        out += &format!(
+            "    let mut {}: {} = {};\n",
+            field.ident,
+            match (&field.kind, field.required) {
+                // The text is parsed once the whole element was read
+                (FieldKind::Text, _) => "String".to_string(),
+                (
+                    FieldKind::CopyData | FieldKind::UnknownAttributes | FieldKind::ChildExtras,
+                    _,
+                ) => field.storage.clone(),
+                (_, RequiredStatus::Many) => format!("Vec<{}>", field.storage),
+                _ => format!("Option<{}>", field.storage),
+            },
+            declaration
+        );
+    }
+    out += "    for attribute in attributes.into_iter() {\n";
+    out += "      let key = field_io::qualified_name(&attribute.name);\n";
+    if attributes.is_empty() {
+        out += "      extra_attributes.insert(key, attribute.value);\n";
+    } else {
+        out += "      match key.as_str() {\n";
+        for field in attributes.iter() {
+            let parse = format!(
+                "field_io::parse_attribute(&attribute.value, \"{}\", \"{}\")",
+                field.xml_name, xml_name
+            );
+            // Invalid values of optional attributes are dropped, as with the derived
+            // deserializers these implementations replaced
+            let action = match field.required {
+                RequiredStatus::One => format!("{} = Some({}?)", field.ident, parse),
+                _ => format!("{} = {}.ok()", field.ident, parse),
+            };
+            out += &format!("        \"{}\" => {},\n", field.xml_name, action);
+        }
+        out +=
+            "        _ => {\n          extra_attributes.insert(key, attribute.value);\n        }\n";
+        out += "      }\n";
+    }
+    out += "    }\n";
+    let mut arms = String::new();
+    let mut unknown = "field_io::skip_child(reader)?".to_string();
+    for field in fields {
+        match &field.kind {
+            // Likewise, values that can't be parsed are dropped unless they are required
+            FieldKind::Value => {
+                let parse = format!("field_io::parse_text(&text, \"{}\")", field.xml_name);
+                let action = match field.required {
+                    RequiredStatus::One => format!("{} = Some({}?)", field.ident, parse),
+                    RequiredStatus::Optional => format!("{} = {}.ok()", field.ident, parse),
+                    RequiredStatus::Many => format!("{}.extend({}.ok())", field.ident, parse),
+                };
+                arms += &format!(
+                    "          \"{}\" => {{\n            let (text, extension) = field_io::read_value_child(reader)?;\n            field_io::keep_extension(&mut child_extras, \"{}\", {}, extension);\n            {};\n          }}\n",
+                    field.xml_name,
+                    field.xml_name,
+                    field.position(),
+                    action
+                );
+            }
+            FieldKind::Enum => {
+                let action = match field.required {
+                    RequiredStatus::Many => format!("{}.push(value)", field.ident),
+                    _ => format!("{} = Some(value)", field.ident),
+                };
+                arms += &format!(
+                    "          \"{}\" => {{\n            let (value, extension) = field_io::read_child_with(reader, {}::read_element)?;\n            field_io::keep_extension(&mut child_extras, \"{}\", {}, extension);\n            {};\n          }}\n",
+                    field.xml_name,
+                    field.storage,
+                    field.xml_name,
+                    field.position(),
+                    action
+                );
+            }
+            FieldKind::Struct { .. } => {
+                let action = match field.required {
+                    RequiredStatus::Many => {
+                        format!("{}.push(field_io::read_child(reader)?)", field.ident)
+                    }
+                    _ => format!("{} = Some(field_io::read_child(reader)?)", field.ident),
+                };
+                arms += &format!("          \"{}\" => {},\n", field.xml_name, action);
+            }
+            FieldKind::Choice { choice } => {
+                let names: Vec<_> = choice
+                    .alternatives
+                    .iter()
+                    .map(|alternative| format!("\"{}\"", alternative))
+                    .collect();
+                arms += &format!(
+                    "          {} => {}::add_choice(&mut {}, &tag, reader, \"{}\")?,\n",
+                    names.join(" | "),
+                    field.storage,
+                    field.ident,
+                    xml_name
+                );
+            }
+            FieldKind::CopyData => {
+                unknown = format!("{}.push(deserialize_element(reader)?)", field.ident);
+            }
+            FieldKind::Attribute { .. }
+            | FieldKind::Text
+            | FieldKind::UnknownAttributes
+            | FieldKind::ChildExtras => {}
+        }
+    }
+    out += "    loop {\n      match field_io::next_child(reader)? {\n";
+    if arms.is_empty() {
+        out += &format!("        field_io::Next::Child(_) => {},\n", unknown);
+    } else {
+        out += "        field_io::Next::Child(tag) => match tag.as_str() {\n";
+        out += &arms;
+        out += &format!("          _ => {},\n        }},\n", unknown);
+    }
+    match fields
+        .iter()
+        .find(|field| matches!(field.kind, FieldKind::Text))
+    {
+        Some(field) => {
+            out += &format!(
+                "        field_io::Next::Text(text) => {} += &text,\n",
+                field.ident
+            )
+        }
+        None => out += "        field_io::Next::Text(_) => {}\n",
+    }
+    out += "        field_io::Next::End => break,\n      }\n    }\n";
+    for field in fields {
+        let FieldKind::Choice {
+            choice:
+                Choice {
+                    selected_by: Some((attribute, allowed)),
+                    ..
+                },
+        } = field.kind
+        else {
+            continue;
+        };
+        let allowed: Vec<_> = allowed
+            .iter()
+            .map(|(value, alternatives)| format!("({:?}, &{:?})", value, alternatives))
+            .collect();
+        out += &format!(
+            "    if let (Some(selected), Some(choice)) = (&{}, &{}) {{\n      field_io::check_choice(selected, choice.name(), &[{}], \"{}\")?;\n    }}\n",
+            sanitize_field(attribute),
+            field.ident,
+            allowed.join(", "),
+            xml_name
+        );
+    }
+    out += "    Ok(Self {\n";
+    for field in fields {
+        match (&field.kind, field.required) {
+            _ if field.default().is_some() => {
+                out += &format!(
+                    "      {}: match {} {{\n        Some(value) => value,\n        None => field_io::parse_attribute({:?}, \"{}\", \"{}\")?,\n      }},\n",
+                    field.ident,
+                    field.ident,
+                    field.default().unwrap_or_default(),
+                    field.xml_name,
+                    xml_name
+                )
+            }
+            (FieldKind::Text, _) if field.storage != "String" => {
+                out += &format!(
+                    "      {}: field_io::parse_text(&{}, \"{}\")?,\n",
+                    field.ident, field.ident, xml_name
+                )
+            }
+            (FieldKind::Text, _)
+            | (FieldKind::CopyData, _)
+            | (FieldKind::UnknownAttributes, _)
+            | (FieldKind::ChildExtras, _)
+            | (_, RequiredStatus::Many)
+            | (_, RequiredStatus::Optional) => out += &format!("      {},\n", field.ident),
+            (_, RequiredStatus::One) => {
+                out += &format!(
+                    "      {}: field_io::required({}, \"{}\", \"{}\")?,\n",
+                    field.ident, field.ident, field.xml_name, xml_name
+                )
+            }
+        }
+    }
+    out += "    })\n  }\n}\n\n";
+
+    // Serialization
+    out += &format!("impl YaSerialize for {} {{\n", typename);
+    out += "  fn serialize<W: Write>(&self, writer: &mut yaserde::ser::Serializer<W>) -> Result<(), String> {\n";
+    out += &format!(
+        "    let skip = field_io::write_start(writer, \"{}\", &[\n",
+        xml_name
+    );
+    for field in attributes.iter() {
+        let value = match field.required {
+            RequiredStatus::One => {
+                format!("Some(field_io::TextValue::to_text(&self.{}))", field.ident)
+            }
+            _ => format!(
+                "self.{}.as_ref().map(field_io::TextValue::to_text)",
+                field.ident
+            ),
+        };
+        out += &format!("      (\"{}\", {}),\n", field.xml_name, value);
+    }
+    out += "    ], &self.extra_attributes)?;\n";
+    for field in fields {
+        match &field.kind {
+            FieldKind::Attribute { .. } | FieldKind::UnknownAttributes | FieldKind::ChildExtras => {}
+            FieldKind::Text if field.storage != "String" => {
+                out += &format!(
+                    "    field_io::write_text(&field_io::TextValue::to_text(&self.{}), writer)?;\n",
+                    field.ident
+                )
+            }
+            FieldKind::Text => {
+                out += &format!("    field_io::write_text(&self.{}, writer)?;\n", field.ident)
+            }
+            FieldKind::CopyData => {
+                out += &format!(
+                    "    field_io::write_elements(&self.{}, writer)?;\n",
+                    field.ident
+                )
+            }
+            FieldKind::Choice { .. } => {
+                out += &format!(
+                    "    if let Some(value) = &self.{} {{\n      value.write_choice(writer)?;\n    }}\n",
+                    field.ident
+                )
+            }
+            FieldKind::Value | FieldKind::Enum => {
+                let write = |value: &str, position: &str| {
+                    let extension = format!(
+                        "field_io::find_extension(&self.child_extras, \"{}\", {})",
+                        field.xml_name, position
+                    );
+                    match field.kind {
+                        FieldKind::Value => format!(
+                            "field_io::write_value_child({}, \"{}\", {}, writer)?;",
+                            value, field.xml_name, extension
+                        ),
+                        _ => format!(
+                            "field_io::start_child(\"{}\", writer);\n      {}::write_element({}, {}, writer)?;",
+                            field.xml_name, field.storage, value, extension
+                        ),
+                    }
+                };
+                out += &match field.required {
+                    RequiredStatus::One => {
+                        format!("    {}\n", write(&format!("&self.{}", field.ident), "0"))
+                    }
+                    RequiredStatus::Optional => format!(
+                        "    if let Some(value) = &self.{} {{\n      {}\n    }}\n",
+                        field.ident,
+                        write("value", "0")
+                    ),
+                    RequiredStatus::Many => format!(
+                        "    for (index, value) in self.{}.iter().enumerate() {{\n      {}\n    }}\n",
+                        field.ident,
+                        write("value", "index")
+                    ),
+                };
+            }
+            FieldKind::Struct { .. } => {
+                let write = format!("field_io::write_child(value, \"{}\", writer)?;", field.xml_name);
+                out += &match field.required {
+                    RequiredStatus::One => format!(
+                        "    field_io::write_child(&self.{}, \"{}\", writer)?;\n",
+                        field.ident,
+                        field.xml_name
+                    ),
+                    RequiredStatus::Optional => format!(
+                        "    if let Some(value) = &self.{} {{\n      {}\n    }}\n",
+                        field.ident, write
+                    ),
+                    RequiredStatus::Many => format!(
+                        "    for value in self.{}.iter() {{\n      {}\n    }}\n",
+                        field.ident, write
+                    ),
+                };
+            }
+        }
+    }
+    out += "    field_io::write_end(writer, skip)\n  }\n\n";
+    out += SERIALIZE_ATTRIBUTES;
+    out += "}\n\n";
+    out
+}
+
+/// Generates an enum with one variant per alternative. When `xml_name` is set the enum is the
+/// element holding the alternatives, otherwise it is the alternative itself. Repeated
+/// alternatives hold a list of elements.
+fn enum_code(typename: &str, xml_name: Option<&str>, alternatives: &[Field]) -> String {
+    // The first alternative is the default one
+    let first = &alternatives[0];
+    let unit_default = matches!(first.kind, FieldKind::Struct { unit: true });
+    let variant = |field: &Field| field.xml_name.to_case(Case::Pascal);
+    let repeated = |field: &Field| matches!(field.required, RequiredStatus::Many);
+    let mut out = if unit_default {
+        "#[derive(Default, PartialEq, Clone, Debug)]\n".to_string()
+    } else {
+        "#[derive(PartialEq, Clone, Debug)]\n".to_string()
+    };
+    // Alternatives are whole elements of very different sizes, boxing them would only make
+    // matching on them harder
+    out += "#[allow(clippy::large_enum_variant)]\n";
+    out += &format!("pub enum {} {{\n", typename);
+    for (idx, field) in alternatives.iter().enumerate() {
+        if idx == 0 && unit_default {
+            out += "  #[default]\n";
+        }
+        match field.kind {
+            FieldKind::Struct { unit: true } => out += &format!("  {},\n", variant(field)),
+            _ if repeated(field) => {
+                out += &format!("  {}(Vec<{}>),\n", variant(field), field.storage)
+            }
+            _ => out += &format!("  {}({}),\n", variant(field), field.storage),
+        }
+    }
+    out += "}\n\n";
+
+    if !unit_default {
+        out += &format!(
+            "impl Default for {} {{\n  fn default() -> Self {{\n",
+            typename
+        );
+        out += &format!("    Self::{}(Default::default())\n", variant(first));
+        out += "  }\n}\n\n";
+    }
+
+    out += &format!("impl {} {{\n", typename);
+    out += "  /// Returns the name of the element of the alternative.\n";
+    out += "  pub fn name(&self) -> &'static str {\n    match self {\n";
+    for field in alternatives {
+        let pattern = match field.kind {
+            FieldKind::Struct { unit: true } => variant(field),
+            _ => format!("{}(_)", variant(field)),
+        };
+        out += &format!("      Self::{} => \"{}\",\n", pattern, field.xml_name);
+    }
+    out += "    }\n  }\n\n";
+    out += "  fn read_choice<R: Read>(name: &str, reader: &mut yaserde::de::Deserializer<R>) -> Result<Self, String> {\n";
+    out += "    match name {\n";
+    for field in alternatives {
+        match field.kind {
+            FieldKind::Struct { unit: true } => {
+                out += &format!(
+                    "      \"{}\" => {{\n        field_io::skip_child(reader)?;\n        Ok(Self::{})\n      }}\n",
+                    field.xml_name,
+                    variant(field)
+                )
+            }
+            _ if repeated(field) => {
+                out += &format!(
+                    "      \"{}\" => Ok(Self::{}(vec![field_io::read_child(reader)?])),\n",
+                    field.xml_name,
+                    variant(field)
+                )
+            }
+            _ => {
+                out += &format!(
+                    "      \"{}\" => Ok(Self::{}(field_io::read_child(reader)?)),\n",
+                    field.xml_name,
+                    variant(field)
+                )
+            }
+        }
+    }
+    out += &format!(
+        "      _ => Err(format!(\"<{{}}> is not one of the alternatives of {}\", name)),\n",
+        typename
+    );
+    out += "    }\n  }\n\n";
+    // Repeated alternatives add to the list, any other child is a second alternative
+    out += "  fn add_choice<R: Read>(choice: &mut Option<Self>, name: &str, reader: &mut yaserde::de::Deserializer<R>, element: &str) -> Result<(), String> {\n";
+    for field in alternatives.iter().filter(|field| repeated(field)) {
+        out += &format!(
+            "    if let (Some(Self::{}(values)), \"{}\") = (choice.as_mut(), name) {{\n      values.push(field_io::read_child(reader)?);\n      return Ok(());\n    }}\n",
+            variant(field),
+            field.xml_name
+        );
+    }
+    out += "    field_io::set_choice(choice, Self::read_choice(name, reader)?, element)\n  }\n\n";
+    out += "  fn write_choice<W: Write>(&self, writer: &mut yaserde::ser::Serializer<W>) -> Result<(), String> {\n";
+    out += "    match self {\n";
+    for field in alternatives {
+        match field.kind {
+            FieldKind::Struct { unit: true } => {
+                out += &format!(
+                    "      Self::{} => field_io::write_empty(\"{}\", writer),\n",
+                    variant(field),
+                    field.xml_name
+                )
+            }
+            _ if repeated(field) => {
+                out += &format!(
+                    "      Self::{}(values) => values.iter().try_for_each(|value| field_io::write_child(value, \"{}\", writer)),\n",
+                    variant(field),
+                    field.xml_name
+                )
+            }
+            _ => {
+                out += &format!(
+                    "      Self::{}(value) => field_io::write_child(value, \"{}\", writer),\n",
+                    variant(field),
+                    field.xml_name
+                )
+            }
+        }
+    }
+    out += "    }\n  }\n}\n\n";
+
+    if let Some(xml_name) = xml_name {
+        let names: Vec<_> = alternatives
+            .iter()
+            .map(|field| format!("\"{}\"", field.xml_name))
+            .collect();
+        out += &format!("impl {} {{\n", typename);
+        out += "  /// Deserializes the element along with the content that is not part of the spec, which is\n";
+        out += "  /// returned as an element of the same name holding the unknown attributes and children.\n";
+        out += "  pub(crate) fn read_element<R: Read>(reader: &mut yaserde::de::Deserializer<R>) -> Result<(Self, Option<XmlElement>), String> {\n";
+        out += &format!(
+            "    let attributes = field_io::read_start(reader, \"{}\")?;\n",
+            xml_name
+        );
+        out += "    let mut choice = None;\n    let mut extra = ElementMap::default();\n";
+        out += "    loop {\n      match field_io::next_child(reader)? {\n";
+        out += "        field_io::Next::Child(tag) => match tag.as_str() {\n";
+        out += &format!(
+            "          {} => Self::add_choice(&mut choice, &tag, reader, \"{}\")?,\n",
+            names.join(" | "),
+            xml_name
+        );
+        out += "          _ => extra.push(deserialize_element(reader)?),\n        },\n";
+        out += "        field_io::Next::Text(_) => {}\n";
+        out += "        field_io::Next::End => break,\n      }\n    }\n";
+        out += &format!(
+            "    Ok((choice.unwrap_or_default(), field_io::extension(\"{}\", attributes, extra)))\n",
+            xml_name
+        );
+        out += "  }\n\n";
+        out += "  /// Serializes the element along with the content returned by `read_element`.\n";
+        out += "  pub(crate) fn write_element<W: Write>(&self, extension: Option<&XmlElement>, writer: &mut yaserde::ser::Serializer<W>) -> Result<(), String> {\n";
+        out += &format!(
+            "    let skip = field_io::write_start(writer, \"{}\", &[], extension.map_or(&HashMap::new(), |extension| &extension.attributes))?;\n",
+            xml_name
+        );
+        out += "    self.write_choice(writer)?;\n";
+        out += "    field_io::write_extension_children(extension, writer)?;\n";
+        out += "    field_io::write_end(writer, skip)\n";
+        out += "  }\n}\n\n";
+    }
+
+    out += &format!("impl YaDeserialize for {} {{\n", typename);
+    out += "  fn deserialize<R: Read>(reader: &mut yaserde::de::Deserializer<R>) -> Result<Self, String> {\n";
+    match xml_name {
+        // Unknown content is only kept when the element is read as part of its parent
+        Some(_) => out += "    Ok(Self::read_element(reader)?.0)\n",
+        None => {
+            out += "    match field_io::next_child(reader)? {\n";
+            out += "      field_io::Next::Child(name) => Self::read_choice(&name, reader),\n";
+            out += &format!(
+                "      _ => Err(\"Expected one of the alternatives of {}\".to_string()),\n",
+                typename
+            );
+            out += "    }\n";
+        }
+    }
+    out += "  }\n}\n\n";
+
+    out += &format!("impl YaSerialize for {} {{\n", typename);
+    out += "  fn serialize<W: Write>(&self, writer: &mut yaserde::ser::Serializer<W>) -> Result<(), String> {\n";
+    match xml_name {
+        Some(_) => out += "    self.write_element(None, writer)\n",
+        None => out += "    self.write_choice(writer)\n",
+    }
+    out += "  }\n\n";
+    out += SERIALIZE_ATTRIBUTES;
+    out += "}\n\n";
+    out
+}
+
+const SERIALIZE_ATTRIBUTES: &str = "  fn serialize_attributes(
+    &self,
+    attributes: Vec<OwnedAttribute>,
+    namespace: Namespace,
+  ) -> Result<(Vec<OwnedAttribute>, Namespace), String> {
+    Ok((attributes, namespace))
+  }
+";
+
+/// Spec files refer to other element descriptions by their file name without the extension.
+fn reference_filename(reference: &str) -> String {
+    format!("{}.sdf", reference)
+}
+
 fn parse_element(model: &mut SDFElement, element: &Element) {
     if element.name == "element" {
         // Parse element description
@@ -261,6 +1146,9 @@ fn parse_element(model: &mut SDFElement, element: &Element) {
         if let Some(reference) = element.attributes.get("ref") {
             model.properties.reference = Some(reference.clone());
         }
+        if let Some(copy_data) = element.attributes.get("copy_data") {
+            model.copy_data = copy_data == "true" || copy_data == "1";
+        }
     } else if element.name == "attribute" {
         let mut attr = SDFAttribute::new();
         // Parse element description
@@ -310,9 +1198,10 @@ fn parse_element(model: &mut SDFElement, element: &Element) {
     }
 }
 
-fn read_all_specs() -> Result<HashMap<String, SDFElement>, String> {
+fn read_all_specs(version: &str) -> Result<HashMap<String, SDFElement>, String> {
     let mut res = HashMap::new();
-    for dir_entry in std::fs::read_dir("sdformat_spec/1.10").unwrap().flatten() {
+    let spec_dir = Path::new("sdformat_spec").join(version);
+    for dir_entry in std::fs::read_dir(spec_dir).unwrap().flatten() {
         if !dir_entry.metadata().unwrap().is_file() {
             continue;
         }
@@ -334,24 +1223,101 @@ fn read_all_specs() -> Result<HashMap<String, SDFElement>, String> {
     Ok(res)
 }
 
+/// Generates the rows describing the children and attributes of an element. Scopes are named after
+/// the spec file they are defined in followed by the path of nested elements, e.g. `joint.sdf/axis`.
+fn spec_rows(
+    element: &SDFElement,
+    scope: &str,
+    file_map: &HashMap<String, SDFElement>,
+    out: &mut String,
+) {
+    let row = |name: &str,
+               child_scope: &str,
+               properties: &SDFAttribute,
+               required: RequiredStatus,
+               attribute: bool| {
+        format!(
+            "  SpecRow {{ scope: {:?}, name: {:?}, child_scope: {:?}, rtype: {:?}, default: {:?}, required: {}, attribute: {} }},\n",
+            scope,
+            name,
+            child_scope,
+            properties.rtype,
+            properties.default,
+            matches!(required, RequiredStatus::One),
+            attribute
+        )
+    };
+    for attr in &element.child_attrs {
+        *out += &row(&attr.name, "", attr, attr.required, true);
+    }
+    for child in &element.child_elems {
+        if child.copy_data {
+            continue;
+        }
+        let name = &child.properties.name;
+        let child_scope = match &child.properties.reference {
+            Some(reference) => reference_filename(reference),
+            None => format!("{}/{}", scope, name),
+        };
+        *out += &row(
+            name,
+            &child_scope,
+            &child.properties,
+            child.properties.required,
+            false,
+        );
+        if child.properties.reference.is_none() {
+            spec_rows(child, &child_scope, file_map, out);
+        }
+    }
+    for include in &element.child_includes {
+        if let Some(included) = file_map.get(&include.filename) {
+            *out += &row(
+                &included.properties.name,
+                &include.filename,
+                &included.properties,
+                include.required,
+                false,
+            );
+        }
+    }
+}
+
 fn main() {
-    let hashmap = read_all_specs().unwrap();
+    let hashmap = read_all_specs(SPEC_VERSION).unwrap();
 
     let mut contents = String::new();
-    for (file, model) in &hashmap {
-        if file == "plugin.sdf"
-            || file == "frame.sdf"
-            || file == "geometry.sdf"
-            || file == "params.sdf"
-        {
-            //Skip
-            continue;
-        }
-        contents += &model.code_gen("", &hashmap);
+    for model in hashmap.values() {
+        contents += &model.code_gen("", &model.properties.name, &hashmap);
     }
 
     let out_dir = env::var_os("OUT_DIR").unwrap();
     let dest_path = Path::new(&out_dir).join("sdf.rs");
     fs::write(dest_path, contents).unwrap();
+
+    let mut tables = String::new();
+    let mut versions = String::new();
+    for version in TABLE_VERSIONS {
+        let file_map = read_all_specs(version).unwrap();
+        let ident = format!("SPEC_{}", version.replace('.', "_"));
+        let mut files: Vec<_> = file_map.iter().collect();
+        files.sort_by_key(|(file, _)| file.as_str());
+        tables += &format!("static {}: &[SpecRow] = &[\n", ident);
+        for (file, element) in files {
+            // Rows without a scope list the top level element of each file
+            tables += &format!(
+                "  SpecRow {{ scope: \"\", name: {:?}, child_scope: {:?}, rtype: {:?}, default: {:?}, required: false, attribute: false }},\n",
+                element.properties.name, file, element.properties.rtype, element.properties.default
+            );
+            spec_rows(element, file, &file_map, &mut tables);
+        }
+        tables += "];\n\n";
+        versions += &format!("    ({:?}, {}),\n", version, ident);
+    }
+    tables += &format!(
+        "pub(crate) static SPEC_VERSIONS: &[(&str, &[SpecRow])] = &[\n{}];\n",
+        versions
+    );
+    fs::write(Path::new(&out_dir).join("spec.rs"), tables).unwrap();
     println!("cargo:rerun-if-changed=build.rs");
 }
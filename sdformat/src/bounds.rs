@@ -0,0 +1,398 @@
+//! Bounding boxes of geometries, links, models and worlds.
+//!
+//! Boxes of geometries are computed in the geometry frame and moved through the poses of
+//! visuals or collisions, links and models. Planes are infinite along their surface. Meshes are
+//! bounded by their vertices, given by a loader such as `mesh::load_submesh` with the `mesh`
+//! feature.
+
+use nalgebra::{Isometry3, UnitQuaternion, Vector3};
+
+use crate::frames::{scoped_name, FrameGraph};
+use crate::mesh::TriangleMesh;
+use crate::polyline::extrude;
+use crate::{
+    SdfCollision, SdfGeometry, SdfLink, SdfMeshShape, SdfModel, SdfPose, SdfVisual, SdfWorld,
+};
+
+/// An axis-aligned bounding box. Empty boxes have their minimum above their maximum.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Aabb {
+    pub min: Vector3<f64>,
+    pub max: Vector3<f64>,
+}
+
+impl Default for Aabb {
+    fn default() -> Self {
+        Self::empty()
+    }
+}
+
+impl Aabb {
+    /// Returns a box that contains nothing.
+    pub fn empty() -> Self {
+        Self {
+            min: Vector3::repeat(f64::INFINITY),
+            max: Vector3::repeat(f64::NEG_INFINITY),
+        }
+    }
+
+    /// Returns the smallest box containing the points.
+    pub fn from_points<'a>(points: impl IntoIterator<Item = &'a Vector3<f64>>) -> Self {
+        points.into_iter().fold(Self::empty(), |aabb, point| Self {
+            min: aabb.min.inf(point),
+            max: aabb.max.sup(point),
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        (0..3).any(|axis| self.min[axis] > self.max[axis])
+    }
+
+    /// Returns the smallest box containing both boxes.
+    pub fn merged(&self, other: &Self) -> Self {
+        Self {
+            min: self.min.inf(&other.min),
+            max: self.max.sup(&other.max),
+        }
+    }
+
+    pub fn center(&self) -> Vector3<f64> {
+        (self.min + self.max) / 2.0
+    }
+
+    pub fn size(&self) -> Vector3<f64> {
+        self.max - self.min
+    }
+
+    /// Returns the box containing this box once moved by `pose`, in the frame `pose` is relative
+    /// to.
+    pub fn transformed(&self, pose: &Isometry3<f64>) -> Self {
+        if self.is_empty() {
+            return *self;
+        }
+        let rotation = pose.rotation.to_rotation_matrix();
+        let mut aabb = Self {
+            min: pose.translation.vector,
+            max: pose.translation.vector,
+        };
+        for row in 0..3 {
+            for column in 0..3 {
+                // Skipping null terms keeps infinite extents from turning into NaN
+                let factor = rotation[(row, column)];
+                if factor.abs() < 1e-12 {
+                    continue;
+                }
+                let (low, high) = (factor * self.min[column], factor * self.max[column]);
+                aabb.min[row] += low.min(high);
+                aabb.max[row] += low.max(high);
+            }
+        }
+        aabb
+    }
+}
+
+/// An oriented bounding box, centered on the origin of its pose.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Obb {
+    pub pose: Isometry3<f64>,
+    pub half_extents: Vector3<f64>,
+}
+
+impl Obb {
+    /// Returns the box with the orientation of the frame of `aabb`, placed by `pose`.
+    pub fn from_aabb(aabb: &Aabb, pose: &Isometry3<f64>) -> Self {
+        if aabb.is_empty() {
+            return Self {
+                pose: *pose,
+                half_extents: Vector3::zeros(),
+            };
+        }
+        // The center of infinite extents is taken at the origin
+        let center = Vector3::from_fn(|axis, _| match aabb.center()[axis] {
+            center if center.is_finite() => center,
+            _ => 0.0,
+        });
+        Self {
+            pose: pose * Isometry3::translation(center.x, center.y, center.z),
+            half_extents: aabb.size() / 2.0,
+        }
+    }
+
+    /// Returns the box moved by `pose`.
+    pub fn transformed(&self, pose: &Isometry3<f64>) -> Self {
+        Self {
+            pose: pose * self.pose,
+            half_extents: self.half_extents,
+        }
+    }
+
+    /// Returns the box containing this one, aligned with the frame its pose is relative to.
+    pub fn aabb(&self) -> Aabb {
+        Aabb {
+            min: -self.half_extents,
+            max: self.half_extents,
+        }
+        .transformed(&self.pose)
+    }
+}
+
+/// The geometries bounding boxes are computed from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Shapes {
+    Collisions,
+    Visuals,
+}
+
+impl SdfGeometry {
+    /// Returns the box bounding the geometry in its frame, a box of null size at the origin for
+    /// empty geometries. `load` returns the surface of mesh
+    /// geometries before their scale is applied.
+    pub fn obb(
+        &self,
+        load: &mut dyn FnMut(&SdfMeshShape) -> Result<TriangleMesh, String>,
+    ) -> Result<Obb, String> {
+        let centered = |half_extents: Vector3<f64>| Obb {
+            pose: Isometry3::identity(),
+            half_extents,
+        };
+        match self {
+            SdfGeometry::Empty => Ok(Obb::from_aabb(&Aabb::empty(), &Isometry3::identity())),
+            SdfGeometry::Box(shape) => Ok(centered(shape.size.0 / 2.0)),
+            SdfGeometry::Cylinder(shape) => Ok(centered(Vector3::new(
+                shape.radius,
+                shape.radius,
+                shape.length / 2.0,
+            ))),
+            SdfGeometry::Sphere(shape) => Ok(centered(Vector3::repeat(shape.radius))),
+            SdfGeometry::Capsule(shape) => Ok(centered(Vector3::new(
+                shape.radius,
+                shape.radius,
+                shape.length / 2.0 + shape.radius,
+            ))),
+            SdfGeometry::Ellipsoid(shape) => Ok(centered(shape.radii.0)),
+            SdfGeometry::Plane(shape) => {
+                let rotation = UnitQuaternion::rotation_between(&Vector3::z(), &shape.normal.0)
+                    .unwrap_or_else(|| {
+                        UnitQuaternion::from_euler_angles(std::f64::consts::PI, 0.0, 0.0)
+                    });
+                Ok(Obb {
+                    pose: Isometry3::from_parts(Default::default(), rotation),
+                    half_extents: Vector3::new(f64::INFINITY, f64::INFINITY, 0.0),
+                })
+            }
+            SdfGeometry::Polyline(shapes) => Ok(Obb::from_aabb(
+                &Aabb::from_points(extrude(shapes)?.vertices.iter()),
+                &Isometry3::identity(),
+            )),
+            SdfGeometry::Heightmap(shape) => {
+                let size = shape
+                    .size
+                    .as_ref()
+                    .map_or(Vector3::repeat(1.0), |size| size.0);
+                let pos = shape.pos.as_ref().map_or(Vector3::zeros(), |pos| pos.0);
+                let aabb = Aabb {
+                    min: pos - Vector3::new(size.x / 2.0, size.y / 2.0, 0.0),
+                    max: pos + Vector3::new(size.x / 2.0, size.y / 2.0, size.z),
+                };
+                Ok(Obb::from_aabb(&aabb, &Isometry3::identity()))
+            }
+            SdfGeometry::Mesh(shape) => {
+                let mut mesh = load(shape)?;
+                if let Some(scale) = shape.scale.as_ref() {
+                    mesh = mesh.scaled(&scale.0);
+                }
+                Ok(Obb::from_aabb(
+                    &Aabb::from_points(mesh.vertices.iter()),
+                    &Isometry3::identity(),
+                ))
+            }
+            SdfGeometry::Image(shape) => Err(format!(
+                "Bounds of image geometries are not supported, found {}",
+                shape.uri
+            )),
+        }
+    }
+
+    /// Returns the axis-aligned box bounding the geometry in its frame, empty geometries have
+    /// an empty box.
+    pub fn aabb(
+        &self,
+        load: &mut dyn FnMut(&SdfMeshShape) -> Result<TriangleMesh, String>,
+    ) -> Result<Aabb, String> {
+        match self {
+            SdfGeometry::Empty => Ok(Aabb::empty()),
+            geometry => Ok(geometry.obb(load)?.aabb()),
+        }
+    }
+}
+
+fn local_pose(pose: Option<&SdfPose>) -> Result<Isometry3<f64>, String> {
+    match pose {
+        Some(pose) => Ok(pose.get_pose()?.isometry()),
+        None => Ok(Isometry3::identity()),
+    }
+}
+
+impl SdfCollision {
+    /// Returns the box bounding the geometry in the frame the pose of the collision is relative
+    /// to, usually its link.
+    pub fn obb(
+        &self,
+        load: &mut dyn FnMut(&SdfMeshShape) -> Result<TriangleMesh, String>,
+    ) -> Result<Obb, String> {
+        Ok(self
+            .geometry
+            .obb(load)?
+            .transformed(&local_pose(self.pose.as_ref())?))
+    }
+}
+
+impl SdfVisual {
+    /// Returns the box bounding the geometry in the frame the pose of the visual is relative
+    /// to, usually its link.
+    pub fn obb(
+        &self,
+        load: &mut dyn FnMut(&SdfMeshShape) -> Result<TriangleMesh, String>,
+    ) -> Result<Obb, String> {
+        Ok(self
+            .geometry
+            .obb(load)?
+            .transformed(&local_pose(self.pose.as_ref())?))
+    }
+}
+
+/// Merges the bounds of the shapes of a link into `aabb`, in the frame whose pose in the root
+/// frame of `graph` is the inverse of `root`. `scope` is the scope of the model of the link in the
+/// graph.
+fn add_link(
+    link: &SdfLink,
+    scope: &str,
+    graph: &FrameGraph,
+    root: &Isometry3<f64>,
+    shapes: Shapes,
+    load: &mut dyn FnMut(&SdfMeshShape) -> Result<TriangleMesh, String>,
+    aabb: &mut Aabb,
+) -> Result<(), String> {
+    let name = scoped_name(scope, &link.name);
+    let elements: Vec<_> = match shapes {
+        Shapes::Collisions => link
+            .collision
+            .iter()
+            .map(|collision| (collision.pose.as_ref(), &collision.geometry))
+            .collect(),
+        Shapes::Visuals => link
+            .visual
+            .iter()
+            .map(|visual| (visual.pose.as_ref(), &visual.geometry))
+            .collect(),
+    };
+    for (pose, geometry) in elements {
+        if matches!(geometry, SdfGeometry::Empty) {
+            continue;
+        }
+        let pose = root * graph.resolve_pose(pose, scope, &name)?;
+        *aabb = aabb.merged(&geometry.obb(load)?.transformed(&pose).aabb());
+    }
+    Ok(())
+}
+
+/// Merges the bounds of the shapes of a model and of its nested models into `aabb`.
+fn add_model(
+    model: &SdfModel,
+    scope: &str,
+    graph: &FrameGraph,
+    shapes: Shapes,
+    load: &mut dyn FnMut(&SdfMeshShape) -> Result<TriangleMesh, String>,
+    aabb: &mut Aabb,
+) -> Result<(), String> {
+    for link in model.link.iter() {
+        add_link(
+            link,
+            scope,
+            graph,
+            &Isometry3::identity(),
+            shapes,
+            load,
+            aabb,
+        )?;
+    }
+    for nested in model.model.iter() {
+        add_model(
+            nested,
+            &scoped_name(scope, &nested.name),
+            graph,
+            shapes,
+            load,
+            aabb,
+        )?;
+    }
+    Ok(())
+}
+
+/// Returns the box bounding the shapes of a link, in the link frame. `link` is the scoped name
+/// of a link of the model.
+pub fn link_aabb(
+    model: &SdfModel,
+    link: &str,
+    shapes: Shapes,
+    mut load: impl FnMut(&SdfMeshShape) -> Result<TriangleMesh, String>,
+) -> Result<Aabb, String> {
+    let not_found = || format!("Link {} not found", link);
+    let (scope, name) = link.rsplit_once("::").unwrap_or(("", link));
+    let mut parent = model;
+    for segment in scope.split("::").filter(|segment| !segment.is_empty()) {
+        parent = parent
+            .model
+            .iter()
+            .find(|nested| nested.name == segment)
+            .ok_or_else(not_found)?;
+    }
+    let element = parent
+        .link
+        .iter()
+        .find(|element| element.name == name)
+        .ok_or_else(not_found)?;
+    let graph = FrameGraph::from_model(model)?;
+    // Shapes are placed in the link frame before their boxes are aligned with it
+    let root = graph.pose_in_root(link)?.inverse();
+    let mut aabb = Aabb::empty();
+    add_link(element, scope, &graph, &root, shapes, &mut load, &mut aabb)?;
+    Ok(aabb)
+}
+
+/// Returns the box bounding the shapes of a model and of its nested models, in the model frame.
+pub fn model_aabb(
+    model: &SdfModel,
+    shapes: Shapes,
+    mut load: impl FnMut(&SdfMeshShape) -> Result<TriangleMesh, String>,
+) -> Result<Aabb, String> {
+    let graph = FrameGraph::from_model(model)?;
+    let mut aabb = Aabb::empty();
+    add_model(model, "", &graph, shapes, &mut load, &mut aabb)?;
+    Ok(aabb)
+}
+
+/// Returns the box bounding the shapes of a model, aligned with the model frame and placed by
+/// the pose of the model.
+pub fn model_obb(
+    model: &SdfModel,
+    shapes: Shapes,
+    load: impl FnMut(&SdfMeshShape) -> Result<TriangleMesh, String>,
+) -> Result<Obb, String> {
+    let aabb = model_aabb(model, shapes, load)?;
+    Ok(Obb::from_aabb(&aabb, &local_pose(model.pose.as_ref())?))
+}
+
+/// Returns the box bounding the shapes of all the models of a world, in the world frame.
+pub fn world_aabb(
+    world: &SdfWorld,
+    shapes: Shapes,
+    mut load: impl FnMut(&SdfMeshShape) -> Result<TriangleMesh, String>,
+) -> Result<Aabb, String> {
+    let graph = FrameGraph::from_world(world)?;
+    let mut aabb = Aabb::empty();
+    for model in world.model.iter() {
+        add_model(model, &model.name, &graph, shapes, &mut load, &mut aabb)?;
+    }
+    Ok(aabb)
+}
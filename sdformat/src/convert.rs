@@ -0,0 +1,475 @@
+//! Conversion of documents to older versions of the specification.
+//!
+//! Elements and attributes the target version does not know about are removed and reported, as
+//! are constructs whose meaning would change such as poses relative to other frames. Some of them
+//! can be resolved instead by computing the equivalent poses, see [`ConvertOptions::flatten`].
+//! Documents of SDFormat 1.7 and older converted to a newer version have the nested models of
+//! their worlds rebuilt from scoped names, see [`crate::flatten::unflatten`].
+
+use nalgebra::Isometry3;
+
+use crate::cst::{same_value, Document, Element};
+use crate::flatten::unflatten_world;
+use crate::frames::{scoped_name, FrameGraph, FrameKind};
+use crate::spec::{Spec, SpecRow};
+use crate::writer::{write_root, WriteOptions};
+use crate::{SdfModel, SdfPose, SdfRoot};
+
+/// Options for [`to_version_with_options`].
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ConvertOptions {
+    /// Replace poses relative to other frames and references to explicit frames with poses
+    /// expressed in the frames the target version uses by default.
+    pub flatten: bool,
+    pub write: WriteOptions,
+}
+
+/// A construct that could not be represented as is in the target version.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ConversionIssue {
+    /// The location of the construct, e.g. `/sdf/model[robot]/link[base]/pose`.
+    pub path: String,
+    pub message: String,
+    /// Whether an equivalent construct was written instead. Unresolved constructs were dropped.
+    pub resolved: bool,
+}
+
+/// The result of a conversion.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Converted {
+    pub output: String,
+    pub issues: Vec<ConversionIssue>,
+}
+
+/// Serializes a document for an older version of the specification, e.g. `"1.6"`.
+pub fn to_version(root: &SdfRoot, version: &str) -> Result<Converted, String> {
+    to_version_with_options(root, version, &ConvertOptions::default())
+}
+
+/// Serializes a document for an older version of the specification. Documents read from
+/// SDFormat 1.7 and older can also be written for newer versions.
+pub fn to_version_with_options(
+    root: &SdfRoot,
+    version: &str,
+    options: &ConvertOptions,
+) -> Result<Converted, String> {
+    let target =
+        Spec::get(version).ok_or_else(|| format!("SDFormat {} is not supported", version))?;
+    let source = Spec::latest();
+    let mut converter = Converter {
+        source,
+        target,
+        flatten: options.flatten,
+        issues: vec![],
+    };
+
+    let mut root = root.clone();
+    if older(&root.version, "1.8") && !older(version, "1.8") {
+        converter.unflatten(&mut root)?;
+    }
+    root.version = version.to_string();
+    converter.convert_frames(&mut root)?;
+
+    let mut document = Document::parse(&yaserde::ser::to_string(&root)?)?;
+    let element = document.root_mut();
+    let row = source.root(element.name());
+    let path = format!("/{}", element.name());
+    converter.convert_element(element, row, row, &path);
+
+    Ok(Converted {
+        output: write_root(document.root(), &options.write),
+        issues: converter.issues,
+    })
+}
+
+struct Converter {
+    source: &'static Spec,
+    target: &'static Spec,
+    flatten: bool,
+    issues: Vec<ConversionIssue>,
+}
+
+/// Returns true if `version` is older than `than`, both in the `major.minor` form.
+fn older(version: &str, than: &str) -> bool {
+    let numbers = |version: &str| -> Vec<u32> {
+        version
+            .split('.')
+            .map(|number| number.parse().unwrap_or(0))
+            .collect()
+    };
+    numbers(version) < numbers(than)
+}
+
+/// Returns the path of a child, named elements are identified by their name.
+fn child_path(path: &str, element: &Element) -> String {
+    match element.attribute("name") {
+        Some(name) => format!("{}/{}[{}]", path, element.name(), name),
+        None => format!("{}/{}", path, element.name()),
+    }
+}
+
+fn relative_to(pose: &Option<SdfPose>) -> Option<String> {
+    pose.as_ref()
+        .and_then(|pose| pose.relative_to.clone())
+        .filter(|relative_to| !relative_to.is_empty())
+}
+
+/// Returns the name of a frame as seen from inside the model whose frame is `scope`.
+fn local_name<'a>(scope: &str, frame: &'a str) -> &'a str {
+    if scope.is_empty() {
+        frame
+    } else {
+        frame
+            .strip_prefix(scope)
+            .and_then(|name| name.strip_prefix("::"))
+            .unwrap_or(frame)
+    }
+}
+
+impl Converter {
+    fn report(&mut self, path: &str, message: String, resolved: bool) {
+        self.issues.push(ConversionIssue {
+            path: path.to_string(),
+            message,
+            resolved,
+        });
+    }
+
+    /// Rebuilds the nested models of worlds that older versions encode with scoped names, as the
+    /// `<unflatten/>` rule of `1_7.convert` does.
+    fn unflatten(&mut self, root: &mut SdfRoot) -> Result<(), String> {
+        for world in root.world.iter_mut() {
+            let unflattened = unflatten_world(world)?;
+            for (before, after) in world.model.iter().zip(unflattened.model.iter()) {
+                if before.model.len() != after.model.len() {
+                    self.report(
+                        &format!("/sdf/world[{}]/model[{}]", world.name, after.name),
+                        "Nested models were rebuilt from scoped names".to_string(),
+                        true,
+                    );
+                }
+            }
+            *world = unflattened;
+        }
+        Ok(())
+    }
+
+    /// Handles the constructs that need the frame semantics of the document, only needed when
+    /// the target version has no `relative_to` attribute.
+    fn convert_frames(&mut self, root: &mut SdfRoot) -> Result<(), String> {
+        if self.target.attribute("pose.sdf", "relative_to").is_some() {
+            return Ok(());
+        }
+        for world in root.world.iter_mut() {
+            let graph = FrameGraph::from_world(world)?;
+            let path = format!("/sdf/world[{}]", world.name);
+            for model in world.model.iter_mut() {
+                let model_path = format!("{}/model[{}]", path, model.name);
+                let name = model.name.clone();
+                self.pose(&mut model.pose, &model_path, || graph.pose(&name, "world"))?;
+                self.model_contents(model, &graph, &name, &model_path)?;
+            }
+            for light in world.light.iter_mut() {
+                let light_path = format!("{}/light[{}]", path, light.name);
+                let resolved = graph.resolve_pose(light.pose.as_ref(), "", "world");
+                self.pose(&mut light.pose, &light_path, || resolved)?;
+            }
+            self.remove_frames(&mut world.frame, &path);
+        }
+        if let Some(model) = root.model.as_mut() {
+            let graph = FrameGraph::from_model(model)?;
+            let path = format!("/sdf/model[{}]", model.name);
+            self.model_contents(model, &graph, "", &path)?;
+        }
+        Ok(())
+    }
+
+    /// Reports, and resolves when flattening, a pose relative to another frame. `resolved`
+    /// computes the pose in the frame the target version uses by default.
+    fn pose<F>(&mut self, pose: &mut Option<SdfPose>, path: &str, resolved: F) -> Result<(), String>
+    where
+        F: FnOnce() -> Result<Isometry3<f64>, String>,
+    {
+        let Some(frame) = relative_to(pose) else {
+            return Ok(());
+        };
+        let path = format!("{}/pose", path);
+        if self.flatten {
+            *pose = Some(SdfPose::from_isometry(&resolved()?, None));
+            self.report(
+                &path,
+                format!("Pose relative to `{}` was resolved", frame),
+                true,
+            );
+        } else {
+            if let Some(pose) = pose.as_mut() {
+                pose.relative_to = None;
+            }
+            self.report(
+                &path,
+                format!(
+                    "Poses relative to other frames such as `{}` are not supported by SDFormat {}",
+                    frame, self.target.version
+                ),
+                false,
+            );
+        }
+        Ok(())
+    }
+
+    fn remove_frames<T>(&mut self, frames: &mut Vec<T>, path: &str) {
+        if frames.is_empty() || self.target.attribute("frame.sdf", "attached_to").is_some() {
+            return;
+        }
+        let message = match self.flatten {
+            true => "Explicit frames were removed, the poses referring to them were resolved",
+            false => "Explicit frames are not supported",
+        };
+        let message = format!("{} ({} removed)", message, frames.len());
+        self.report(path, message, self.flatten);
+        frames.clear();
+    }
+
+    /// Converts the contents of a model whose frame is `scope` in the graph.
+    fn model_contents(
+        &mut self,
+        model: &mut SdfModel,
+        graph: &FrameGraph,
+        scope: &str,
+        path: &str,
+    ) -> Result<(), String> {
+        let model_frame = if scope.is_empty() { "__model__" } else { scope };
+        for link in model.link.iter_mut() {
+            let frame = scoped_name(scope, &link.name);
+            let path = format!("{}/link[{}]", path, link.name);
+            self.pose(&mut link.pose, &path, || graph.pose(&frame, model_frame))?;
+
+            let link_pose = graph.pose_in_root(&frame)?;
+            let in_link = |pose: &Option<SdfPose>| -> Result<Isometry3<f64>, String> {
+                Ok(link_pose.inverse() * graph.resolve_pose(pose.as_ref(), scope, &frame)?)
+            };
+            for visual in link.visual.iter_mut() {
+                let path = format!("{}/visual[{}]", path, visual.name);
+                let resolved = in_link(&visual.pose);
+                self.pose(&mut visual.pose, &path, || resolved)?;
+            }
+            for collision in link.collision.iter_mut() {
+                let path = format!("{}/collision[{}]", path, collision.name);
+                let resolved = in_link(&collision.pose);
+                self.pose(&mut collision.pose, &path, || resolved)?;
+            }
+            for sensor in link.sensor.iter_mut() {
+                let path = format!("{}/sensor[{}]", path, sensor.name);
+                let resolved = in_link(&sensor.pose);
+                self.pose(&mut sensor.pose, &path, || resolved)?;
+            }
+            for light in link.light.iter_mut() {
+                let path = format!("{}/light[{}]", path, light.name);
+                let resolved = in_link(&light.pose);
+                self.pose(&mut light.pose, &path, || resolved)?;
+            }
+        }
+
+        for joint in model.joint.iter_mut() {
+            let frame = scoped_name(scope, &joint.name);
+            let path = format!("{}/joint[{}]", path, joint.name);
+            let mut moved = false;
+            for (role, body) in [("parent", &mut joint.parent), ("child", &mut joint.child)] {
+                let target = match body.as_str() {
+                    "world" => continue,
+                    "__model__" => model_frame.to_string(),
+                    body => scoped_name(scope, body),
+                };
+                if !matches!(
+                    graph.kind(&target),
+                    Some(FrameKind::Frame | FrameKind::Model)
+                ) {
+                    continue;
+                }
+                if self.flatten {
+                    let link = graph.attached_link(&target)?;
+                    let message = format!(
+                        "The {} `{}` of the joint was replaced by the link it is attached to",
+                        role, body
+                    );
+                    *body = local_name(scope, &link).to_string();
+                    moved |= role == "child";
+                    self.report(&path, message, true);
+                } else {
+                    self.report(
+                        &path,
+                        format!("The {} `{}` of the joint is not a link", role, body),
+                        false,
+                    );
+                }
+            }
+            for body in [&joint.parent, &joint.child] {
+                if body.contains("::") {
+                    self.report(
+                        &path,
+                        format!(
+                            "Joints to links of nested models such as `{}` are not supported",
+                            body
+                        ),
+                        false,
+                    );
+                }
+            }
+
+            // The pose of a joint is relative to its child link
+            let child = scoped_name(scope, &joint.child);
+            if moved && relative_to(&joint.pose).is_none() {
+                let pose = graph.pose(&frame, &child)?;
+                joint.pose = Some(SdfPose::from_isometry(&pose, None));
+            }
+            self.pose(&mut joint.pose, &path, || graph.pose(&frame, &child))?;
+        }
+
+        for nested in model.model.iter_mut() {
+            let frame = scoped_name(scope, &nested.name);
+            let path = format!("{}/model[{}]", path, nested.name);
+            self.pose(&mut nested.pose, &path, || graph.pose(&frame, model_frame))?;
+            self.model_contents(nested, graph, &frame, &path)?;
+        }
+
+        self.remove_frames(&mut model.frame, path);
+        Ok(())
+    }
+
+    /// Removes the contents of an element that the target version does not describe.
+    /// `source_row` and `target_row` describe the element in both versions.
+    fn convert_element(
+        &mut self,
+        element: &mut Element,
+        source_row: Option<&'static SpecRow>,
+        target_row: Option<&'static SpecRow>,
+        path: &str,
+    ) {
+        let (Some(source_row), Some(target_row)) = (source_row, target_row) else {
+            return;
+        };
+        let source_scope = source_row.child_scope;
+        let target_scope = target_row.child_scope;
+
+        if source_scope == "pose.sdf" {
+            self.convert_pose(element, path);
+        }
+
+        let names: Vec<String> = element.attribute_names().map(str::to_string).collect();
+        for name in names {
+            // Namespaced attributes are extensions and are kept as they are
+            if name.contains(':') || name == "xmlns" {
+                continue;
+            }
+            let Some(row) = self.source.attribute(source_scope, &name) else {
+                continue;
+            };
+            if self.target.attribute(target_scope, &name).is_some() {
+                continue;
+            }
+            let value = element.remove_attribute(&name).unwrap_or_default();
+            if row
+                .default
+                .is_some_and(|default| same_value(default, &value))
+            {
+                continue;
+            }
+            self.report(
+                path,
+                format!(
+                    "Attribute `{}` is not supported by SDFormat {}",
+                    name, self.target.version
+                ),
+                false,
+            );
+        }
+
+        let mut removed = vec![];
+        element.retain_elements(|child| {
+            if child.name().contains(':')
+                || self.source.element(source_scope, child.name()).is_none()
+            {
+                return true;
+            }
+            let keep = self.target.element(target_scope, child.name()).is_some();
+            if !keep {
+                removed.push(child_path(path, child));
+            }
+            keep
+        });
+        for path in removed.into_iter().rev() {
+            self.report(
+                &path,
+                format!("Not supported by SDFormat {}", self.target.version),
+                false,
+            );
+        }
+
+        for child in element.elements_mut() {
+            let child_path = child_path(path, child);
+            let source_row = self.source.element(source_scope, child.name());
+            let target_row = self.target.element(target_scope, child.name());
+            self.convert_element(child, source_row, target_row, &child_path);
+        }
+
+        // Elements that became required are added with their default value
+        for row in self.target.children(target_scope) {
+            let Some(default) = row.default else {
+                continue;
+            };
+            if row.attribute || !row.required || element.child(row.name).is_some() {
+                continue;
+            }
+            if self
+                .source
+                .element(source_scope, row.name)
+                .is_some_and(|row| row.required)
+            {
+                continue;
+            }
+            let mut child = Element::new(row.name);
+            child.set_text(default);
+            element.push_element(child);
+        }
+    }
+
+    /// Writes poses using Euler angles in radians when the target version has no other formats.
+    fn convert_pose(&mut self, element: &mut Element, path: &str) {
+        let rotation_format = element.attribute("rotation_format");
+        let degrees = element.attribute("degrees");
+        let supports_format = self
+            .target
+            .attribute("pose.sdf", "rotation_format")
+            .is_some();
+        let supports_degrees = self.target.attribute("pose.sdf", "degrees").is_some();
+        let in_format = supports_format
+            || rotation_format
+                .as_deref()
+                .is_none_or(|format| format == "euler_rpy");
+        let in_degrees =
+            supports_degrees || !degrees.as_deref().is_some_and(|d| same_value(d, "true"));
+        if in_format && in_degrees {
+            return;
+        }
+        let pose = SdfPose {
+            data: element.text(),
+            rotation_format,
+            degrees: degrees.map(|degrees| same_value(&degrees, "true")),
+            ..Default::default()
+        };
+        match pose.get_pose() {
+            Ok(pose) => {
+                let converted = SdfPose::from_isometry(&pose.isometry(), None);
+                element.set_text(&converted.data);
+                element.remove_attribute("rotation_format");
+                element.remove_attribute("degrees");
+                self.report(
+                    path,
+                    "Rotation converted to Euler angles in radians".to_string(),
+                    true,
+                );
+            }
+            Err(err) => self.report(path, err, false),
+        }
+    }
+}
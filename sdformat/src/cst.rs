@@ -0,0 +1,749 @@
+//! Lossless representation of an SDFormat file.
+//!
+//! A [`Document`] keeps the exact text it was parsed from: comments, whitespace, attribute order
+//! and quoting are written back untouched. Edits only rewrite the parts of the file they change,
+//! either through the [`Element`] API or by applying a modified typed struct with
+//! [`Document::update`].
+//!
+//! ```
+//! use sdformat::cst::Document;
+//! use sdformat::SdfModel;
+//!
+//! let source = "<model name=\"box\">\n  <!-- Keep me -->\n  <pose>0 0 0 0 0 0</pose>\n</model>";
+//! let mut document = Document::parse(source).unwrap();
+//! let mut model: SdfModel = document.to_typed().unwrap();
+//! model.pose.as_mut().unwrap().data = "1 0 0 0 0 0".to_string();
+//! document.update(&model).unwrap();
+//! assert_eq!(
+//!     document.to_string(),
+//!     "<model name=\"box\">\n  <!-- Keep me -->\n  <pose>1 0 0 0 0 0</pose>\n</model>"
+//! );
+//! ```
+
+use std::collections::HashSet;
+use std::fmt;
+
+use yaserde::{YaDeserialize, YaSerialize};
+
+use crate::spec::Spec;
+
+/// A node of the document tree.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Node {
+    Element(Element),
+    /// Text, including whitespace, as it appears in the file (entities are not decoded).
+    Text(String),
+    /// A comment including its `<!--` and `-->` delimiters.
+    Comment(String),
+    /// A CDATA section including its delimiters.
+    CData(String),
+    /// A processing instruction or XML declaration including its delimiters.
+    ProcessingInstruction(String),
+    /// A document type declaration including its delimiters.
+    Doctype(String),
+}
+
+impl Node {
+    fn is_whitespace(&self) -> bool {
+        matches!(self, Node::Text(text) if text.trim().is_empty())
+    }
+
+    fn write(&self, out: &mut String) {
+        match self {
+            Node::Element(element) => element.write(out),
+            Node::Text(raw)
+            | Node::Comment(raw)
+            | Node::CData(raw)
+            | Node::ProcessingInstruction(raw)
+            | Node::Doctype(raw) => out.push_str(raw),
+        }
+    }
+}
+
+/// An attribute along with the formatting surrounding it.
+#[derive(Clone, Debug, PartialEq)]
+struct Attribute {
+    /// Whitespace preceding the name.
+    leading: String,
+    name: String,
+    /// Everything between the name and the opening quote, usually `=`.
+    equals: String,
+    quote: char,
+    /// The value as it appears in the file.
+    raw_value: String,
+}
+
+/// An element of the document.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Element {
+    name: String,
+    attributes: Vec<Attribute>,
+    /// Whitespace between the last attribute and the end of the start tag.
+    tag_tail: String,
+    self_closing: bool,
+    children: Vec<Node>,
+    /// The end tag as it appears in the file.
+    end_tag: String,
+}
+
+impl Element {
+    /// Creates an empty element.
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            attributes: vec![],
+            tag_tail: "".to_string(),
+            self_closing: true,
+            children: vec![],
+            end_tag: "".to_string(),
+        }
+    }
+
+    /// Returns the name of the element, including its namespace prefix.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the value of an attribute.
+    pub fn attribute(&self, name: &str) -> Option<String> {
+        self.attributes
+            .iter()
+            .find(|attribute| attribute.name == name)
+            .map(|attribute| unescape(&attribute.raw_value))
+    }
+
+    /// Returns the names of the attributes in the order they appear in the file.
+    pub fn attribute_names(&self) -> impl Iterator<Item = &str> {
+        self.attributes
+            .iter()
+            .map(|attribute| attribute.name.as_str())
+    }
+
+    /// Sets the value of an attribute. Existing attributes keep their position and quoting, new
+    /// ones are added after the others.
+    pub fn set_attribute(&mut self, name: &str, value: &str) {
+        if let Some(attribute) = self
+            .attributes
+            .iter_mut()
+            .find(|attribute| attribute.name == name)
+        {
+            attribute.raw_value = escape(value, Some(attribute.quote));
+            return;
+        }
+        self.attributes.push(Attribute {
+            leading: " ".to_string(),
+            name: name.to_string(),
+            equals: "=".to_string(),
+            quote: '"',
+            raw_value: escape(value, Some('"')),
+        });
+    }
+
+    /// Removes an attribute, returning its value.
+    pub fn remove_attribute(&mut self, name: &str) -> Option<String> {
+        let idx = self
+            .attributes
+            .iter()
+            .position(|attribute| attribute.name == name)?;
+        Some(unescape(&self.attributes.remove(idx).raw_value))
+    }
+
+    /// Returns all the nodes contained by this element.
+    pub fn children(&self) -> &[Node] {
+        &self.children
+    }
+
+    /// Iterates over the child elements.
+    pub fn elements(&self) -> impl Iterator<Item = &Element> {
+        self.children.iter().filter_map(|node| match node {
+            Node::Element(element) => Some(element),
+            _ => None,
+        })
+    }
+
+    /// Iterates mutably over the child elements.
+    pub fn elements_mut(&mut self) -> impl Iterator<Item = &mut Element> {
+        self.children.iter_mut().filter_map(|node| match node {
+            Node::Element(element) => Some(element),
+            _ => None,
+        })
+    }
+
+    /// Returns the first child element with the given name.
+    pub fn child(&self, name: &str) -> Option<&Element> {
+        self.elements().find(|element| element.name == name)
+    }
+
+    /// Returns the first child element with the given name, mutably.
+    pub fn child_mut(&mut self, name: &str) -> Option<&mut Element> {
+        self.elements_mut().find(|element| element.name == name)
+    }
+
+    /// Returns the text directly contained by the element, with entities decoded.
+    pub fn text(&self) -> String {
+        let mut text = String::new();
+        for node in self.children.iter() {
+            match node {
+                Node::Text(raw) => text += &unescape(raw),
+                Node::CData(raw) => text += &raw[9..raw.len() - 3],
+                _ => {}
+            }
+        }
+        text
+    }
+
+    /// Replaces all the text directly contained by the element, keeping comments and child
+    /// elements.
+    pub fn set_text(&mut self, text: &str) {
+        let position = self
+            .children
+            .iter()
+            .position(|node| matches!(node, Node::Text(_) | Node::CData(_)))
+            .unwrap_or(self.children.len());
+        self.children
+            .retain(|node| !matches!(node, Node::Text(_) | Node::CData(_)));
+        let position = position.min(self.children.len());
+        self.children
+            .insert(position, Node::Text(escape(text, None)));
+        self.open();
+    }
+
+    /// Appends a child element using the same indentation as its siblings.
+    pub fn push_element(&mut self, element: Element) {
+        let at = self.closing_position();
+        let indent = self.child_indent();
+        self.insert_with_indent(at, indent, element);
+    }
+
+    /// Removes the first child element with the given name along with the whitespace preceding
+    /// it.
+    pub fn remove_element(&mut self, name: &str) -> Option<Element> {
+        let idx = self
+            .children
+            .iter()
+            .position(|node| matches!(node, Node::Element(element) if element.name == name))?;
+        Some(self.remove_node(idx))
+    }
+
+    /// Keeps only the child elements for which the predicate returns true, removed elements take
+    /// the whitespace preceding them along.
+    pub fn retain_elements<F: FnMut(&Element) -> bool>(&mut self, mut keep: F) {
+        let mut idx = self.children.len();
+        while idx > 0 {
+            idx -= 1;
+            if matches!(&self.children[idx], Node::Element(element) if !keep(element)) {
+                let len = self.children.len();
+                self.remove_node(idx);
+                // The whitespace preceding the element was removed as well
+                if self.children.len() + 2 == len {
+                    idx -= 1;
+                }
+            }
+        }
+    }
+
+    fn remove_node(&mut self, idx: usize) -> Element {
+        let Node::Element(element) = self.children.remove(idx) else {
+            unreachable!("Only elements are removed");
+        };
+        if idx > 0 && self.children[idx - 1].is_whitespace() {
+            self.children.remove(idx - 1);
+        }
+        element
+    }
+
+    /// Makes sure the element has an end tag so children can be added.
+    fn open(&mut self) {
+        if self.self_closing {
+            self.self_closing = false;
+            self.end_tag = format!("</{}>", self.name);
+        }
+    }
+
+    /// The whitespace used before child elements.
+    fn child_indent(&self) -> Option<String> {
+        let mut previous: Option<&Node> = None;
+        for node in self.children.iter() {
+            match node {
+                Node::Element(_) => {
+                    if let Some(Node::Text(text)) = previous {
+                        if text.trim().is_empty() {
+                            return Some(text.clone());
+                        }
+                    }
+                    return None;
+                }
+                node => previous = Some(node),
+            }
+        }
+        None
+    }
+
+    /// Where elements are appended: before the whitespace preceding the end tag.
+    fn closing_position(&self) -> usize {
+        match self.children.last() {
+            Some(node) if node.is_whitespace() => self.children.len() - 1,
+            _ => self.children.len(),
+        }
+    }
+
+    fn insert_with_indent(&mut self, at: usize, indent: Option<String>, element: Element) {
+        self.open();
+        self.children.insert(at, Node::Element(element));
+        if let Some(indent) = indent {
+            self.children.insert(at, Node::Text(indent));
+        }
+    }
+
+    fn write(&self, out: &mut String) {
+        out.push('<');
+        out.push_str(&self.name);
+        for attribute in self.attributes.iter() {
+            out.push_str(&attribute.leading);
+            out.push_str(&attribute.name);
+            out.push_str(&attribute.equals);
+            out.push(attribute.quote);
+            out.push_str(&attribute.raw_value);
+            out.push(attribute.quote);
+        }
+        out.push_str(&self.tag_tail);
+        if self.self_closing {
+            out.push_str("/>");
+            return;
+        }
+        out.push('>');
+        for child in self.children.iter() {
+            child.write(out);
+        }
+        out.push_str(&self.end_tag);
+    }
+
+    /// Applies the content of `other` to this element, only touching what differs. `scope` is the
+    /// spec scope describing the element, only the attributes and children it lists are owned by
+    /// the typed structs and removed when missing from `other`.
+    fn reconcile(&mut self, other: &Element, scope: Option<&str>) {
+        let spec = Spec::latest();
+        // Attributes, namespace declarations are managed by the writer and are left alone
+        let is_namespace = |name: &str| name == "xmlns" || name.starts_with("xmlns:");
+        for attribute in other.attributes.iter() {
+            if is_namespace(&attribute.name) {
+                continue;
+            }
+            let value = unescape(&attribute.raw_value);
+            match self.attribute(&attribute.name) {
+                Some(current) if same_value(&current, &value) => {}
+                _ => self.set_attribute(&attribute.name, &value),
+            }
+        }
+        self.attributes.retain(|attribute| {
+            is_namespace(&attribute.name)
+                || other.attribute(&attribute.name).is_some()
+                || scope.is_none_or(|scope| spec.attribute(scope, &attribute.name).is_none())
+        });
+
+        // Text
+        if other.elements().next().is_none() {
+            let text = other.text();
+            if !same_value(&self.text(), &text) {
+                self.set_text(&text);
+            }
+        }
+
+        // Child elements are matched by name and, when present, by their name attribute
+        let key = |element: &Element| (element.name.clone(), element.attribute("name"));
+        let mut matched = HashSet::new();
+        let mut insertions: Vec<(Option<usize>, Element)> = vec![];
+        let mut previous = None;
+        for element in other.elements() {
+            let found = self.children.iter().enumerate().position(|(idx, node)| {
+                matches!(node, Node::Element(e) if !matched.contains(&idx) && key(e) == key(element))
+            });
+            match found {
+                Some(idx) => {
+                    if let Node::Element(e) = &mut self.children[idx] {
+                        let scope = scope
+                            .and_then(|scope| spec.element(scope, &element.name))
+                            .map(|row| row.child_scope);
+                        e.reconcile(element, scope);
+                    }
+                    matched.insert(idx);
+                    previous = Some(idx);
+                }
+                None => insertions.push((previous, element.clone())),
+            }
+        }
+
+        let first_matched = matched.iter().min().copied();
+        let indent = self.child_indent();
+        let children = std::mem::take(&mut self.children);
+        let mut kept: Vec<Node> = Vec::with_capacity(children.len());
+        let mut anchors = vec![None; children.len()];
+        for (idx, node) in children.into_iter().enumerate() {
+            let owned = |element: &Element| {
+                scope.is_some_and(|scope| spec.element(scope, &element.name).is_some())
+            };
+            if matches!(&node, Node::Element(element) if owned(element)) && !matched.contains(&idx)
+            {
+                if kept.last().is_some_and(Node::is_whitespace) {
+                    kept.pop();
+                }
+                continue;
+            }
+            kept.push(node);
+            anchors[idx] = Some(kept.len() - 1);
+        }
+        self.children = kept;
+
+        let mut insertions: Vec<_> = insertions
+            .into_iter()
+            .map(|(after, element)| {
+                let at = match after.or(first_matched) {
+                    Some(idx) => {
+                        let anchor = anchors[idx].expect("Matched elements are kept");
+                        if after.is_some() {
+                            anchor + 1
+                        } else if anchor > 0 && self.children[anchor - 1].is_whitespace() {
+                            // Goes before the first matched element and its indentation
+                            anchor - 1
+                        } else {
+                            anchor
+                        }
+                    }
+                    None => self.closing_position(),
+                };
+                (at, element)
+            })
+            .collect();
+        // Insert from the back so that the positions stay valid
+        insertions.sort_by_key(|(at, _)| *at);
+        for (at, element) in insertions.into_iter().rev() {
+            self.insert_with_indent(at, indent.clone(), element);
+        }
+    }
+}
+
+/// A parsed file, with everything outside of the root element kept as is.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Document {
+    prolog: Vec<Node>,
+    root: Element,
+    epilog: Vec<Node>,
+}
+
+impl Document {
+    /// Parses a document keeping all of its formatting.
+    pub fn parse(source: &str) -> Result<Self, String> {
+        let mut parser = Parser { source, pos: 0 };
+        let mut prolog = vec![];
+        let root = loop {
+            match parser.next_node()? {
+                Some(Node::Element(element)) => break element,
+                Some(node) => prolog.push(node),
+                None => return Err("Document has no root element".to_string()),
+            }
+        };
+        let mut epilog = vec![];
+        while let Some(node) = parser.next_node()? {
+            if let Node::Element(element) = node {
+                return Err(parser.error(&format!(
+                    "Unexpected element <{}> after the root element",
+                    element.name
+                )));
+            }
+            epilog.push(node);
+        }
+        Ok(Self {
+            prolog,
+            root,
+            epilog,
+        })
+    }
+
+    /// Returns the root element.
+    pub fn root(&self) -> &Element {
+        &self.root
+    }
+
+    /// Returns the root element mutably.
+    pub fn root_mut(&mut self) -> &mut Element {
+        &mut self.root
+    }
+
+    /// Deserializes the document into a typed struct.
+    pub fn to_typed<T: YaDeserialize>(&self) -> Result<T, String> {
+        yaserde::de::from_str(&self.to_string())
+    }
+
+    /// Applies the content of a typed struct to the document. Elements and attributes whose value
+    /// did not change are left untouched, as are comments and formatting. Elements and attributes
+    /// that are not part of the specification are never removed, even if the typed struct dropped
+    /// them.
+    pub fn update<T: YaSerialize>(&mut self, value: &T) -> Result<(), String> {
+        let serialized = yaserde::ser::to_string(value)?;
+        let updated = Document::parse(&serialized)?;
+        if updated.root.name != self.root.name {
+            return Err(format!(
+                "Cannot update <{}> with <{}>",
+                self.root.name, updated.root.name
+            ));
+        }
+        let scope = Spec::latest()
+            .root(&self.root.name)
+            .map(|row| row.child_scope);
+        self.root.reconcile(&updated.root, scope);
+        Ok(())
+    }
+}
+
+impl fmt::Display for Element {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut out = String::new();
+        self.write(&mut out);
+        f.write_str(&out)
+    }
+}
+
+impl fmt::Display for Document {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut out = String::new();
+        for node in self.prolog.iter() {
+            node.write(&mut out);
+        }
+        self.root.write(&mut out);
+        for node in self.epilog.iter() {
+            node.write(&mut out);
+        }
+        f.write_str(&out)
+    }
+}
+
+/// Returns true if two values are the same once parsed, so that `1.0` and `1` are not considered
+/// a change.
+pub(crate) fn same_value(a: &str, b: &str) -> bool {
+    let a: Vec<_> = a.split_whitespace().collect();
+    let b: Vec<_> = b.split_whitespace().collect();
+    a.len() == b.len()
+        && a.iter().zip(b.iter()).all(|(a, b)| {
+            if a == b {
+                return true;
+            }
+            match (a.parse::<f64>(), b.parse::<f64>()) {
+                (Ok(a), Ok(b)) => a == b,
+                _ => matches!(
+                    (*a, *b),
+                    ("1", "true") | ("true", "1") | ("0", "false") | ("false", "0")
+                ),
+            }
+        })
+}
+
+pub(crate) fn escape(value: &str, quote: Option<char>) -> String {
+    let mut out = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' if quote == Some('"') => out.push_str("&quot;"),
+            '\'' if quote == Some('\'') => out.push_str("&apos;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+fn unescape(raw: &str) -> String {
+    let mut out = String::with_capacity(raw.len());
+    let mut rest = raw;
+    while let Some(start) = rest.find('&') {
+        out.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let Some(end) = rest.find(';') else {
+            break;
+        };
+        let entity = &rest[1..end];
+        let decoded = match entity {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            _ => entity
+                .strip_prefix("#x")
+                .map(|hex| u32::from_str_radix(hex, 16))
+                .or_else(|| entity.strip_prefix('#').map(|dec| dec.parse::<u32>()))
+                .and_then(|code| code.ok())
+                .and_then(char::from_u32),
+        };
+        match decoded {
+            Some(c) => {
+                out.push(c);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+struct Parser<'a> {
+    source: &'a str,
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn error(&self, message: &str) -> String {
+        let line = self.source[..self.pos].matches('\n').count() + 1;
+        format!("{} at line {}", message, line)
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.source[self.pos..]
+    }
+
+    /// Consumes everything up to and including `end`.
+    fn take_until(&mut self, end: &str, what: &str) -> Result<&'a str, String> {
+        let Some(idx) = self.rest().find(end) else {
+            return Err(self.error(&format!("Unterminated {}", what)));
+        };
+        let taken = &self.rest()[..idx + end.len()];
+        self.pos += taken.len();
+        Ok(taken)
+    }
+
+    fn take_while(&mut self, f: impl Fn(char) -> bool) -> &'a str {
+        let len = self.rest().find(|c| !f(c)).unwrap_or(self.rest().len());
+        let taken = &self.rest()[..len];
+        self.pos += len;
+        taken
+    }
+
+    fn take_name(&mut self) -> Result<&'a str, String> {
+        let name = self.take_while(|c| !c.is_whitespace() && !"/>=<\"'".contains(c));
+        if name.is_empty() {
+            return Err(self.error("Expected a name"));
+        }
+        Ok(name)
+    }
+
+    fn next_node(&mut self) -> Result<Option<Node>, String> {
+        let rest = self.rest();
+        if rest.is_empty() {
+            return Ok(None);
+        }
+        let node = if rest.starts_with("<!--") {
+            Node::Comment(self.take_until("-->", "comment")?.to_string())
+        } else if rest.starts_with("<![CDATA[") {
+            Node::CData(self.take_until("]]>", "CDATA section")?.to_string())
+        } else if rest.starts_with("<?") {
+            Node::ProcessingInstruction(
+                self.take_until("?>", "processing instruction")?.to_string(),
+            )
+        } else if rest.starts_with("<!") {
+            self.doctype()?
+        } else if rest.starts_with("</") {
+            return Err(self.error("Unexpected end tag"));
+        } else if rest.starts_with('<') {
+            Node::Element(self.element()?)
+        } else {
+            let len = rest.find('<').unwrap_or(rest.len());
+            self.pos += len;
+            Node::Text(rest[..len].to_string())
+        };
+        Ok(Some(node))
+    }
+
+    fn doctype(&mut self) -> Result<Node, String> {
+        // The internal subset may contain `>` inside brackets
+        let start = self.pos;
+        let mut depth = 0;
+        for (idx, c) in self.rest().char_indices() {
+            match c {
+                '[' => depth += 1,
+                ']' => depth -= 1,
+                '>' if depth == 0 => {
+                    self.pos += idx + 1;
+                    return Ok(Node::Doctype(self.source[start..self.pos].to_string()));
+                }
+                _ => {}
+            }
+        }
+        Err(self.error("Unterminated document type declaration"))
+    }
+
+    fn element(&mut self) -> Result<Element, String> {
+        self.pos += 1;
+        let name = self.take_name()?.to_string();
+        let mut element = Element::new(&name);
+        loop {
+            let whitespace = self.take_while(char::is_whitespace).to_string();
+            let rest = self.rest();
+            if rest.starts_with("/>") {
+                self.pos += 2;
+                element.tag_tail = whitespace;
+                return Ok(element);
+            } else if rest.starts_with('>') {
+                self.pos += 1;
+                element.tag_tail = whitespace;
+                break;
+            } else if rest.is_empty() {
+                return Err(self.error(&format!("Unterminated start tag of <{}>", name)));
+            }
+            if whitespace.is_empty() {
+                return Err(self.error("Expected whitespace between attributes"));
+            }
+            let attribute_name = self.take_name()?.to_string();
+            let mut equals = self.take_while(char::is_whitespace).to_string();
+            if !self.rest().starts_with('=') {
+                return Err(self.error(&format!("Expected = after {}", attribute_name)));
+            }
+            self.pos += 1;
+            equals.push('=');
+            equals += self.take_while(char::is_whitespace);
+            let quote = match self.rest().chars().next() {
+                Some(quote @ ('"' | '\'')) => quote,
+                _ => {
+                    return Err(
+                        self.error(&format!("Expected a quoted value for {}", attribute_name))
+                    )
+                }
+            };
+            self.pos += 1;
+            let value = self.take_until(&quote.to_string(), "attribute value")?;
+            element.attributes.push(Attribute {
+                leading: whitespace,
+                name: attribute_name,
+                equals,
+                quote,
+                raw_value: value[..value.len() - 1].to_string(),
+            });
+        }
+
+        element.self_closing = false;
+        loop {
+            if self.rest().starts_with("</") {
+                let start = self.pos;
+                self.pos += 2;
+                let end_name = self.take_name()?;
+                if end_name != name {
+                    return Err(self.error(&format!("Expected </{}>, found </{}>", name, end_name)));
+                }
+                self.take_while(char::is_whitespace);
+                if !self.rest().starts_with('>') {
+                    return Err(self.error(&format!("Unterminated end tag of <{}>", name)));
+                }
+                self.pos += 1;
+                element.end_tag = self.source[start..self.pos].to_string();
+                return Ok(element);
+            }
+            match self.next_node()? {
+                Some(node) => element.children.push(node),
+                None => return Err(self.error(&format!("Unterminated element <{}>", name))),
+            }
+        }
+    }
+}
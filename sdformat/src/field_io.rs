@@ -0,0 +1,484 @@
+//! Helpers used by the generated `YaDeserialize` and `YaSerialize` implementations.
+//!
+//! The generated code follows the same conventions as the hand written implementations in this
+//! crate: a deserializer consumes the start event of its element and stops right before the end
+//! event, a serializer writes its element using the start event name set by its parent.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::io::{Read, Write};
+
+use xml::attribute::OwnedAttribute;
+use xml::name::OwnedName;
+use xml::namespace::{
+    Namespace, NS_EMPTY_URI, NS_NO_PREFIX, NS_XMLNS_PREFIX, NS_XMLNS_URI, NS_XML_PREFIX,
+};
+use xml::reader::XmlEvent;
+use yaserde::de::Deserializer;
+use yaserde::ser::Serializer;
+use yaserde::{YaDeserialize, YaSerialize};
+
+use crate::{ElementData, ElementMap, Vector3d, Vector3i, XmlElement};
+
+/// Values that are stored as the text of an element or as the value of an attribute.
+pub(crate) trait TextValue: Sized {
+    fn from_text(text: &str) -> Result<Self, String>;
+    fn to_text(&self) -> String;
+}
+
+impl TextValue for String {
+    fn from_text(text: &str) -> Result<Self, String> {
+        Ok(text.to_string())
+    }
+
+    fn to_text(&self) -> String {
+        self.clone()
+    }
+}
+
+impl TextValue for f64 {
+    fn from_text(text: &str) -> Result<Self, String> {
+        text.trim()
+            .parse::<f64>()
+            .map_err(|_| format!("Unable to parse {:?} into f64", text))
+    }
+
+    fn to_text(&self) -> String {
+        self.to_string()
+    }
+}
+
+impl TextValue for bool {
+    fn from_text(text: &str) -> Result<Self, String> {
+        // SDFormat also accepts 0 and 1 for booleans
+        match text.trim() {
+            "true" | "1" => Ok(true),
+            "false" | "0" => Ok(false),
+            _ => Err(format!("Unable to parse {:?} into bool", text)),
+        }
+    }
+
+    fn to_text(&self) -> String {
+        self.to_string()
+    }
+}
+
+impl TextValue for Vector3d {
+    fn from_text(text: &str) -> Result<Self, String> {
+        text.to_string().try_into()
+    }
+
+    fn to_text(&self) -> String {
+        format!("{} {} {}", self.0.x, self.0.y, self.0.z)
+    }
+}
+
+impl TextValue for Vector3i {
+    fn from_text(text: &str) -> Result<Self, String> {
+        text.to_string().try_into()
+    }
+
+    fn to_text(&self) -> String {
+        format!("{} {} {}", self.0.x, self.0.y, self.0.z)
+    }
+}
+
+/// What comes next inside the element being deserialized.
+pub(crate) enum Next {
+    /// The start of a child element with the given (prefixed) name.
+    Child(String),
+    /// Text directly contained by the element.
+    Text(String),
+    /// The end of the element.
+    End,
+}
+
+/// Returns the name of an element or attribute including its namespace prefix, if any.
+pub(crate) fn qualified_name(name: &OwnedName) -> String {
+    match &name.prefix {
+        Some(prefix) => format!("{}:{}", prefix, name.local_name),
+        None => name.local_name.clone(),
+    }
+}
+
+thread_local! {
+    /// The namespaces in scope for the elements being deserialized, along with their depth.
+    static SCOPES: RefCell<Vec<(usize, Namespace)>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Consumes the start event of an element and returns its attributes. Namespaces declared by the
+/// element are returned as `xmlns` attributes so they can be written back.
+pub(crate) fn read_start<R: Read>(
+    reader: &mut Deserializer<R>,
+    element: &str,
+) -> Result<Vec<OwnedAttribute>, String> {
+    let depth = reader.depth();
+    let (mut attributes, namespace) = match reader.next_event()? {
+        XmlEvent::StartElement {
+            attributes,
+            namespace,
+            ..
+        } => (attributes, namespace),
+        event => return Err(format!("Expected <{}>, found {:?}", element, event)),
+    };
+    SCOPES.with(|scopes| {
+        let mut scopes = scopes.borrow_mut();
+        // Drop the scopes of elements that were already closed
+        while scopes.last().is_some_and(|(d, _)| *d >= depth) {
+            scopes.pop();
+        }
+        let parent = scopes.last().map(|(_, namespace)| namespace);
+        for (prefix, uri) in namespace.0.iter() {
+            let inherited = match parent {
+                Some(parent) => parent.get(prefix) == Some(uri.as_str()),
+                None => matches!(
+                    (prefix.as_str(), uri.as_str()),
+                    (NS_XML_PREFIX, _) | (NS_XMLNS_PREFIX, _) | (NS_NO_PREFIX, NS_EMPTY_URI)
+                ),
+            };
+            if inherited {
+                continue;
+            }
+            let name = match prefix.as_str() {
+                NS_NO_PREFIX => OwnedName::local(NS_XMLNS_PREFIX),
+                prefix => OwnedName::qualified(prefix, NS_XMLNS_URI, Some(NS_XMLNS_PREFIX)),
+            };
+            attributes.push(OwnedAttribute::new(name, uri));
+        }
+        scopes.push((depth, namespace));
+    });
+    Ok(attributes)
+}
+
+/// Peeks at the next child of the element being deserialized. Text is consumed, child elements
+/// and the end of the element are left for the caller.
+pub(crate) fn next_child<R: Read>(reader: &mut Deserializer<R>) -> Result<Next, String> {
+    loop {
+        match reader.peek()? {
+            XmlEvent::StartElement { name, .. } => return Ok(Next::Child(qualified_name(name))),
+            XmlEvent::EndElement { .. } => return Ok(Next::End),
+            XmlEvent::EndDocument => return Err("Unexpected end of document".to_string()),
+            XmlEvent::Characters(_) => {
+                if let XmlEvent::Characters(text) = reader.next_event()? {
+                    return Ok(Next::Text(text));
+                }
+            }
+            _ => {
+                reader.next_event()?;
+            }
+        }
+    }
+}
+
+/// Deserializes a child element into a type that implements `YaDeserialize`, consuming the
+/// whole child including its end event.
+pub(crate) fn read_child<T: YaDeserialize, R: Read>(
+    reader: &mut Deserializer<R>,
+) -> Result<T, String> {
+    read_child_with(reader, T::deserialize)
+}
+
+/// Deserializes a child element with the given function, which follows the conventions of
+/// `YaDeserialize`, consuming the whole child including its end event.
+pub(crate) fn read_child_with<T, R: Read>(
+    reader: &mut Deserializer<R>,
+    read: impl FnOnce(&mut Deserializer<R>) -> Result<T, String>,
+) -> Result<T, String> {
+    let depth = reader.depth();
+    let value = read(reader)?;
+    while reader.depth() > depth {
+        reader.next_event()?;
+    }
+    Ok(value)
+}
+
+/// Deserializes the text of a child element along with the content that is not part of the
+/// specification, see `extension`. Consumes the whole child.
+pub(crate) fn read_value_child<R: Read>(
+    reader: &mut Deserializer<R>,
+) -> Result<(String, Option<XmlElement>), String> {
+    let name = match reader.peek()? {
+        XmlEvent::StartElement { name, .. } => qualified_name(name),
+        event => {
+            return Err(format!(
+                "Expected the start of an element, found {:?}",
+                event
+            ))
+        }
+    };
+    let attributes = read_start(reader, &name)?;
+    let mut text = String::new();
+    let mut elements = ElementMap::default();
+    loop {
+        match next_child(reader)? {
+            Next::Child(_) => elements.push(crate::deserialize_element(reader)?),
+            Next::Text(value) => text += &value,
+            Next::End => break,
+        }
+    }
+    reader.next_event()?;
+    Ok((text, extension(&name, attributes, elements)))
+}
+
+/// Gathers the attributes and child elements of an element that are not part of the
+/// specification into an element of the same name, or returns `None` if there are none.
+pub(crate) fn extension(
+    name: &str,
+    attributes: Vec<OwnedAttribute>,
+    elements: ElementMap,
+) -> Option<XmlElement> {
+    if attributes.is_empty() && elements.all().is_empty() {
+        return None;
+    }
+    Some(XmlElement {
+        attributes: attributes
+            .into_iter()
+            .map(|attribute| (qualified_name(&attribute.name), attribute.value))
+            .collect(),
+        name: name.into(),
+        data: ElementData::Nested(elements),
+    })
+}
+
+/// Stores the extension of the child at the given position among the children of the same name.
+/// Earlier children without an extension are padded with empty elements.
+pub(crate) fn keep_extension(
+    extras: &mut ElementMap,
+    name: &str,
+    position: usize,
+    extension: Option<XmlElement>,
+) {
+    let Some(extension) = extension else {
+        return;
+    };
+    let kept = extras
+        .all()
+        .iter()
+        .filter(|element| &*element.name == name)
+        .count();
+    for _ in kept..position {
+        extras.push(XmlElement {
+            name: name.into(),
+            ..Default::default()
+        });
+    }
+    extras.push(extension);
+}
+
+/// Returns the extension stored by `keep_extension` for the child at the given position.
+pub(crate) fn find_extension<'a>(
+    extras: &'a ElementMap,
+    name: &str,
+    position: usize,
+) -> Option<&'a XmlElement> {
+    extras
+        .all()
+        .iter()
+        .filter(|element| &*element.name == name)
+        .nth(position)
+}
+
+/// Skips a child element that is not part of the specification.
+pub(crate) fn skip_child<R: Read>(reader: &mut Deserializer<R>) -> Result<(), String> {
+    let depth = reader.depth();
+    reader.next_event()?;
+    while reader.depth() > depth {
+        reader.next_event()?;
+    }
+    Ok(())
+}
+
+/// Parses the value of an attribute.
+pub(crate) fn parse_attribute<T: TextValue>(
+    value: &str,
+    attribute: &str,
+    element: &str,
+) -> Result<T, String> {
+    T::from_text(value).map_err(|e| {
+        format!(
+            "Failed to parse attribute {} of <{}>: {}",
+            attribute, element, e
+        )
+    })
+}
+
+/// Parses the text of an element.
+pub(crate) fn parse_text<T: TextValue>(text: &str, element: &str) -> Result<T, String> {
+    T::from_text(text).map_err(|e| format!("Failed to parse the value of <{}>: {}", element, e))
+}
+
+/// Unwraps a field that must be present in an element.
+pub(crate) fn required<T>(value: Option<T>, field: &str, element: &str) -> Result<T, String> {
+    value.ok_or_else(|| format!("{} is a required field of <{}>", field, element))
+}
+
+/// Stores the selected alternative of a choice, failing if one was already found.
+pub(crate) fn set_choice<T>(choice: &mut Option<T>, value: T, element: &str) -> Result<(), String> {
+    if choice.is_some() {
+        return Err(format!(
+            "<{}> may only contain one of its alternatives",
+            element
+        ));
+    }
+    *choice = Some(value);
+    Ok(())
+}
+
+/// Checks that the alternative of a choice is allowed by the value of the attribute selecting it.
+/// Values that are not listed allow any alternative.
+pub(crate) fn check_choice(
+    value: &str,
+    alternative: &str,
+    allowed: &[(&str, &[&str])],
+    element: &str,
+) -> Result<(), String> {
+    match allowed.iter().find(|(allowed, _)| *allowed == value) {
+        Some((_, alternatives)) if !alternatives.contains(&alternative) => Err(format!(
+            "<{}> of type {} can not contain <{}>",
+            element, value, alternative
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Writes the start of an element, returns whether the start and end events are skipped.
+pub(crate) fn write_start<W: Write>(
+    writer: &mut Serializer<W>,
+    default_name: &str,
+    attributes: &[(&str, Option<String>)],
+    extra_attributes: &HashMap<String, String>,
+) -> Result<bool, String> {
+    if writer.skip_start_end() {
+        return Ok(true);
+    }
+    let name = writer
+        .get_start_event_name()
+        .unwrap_or_else(|| default_name.to_string());
+    write_start_event(writer, &name, attributes, extra_attributes)?;
+    Ok(false)
+}
+
+/// Writes a start event. Attributes that are not part of the specification are written after the
+/// known ones, sorted by name, and namespace declarations among them are registered with the
+/// writer so they are only written once.
+pub(crate) fn write_start_event<W: Write>(
+    writer: &mut Serializer<W>,
+    name: &str,
+    attributes: &[(&str, Option<String>)],
+    extra_attributes: &HashMap<String, String>,
+) -> Result<(), String> {
+    let mut event = xml::writer::XmlEvent::start_element(name);
+    for (attribute, value) in attributes.iter() {
+        if let Some(value) = value {
+            event = event.attr(*attribute, value);
+        }
+    }
+    let mut extra_attributes: Vec<_> = extra_attributes.iter().collect();
+    extra_attributes.sort();
+    for (attribute, value) in extra_attributes {
+        event = match attribute.split_once(':') {
+            Some((NS_XMLNS_PREFIX, prefix)) => event.ns(prefix, value.as_str()),
+            _ if attribute == NS_XMLNS_PREFIX => event.default_ns(value.as_str()),
+            _ => event.attr(attribute.as_str(), value),
+        };
+    }
+    writer.write(event).map_err(|e| e.to_string())
+}
+
+/// Writes the end of an element started with `write_start`.
+pub(crate) fn write_end<W: Write>(writer: &mut Serializer<W>, skip: bool) -> Result<(), String> {
+    if !skip {
+        writer
+            .write(xml::writer::XmlEvent::end_element())
+            .map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+/// Serializes a child element from a type that implements `YaSerialize`.
+pub(crate) fn write_child<T: YaSerialize, W: Write>(
+    value: &T,
+    name: &str,
+    writer: &mut Serializer<W>,
+) -> Result<(), String> {
+    start_child(name, writer);
+    value.serialize(writer)
+}
+
+/// Sets the name of the child element serialized next.
+pub(crate) fn start_child<W: Write>(name: &str, writer: &mut Serializer<W>) {
+    writer.set_start_event_name(Some(name.to_string()));
+    writer.set_skip_start_end(false);
+}
+
+/// Serializes a child element containing only text.
+pub(crate) fn write_text_child<T: TextValue, W: Write>(
+    value: &T,
+    name: &str,
+    writer: &mut Serializer<W>,
+) -> Result<(), String> {
+    writer
+        .write(xml::writer::XmlEvent::start_element(name))
+        .map_err(|e| e.to_string())?;
+    write_text(&value.to_text(), writer)?;
+    writer
+        .write(xml::writer::XmlEvent::end_element())
+        .map_err(|e| e.to_string())
+}
+
+/// Serializes a child element containing text along with its extension, see `read_value_child`.
+pub(crate) fn write_value_child<T: TextValue, W: Write>(
+    value: &T,
+    name: &str,
+    extension: Option<&XmlElement>,
+    writer: &mut Serializer<W>,
+) -> Result<(), String> {
+    let Some(extension) = extension else {
+        return write_text_child(value, name, writer);
+    };
+    write_start_event(writer, name, &[], &extension.attributes)?;
+    write_text(&value.to_text(), writer)?;
+    write_extension_children(Some(extension), writer)?;
+    writer
+        .write(xml::writer::XmlEvent::end_element())
+        .map_err(|e| e.to_string())
+}
+
+/// Serializes the child elements of an extension, see `extension`.
+pub(crate) fn write_extension_children<W: Write>(
+    extension: Option<&XmlElement>,
+    writer: &mut Serializer<W>,
+) -> Result<(), String> {
+    match extension.map(|extension| &extension.data) {
+        Some(ElementData::Nested(elements)) => write_elements(elements, writer),
+        _ => Ok(()),
+    }
+}
+
+/// Serializes an element without attributes or content.
+pub(crate) fn write_empty<W: Write>(name: &str, writer: &mut Serializer<W>) -> Result<(), String> {
+    writer
+        .write(xml::writer::XmlEvent::start_element(name))
+        .map_err(|e| e.to_string())?;
+    writer
+        .write(xml::writer::XmlEvent::end_element())
+        .map_err(|e| e.to_string())
+}
+
+/// Writes the text of the element being serialized.
+pub(crate) fn write_text<W: Write>(text: &str, writer: &mut Serializer<W>) -> Result<(), String> {
+    writer
+        .write(xml::writer::XmlEvent::characters(text))
+        .map_err(|e| e.to_string())
+}
+
+/// Serializes elements that were copied as they are.
+pub(crate) fn write_elements<W: Write>(
+    elements: &ElementMap,
+    writer: &mut Serializer<W>,
+) -> Result<(), String> {
+    for element in elements.all().iter() {
+        crate::serialize_element(element, writer)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,460 @@
+//! Flattening of nested models into a single model, and the reverse.
+//!
+//! The links, joints and frames of nested models are moved into the outermost model and named
+//! with their scope, e.g. the link `finger` of the model `gripper` becomes `gripper::finger`.
+//! Since references from the outer model already use scoped names they are kept as they are,
+//! apart from `gripper::__model__` that becomes `gripper`: the frame of each nested model is
+//! replaced by an explicit frame with the name of the model. See [`unflatten`] to rebuild nested
+//! models from scoped names.
+
+use nalgebra::Isometry3;
+
+use crate::frames::{scoped_name, FrameGraph};
+use crate::{SdfFrame, SdfGripper, SdfJoint, SdfModel, SdfPose, SdfWorld};
+
+/// Rewrites a frame name used in the model defined in `scope` to its flattened name.
+fn rename(scope: &str, name: &str) -> String {
+    match (scope, name) {
+        (_, "world") | ("", "__model__") => name.to_string(),
+        (scope, "__model__") => scope.to_string(),
+        (scope, name) => match name.strip_suffix("::__model__") {
+            Some(model) => scoped_name(scope, model),
+            None => scoped_name(scope, name),
+        },
+    }
+}
+
+fn rename_pose(scope: &str, pose: &mut Option<SdfPose>) {
+    if let Some(relative_to) = pose
+        .as_mut()
+        .and_then(|pose| pose.relative_to.as_mut())
+        .filter(|relative_to| !relative_to.is_empty())
+    {
+        *relative_to = rename(scope, relative_to);
+    }
+}
+
+/// Returns the frames the axes of a joint are expressed in, when set.
+fn expressed_in(joint: &mut SdfJoint) -> impl Iterator<Item = &mut String> {
+    let axis = joint
+        .axis
+        .as_mut()
+        .and_then(|axis| axis.xyz.expressed_in.as_mut());
+    let axis2 = joint
+        .axis2
+        .as_mut()
+        .and_then(|axis| axis.xyz.expressed_in.as_mut());
+    axis.into_iter()
+        .chain(axis2)
+        .filter(|expressed_in| !expressed_in.is_empty())
+}
+
+/// A pose without `relative_to`, expressed in the default frame of the element.
+fn pose(isometry: &Isometry3<f64>) -> Option<SdfPose> {
+    Some(SdfPose::from_isometry(isometry, None))
+}
+
+/// Moves the contents of a nested model into `flat`. `scope` is the scoped name of the nested
+/// model, `inherited` the `self_collide` and `enable_wind` settings of the models around it.
+fn hoist(
+    flat: &mut SdfModel,
+    model: &SdfModel,
+    scope: &str,
+    graph: &FrameGraph,
+    inherited: (Option<bool>, Option<bool>),
+) -> Result<(), String> {
+    if !model.include.is_empty() {
+        return Err(format!(
+            "Model {} has includes, they must be resolved before flattening",
+            scope
+        ));
+    }
+    if model
+        .r#static
+        .is_some_and(|r#static| r#static != flat.r#static.unwrap_or(false))
+    {
+        return Err(format!(
+            "Model {} is not static like the outermost model, it cannot be flattened",
+            scope
+        ));
+    }
+    // The settings of the model apply to its links, unless they have their own
+    let self_collide = model.self_collide.or(inherited.0);
+    let enable_wind = model.enable_wind.or(inherited.1);
+
+    // The model frame is kept as a frame attached to the canonical link
+    let frame = match graph.attached_to(scope) {
+        Some(canonical) if !canonical.is_empty() && canonical != scope => SdfFrame {
+            name: scope.to_string(),
+            attached_to: Some(canonical.to_string()),
+            pose: pose(&graph.pose(scope, canonical)?),
+            ..Default::default()
+        },
+        _ => SdfFrame {
+            name: scope.to_string(),
+            pose: pose(&graph.pose(scope, "__model__")?),
+            ..Default::default()
+        },
+    };
+    flat.frame.push(frame);
+
+    for link in model.link.iter() {
+        let name = scoped_name(scope, &link.name);
+        let mut link = link.clone();
+        link.pose = pose(&graph.pose(&name, "__model__")?);
+        for visual in link.visual.iter_mut() {
+            rename_pose(scope, &mut visual.pose);
+        }
+        for collision in link.collision.iter_mut() {
+            rename_pose(scope, &mut collision.pose);
+        }
+        for sensor in link.sensor.iter_mut() {
+            rename_pose(scope, &mut sensor.pose);
+        }
+        for light in link.light.iter_mut() {
+            rename_pose(scope, &mut light.pose);
+        }
+        link.self_collide = link.self_collide.or(self_collide);
+        link.enable_wind = link.enable_wind.or(enable_wind);
+        link.name = name;
+        flat.link.push(link);
+    }
+    for joint in model.joint.iter() {
+        let name = scoped_name(scope, &joint.name);
+        let mut joint = joint.clone();
+        joint.parent = rename(scope, &joint.parent);
+        joint.child = rename(scope, &joint.child);
+        joint.pose = pose(&graph.pose(&name, &joint.child)?);
+        for sensor in joint.sensor.iter_mut() {
+            rename_pose(scope, &mut sensor.pose);
+        }
+        if let Some(reference) = joint.gearbox_reference_body.as_mut() {
+            *reference = rename(scope, reference);
+        }
+        for expressed_in in expressed_in(&mut joint) {
+            *expressed_in = rename(scope, expressed_in);
+        }
+        joint.name = name;
+        flat.joint.push(joint);
+    }
+    for frame in model.frame.iter() {
+        let name = scoped_name(scope, &frame.name);
+        let attached_to = match frame.attached_to.as_deref() {
+            Some(attached_to) if !attached_to.is_empty() => rename(scope, attached_to),
+            _ => scope.to_string(),
+        };
+        flat.frame.push(SdfFrame {
+            pose: pose(&graph.pose(&name, &attached_to)?),
+            attached_to: Some(attached_to),
+            name,
+            ..frame.clone()
+        });
+    }
+    flat.plugin.extend(model.plugin.iter().cloned());
+    for gripper in model.gripper.iter() {
+        flat.gripper.push(SdfGripper {
+            name: scoped_name(scope, &gripper.name),
+            palm_link: scoped_name(scope, &gripper.palm_link),
+            gripper_link: gripper
+                .gripper_link
+                .iter()
+                .map(|link| scoped_name(scope, link))
+                .collect(),
+            ..gripper.clone()
+        });
+    }
+    for nested in model.model.iter() {
+        let scope = scoped_name(scope, &nested.name);
+        hoist(flat, nested, &scope, graph, (self_collide, enable_wind))?;
+    }
+    Ok(())
+}
+
+/// Returns a copy of a model where the contents of all nested models, at any depth, are moved
+/// into the model itself with scoped names.
+///
+/// Poses of moved links, joints and frames are composed through their `relative_to` chains and
+/// written relative to the frame they default to: links relative to the model frame, joints
+/// relative to their child and frames relative to the frame they are attached to. The
+/// `self_collide` and `enable_wind` settings of nested models are moved to their links and their
+/// plugins to the flattened model. Nested models must not have includes and must not be static
+/// unless the outermost model is.
+pub fn flatten(model: &SdfModel) -> Result<SdfModel, String> {
+    let graph = FrameGraph::from_model(model)?;
+    let mut flat = model.clone();
+    flat.model.clear();
+    // The canonical link would otherwise change to the first link of the flattened model
+    if flat.canonical_link.is_none() && model.link.is_empty() {
+        flat.canonical_link = graph
+            .attached_to("__model__")
+            .filter(|canonical| !canonical.is_empty() && *canonical != "__model__")
+            .map(str::to_string);
+    }
+    for link in flat.link.iter_mut() {
+        rename_pose("", &mut link.pose);
+    }
+    for joint in flat.joint.iter_mut() {
+        joint.parent = rename("", &joint.parent);
+        joint.child = rename("", &joint.child);
+        rename_pose("", &mut joint.pose);
+        for expressed_in in expressed_in(joint) {
+            *expressed_in = rename("", expressed_in);
+        }
+    }
+    for frame in flat.frame.iter_mut() {
+        if let Some(attached_to) = frame.attached_to.as_mut() {
+            *attached_to = rename("", attached_to);
+        }
+        rename_pose("", &mut frame.pose);
+    }
+    for nested in model.model.iter() {
+        hoist(&mut flat, nested, &nested.name, &graph, (None, None))?;
+    }
+    Ok(flat)
+}
+
+/// Returns the part of a name after `prefix::`.
+fn within<'a>(prefix: &str, name: &'a str) -> Option<&'a str> {
+    name.strip_prefix(prefix)?.strip_prefix("::")
+}
+
+/// Rewrites a frame reference for a model named `prefix`, `None` if the frame is outside of it.
+fn local_reference(prefix: &str, name: &str) -> Option<String> {
+    match name == prefix {
+        true => Some("__model__".to_string()),
+        false => within(prefix, name).map(str::to_string),
+    }
+}
+
+/// Rewrites a pose for a model named `prefix`, `None` if it refers to a frame outside of it.
+fn local_pose(prefix: &str, pose: &Option<SdfPose>) -> Option<Option<SdfPose>> {
+    let mut pose = pose.clone();
+    if let Some(relative_to) = pose
+        .as_mut()
+        .and_then(|pose| pose.relative_to.as_mut())
+        .filter(|relative_to| !relative_to.is_empty())
+    {
+        *relative_to = local_reference(prefix, relative_to)?;
+    }
+    Some(pose)
+}
+
+/// Returns the nested model named `name`, created if needed.
+fn scope_model<'a>(models: &'a mut Vec<SdfModel>, name: &str) -> &'a mut SdfModel {
+    let idx = match models.iter().position(|model| model.name == name) {
+        Some(idx) => idx,
+        None => {
+            models.push(SdfModel {
+                name: name.to_string(),
+                ..Default::default()
+            });
+            models.len() - 1
+        }
+    };
+    &mut models[idx]
+}
+
+/// Returns a copy of a model where links, joints, frames and models with scoped names, such as
+/// `gripper::finger`, are moved into nested models, the reverse of [`flatten`].
+///
+/// This follows the `<unflatten/>` rule libsdformat applies to models of SDFormat 1.7 and older.
+/// An element is only moved if all the frames it refers to are moved along with it, e.g. a joint
+/// between `base` and `gripper::finger` stays in the model. Nested models are placed at the
+/// model frame, so the poses of the moved elements are unchanged, unless the model has a frame
+/// with the name of the nested model as written by [`flatten`], which then becomes its frame.
+pub fn unflatten(model: &SdfModel) -> Result<SdfModel, String> {
+    let mut unflattened = model.clone();
+    let mut nested = Vec::new();
+
+    for link in std::mem::take(&mut unflattened.link) {
+        let moved = link.name.split_once("::").and_then(|(prefix, name)| {
+            let pose = local_pose(prefix, &link.pose)?;
+            let mut link = link.clone();
+            // Shapes referring to frames outside of the model keep their poses as they are
+            let poses = link
+                .visual
+                .iter_mut()
+                .map(|visual| &mut visual.pose)
+                .chain(
+                    link.collision
+                        .iter_mut()
+                        .map(|collision| &mut collision.pose),
+                )
+                .chain(link.sensor.iter_mut().map(|sensor| &mut sensor.pose))
+                .chain(link.light.iter_mut().map(|light| &mut light.pose));
+            for pose in poses {
+                if let Some(local) = local_pose(prefix, pose) {
+                    *pose = local;
+                }
+            }
+            link.name = name.to_string();
+            link.pose = pose;
+            Some((prefix, link))
+        });
+        match moved {
+            Some((prefix, moved)) => scope_model(&mut nested, prefix).link.push(moved),
+            None => unflattened.link.push(link),
+        }
+    }
+    for joint in std::mem::take(&mut unflattened.joint) {
+        let moved = joint.name.split_once("::").and_then(|(prefix, name)| {
+            let mut joint = SdfJoint {
+                name: name.to_string(),
+                parent: local_reference(prefix, &joint.parent)?,
+                child: local_reference(prefix, &joint.child)?,
+                pose: local_pose(prefix, &joint.pose)?,
+                ..joint.clone()
+            };
+            for expressed_in in expressed_in(&mut joint) {
+                *expressed_in = local_reference(prefix, expressed_in)?;
+            }
+            Some((prefix, joint))
+        });
+        match moved {
+            Some((prefix, moved)) => scope_model(&mut nested, prefix).joint.push(moved),
+            None => unflattened.joint.push(joint),
+        }
+    }
+    for frame in std::mem::take(&mut unflattened.frame) {
+        let moved = frame.name.split_once("::").and_then(|(prefix, name)| {
+            let attached_to = match frame.attached_to.as_deref() {
+                Some(attached_to) if !attached_to.is_empty() => {
+                    Some(local_reference(prefix, attached_to)?)
+                }
+                _ => None,
+            };
+            let frame = SdfFrame {
+                name: name.to_string(),
+                attached_to,
+                pose: local_pose(prefix, &frame.pose)?,
+                ..frame.clone()
+            };
+            Some((prefix, frame))
+        });
+        match moved {
+            Some((prefix, moved)) => scope_model(&mut nested, prefix).frame.push(moved),
+            None => unflattened.frame.push(frame),
+        }
+    }
+    for model in std::mem::take(&mut unflattened.model) {
+        let moved = model.name.split_once("::").and_then(|(prefix, name)| {
+            let model = SdfModel {
+                name: name.to_string(),
+                pose: local_pose(prefix, &model.pose)?,
+                ..(*model).clone()
+            };
+            Some((prefix, model))
+        });
+        match moved {
+            Some((prefix, moved)) => scope_model(&mut nested, prefix).model.push(moved.into()),
+            None => unflattened.model.push(model),
+        }
+    }
+    for gripper in std::mem::take(&mut unflattened.gripper) {
+        let moved = gripper.name.split_once("::").and_then(|(prefix, name)| {
+            let gripper = SdfGripper {
+                name: name.to_string(),
+                palm_link: within(prefix, &gripper.palm_link)?.to_string(),
+                gripper_link: gripper
+                    .gripper_link
+                    .iter()
+                    .map(|link| within(prefix, link).map(str::to_string))
+                    .collect::<Option<_>>()?,
+                ..gripper.clone()
+            };
+            Some((prefix, gripper))
+        });
+        match moved {
+            Some((prefix, moved)) => scope_model(&mut nested, prefix).gripper.push(moved),
+            None => unflattened.gripper.push(gripper),
+        }
+    }
+
+    // Frames that [`flatten`] writes for nested models become their model frames again
+    let graph = FrameGraph::from_model(model).ok();
+    for model in nested.iter_mut() {
+        let Some(idx) = unflattened.frame.iter().position(|frame| {
+            frame.name == model.name
+                && frame
+                    .attached_to
+                    .as_deref()
+                    .is_none_or(|attached_to| within(&model.name, attached_to).is_some())
+        }) else {
+            continue;
+        };
+        let Some(placement) = graph
+            .as_ref()
+            .and_then(|graph| graph.pose(&model.name, "__model__").ok())
+        else {
+            continue;
+        };
+        let frame = unflattened.frame.remove(idx);
+        model.canonical_link = frame
+            .attached_to
+            .as_deref()
+            .and_then(|attached_to| within(&model.name, attached_to))
+            .map(str::to_string);
+        model.pose = Some(SdfPose::from_isometry(&placement, None));
+        let inverse = placement.inverse();
+        // Frames attached to the outer model are now attached to the nested one
+        let poses = model
+            .link
+            .iter_mut()
+            .map(|link| &mut link.pose)
+            .chain(model.model.iter_mut().map(|nested| &mut nested.pose))
+            .chain(
+                model
+                    .frame
+                    .iter_mut()
+                    .filter(|frame| frame.attached_to.as_deref().is_none_or(str::is_empty))
+                    .map(|frame| &mut frame.pose),
+            );
+        for pose in poses {
+            if pose
+                .as_ref()
+                .and_then(|pose| pose.relative_to.as_deref())
+                .is_none_or(str::is_empty)
+            {
+                let isometry = match pose {
+                    Some(pose) => pose.get_pose()?.isometry(),
+                    None => Isometry3::identity(),
+                };
+                *pose = Some(SdfPose::from_isometry(&(inverse * isometry), None));
+            }
+        }
+    }
+
+    // Moved elements are merged into the nested models that already exist
+    for model in nested {
+        match unflattened
+            .model
+            .iter_mut()
+            .find(|existing| existing.name == model.name)
+        {
+            Some(existing) => {
+                existing.link.extend(model.link);
+                existing.joint.extend(model.joint);
+                existing.frame.extend(model.frame);
+                existing.model.extend(model.model);
+                existing.gripper.extend(model.gripper);
+            }
+            None => unflattened.model.push(model.into()),
+        }
+    }
+    for model in unflattened.model.iter_mut() {
+        **model = unflatten(model)?;
+    }
+    Ok(unflattened)
+}
+
+/// Applies [`unflatten`] to every model of a world.
+pub fn unflatten_world(world: &SdfWorld) -> Result<SdfWorld, String> {
+    Ok(SdfWorld {
+        model: world
+            .model
+            .iter()
+            .map(unflatten)
+            .collect::<Result<_, _>>()?,
+        ..world.clone()
+    })
+}
@@ -0,0 +1,344 @@
+//! Resolution of the poses of the frames defined by models and worlds.
+//!
+//! Every link, joint, explicit frame and nested model defines a frame. Nested frames are named
+//! with their scope, e.g. `arm::gripper::finger`, and `__model__` always refers to the frame of
+//! the enclosing model. The root of the graph is the `__model__` frame when built from a model
+//! and the `world` frame when built from a world.
+
+use std::collections::{HashMap, HashSet};
+
+use nalgebra::Isometry3;
+
+use crate::{SdfModel, SdfPose, SdfWorld};
+
+/// The element that defines a frame.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FrameKind {
+    World,
+    Model,
+    Link,
+    Joint,
+    Frame,
+}
+
+#[derive(Clone, Debug)]
+struct FrameNode {
+    kind: FrameKind,
+    /// The frame the pose is expressed in, empty for the root.
+    relative_to: String,
+    pose: Isometry3<f64>,
+    /// The frame this frame moves with, empty for the root.
+    attached_to: String,
+}
+
+/// The frames of a model or world along with the poses that relate them.
+#[derive(Clone, Debug)]
+pub struct FrameGraph {
+    root: String,
+    frames: HashMap<String, FrameNode>,
+    /// The order in which frames were defined.
+    order: Vec<String>,
+}
+
+/// Joins a name to the scope it is defined in.
+pub fn scoped_name(scope: &str, name: &str) -> String {
+    if scope.is_empty() {
+        name.to_string()
+    } else {
+        format!("{}::{}", scope, name)
+    }
+}
+
+fn isometry(pose: Option<&SdfPose>) -> Result<Isometry3<f64>, String> {
+    match pose {
+        Some(pose) => Ok(pose.get_pose()?.isometry()),
+        None => Ok(Isometry3::identity()),
+    }
+}
+
+fn relative_to(pose: Option<&SdfPose>) -> Option<&str> {
+    pose.and_then(|pose| pose.relative_to.as_deref())
+        .filter(|relative_to| !relative_to.is_empty())
+}
+
+impl FrameGraph {
+    /// Builds the frames of a model, the root of the graph is the model frame `__model__`.
+    pub fn from_model(model: &SdfModel) -> Result<Self, String> {
+        let mut graph = Self::new("__model__", FrameKind::Model);
+        graph.add_model_contents(model, "")?;
+        let canonical = graph.canonical_link(model, "");
+        if let Some(root) = graph.frames.get_mut("__model__") {
+            root.attached_to = canonical;
+        }
+        Ok(graph)
+    }
+
+    /// Builds the frames of a world and of the models it contains, the root of the graph is the
+    /// `world` frame.
+    pub fn from_world(world: &SdfWorld) -> Result<Self, String> {
+        let mut graph = Self::new("world", FrameKind::World);
+        for model in world.model.iter() {
+            graph.add_model(model, "")?;
+        }
+        for frame in world.frame.iter() {
+            let attached_to = match frame.attached_to.as_deref() {
+                Some(attached_to) if !attached_to.is_empty() => attached_to.to_string(),
+                _ => "world".to_string(),
+            };
+            let relative_to = relative_to(frame.pose.as_ref())
+                .map(str::to_string)
+                .unwrap_or_else(|| attached_to.clone());
+            graph.insert(
+                &frame.name,
+                FrameNode {
+                    kind: FrameKind::Frame,
+                    relative_to,
+                    pose: isometry(frame.pose.as_ref())?,
+                    attached_to,
+                },
+            )?;
+        }
+        Ok(graph)
+    }
+
+    fn new(root: &str, kind: FrameKind) -> Self {
+        let node = FrameNode {
+            kind,
+            relative_to: "".to_string(),
+            pose: Isometry3::identity(),
+            attached_to: "".to_string(),
+        };
+        Self {
+            root: root.to_string(),
+            frames: HashMap::from([(root.to_string(), node)]),
+            order: vec![root.to_string()],
+        }
+    }
+
+    fn insert(&mut self, name: &str, node: FrameNode) -> Result<(), String> {
+        if self.frames.insert(name.to_string(), node).is_some() {
+            return Err(format!("Frame {} is defined more than once", name));
+        }
+        self.order.push(name.to_string());
+        Ok(())
+    }
+
+    /// Resolves a frame name used inside a model defined in `scope`.
+    pub(crate) fn resolve(scope: &str, name: &str) -> String {
+        match (scope, name) {
+            ("", "__model__") => "__model__".to_string(),
+            (scope, "__model__") => scope.to_string(),
+            (scope, name) => match name.strip_suffix("::__model__") {
+                Some(model) => scoped_name(scope, model),
+                None => scoped_name(scope, name),
+            },
+        }
+    }
+
+    /// The model frame of a model defined in `scope`.
+    fn model_frame(scope: &str) -> String {
+        Self::resolve(scope, "__model__")
+    }
+
+    fn canonical_link(&self, model: &SdfModel, scope: &str) -> String {
+        match model.canonical_link.as_deref() {
+            Some(link) if !link.is_empty() => Self::resolve(scope, link),
+            _ => match (model.link.first(), model.model.first()) {
+                (Some(link), _) => scoped_name(scope, &link.name),
+                (None, Some(nested)) => {
+                    self.canonical_link(nested, &scoped_name(scope, &nested.name))
+                }
+                (None, None) => Self::model_frame(scope),
+            },
+        }
+    }
+
+    /// Adds a nested model and its contents. `scope` is the scope the model is defined in.
+    fn add_model(&mut self, model: &SdfModel, scope: &str) -> Result<(), String> {
+        let name = scoped_name(scope, &model.name);
+        let relative_to = relative_to(model.pose.as_ref())
+            .map(|relative_to| self.resolve_in_parent(scope, relative_to))
+            .unwrap_or_else(|| self.parent_frame(scope));
+        self.insert(
+            &name,
+            FrameNode {
+                kind: FrameKind::Model,
+                relative_to,
+                pose: isometry(model.pose.as_ref())?,
+                attached_to: "".to_string(),
+            },
+        )?;
+        self.add_model_contents(model, &name)?;
+        let canonical = self.canonical_link(model, &name);
+        if let Some(node) = self.frames.get_mut(&name) {
+            node.attached_to = canonical;
+        }
+        Ok(())
+    }
+
+    /// The frame poses default to in `scope`, the world or the enclosing model.
+    fn parent_frame(&self, scope: &str) -> String {
+        if scope.is_empty() {
+            self.root.clone()
+        } else {
+            scope.to_string()
+        }
+    }
+
+    fn resolve_in_parent(&self, scope: &str, name: &str) -> String {
+        if scope.is_empty() && self.root == "world" {
+            name.to_string()
+        } else {
+            Self::resolve(scope, name)
+        }
+    }
+
+    /// Adds the frames defined inside a model whose frame is `scope`.
+    fn add_model_contents(&mut self, model: &SdfModel, scope: &str) -> Result<(), String> {
+        let model_frame = Self::model_frame(scope);
+        for link in model.link.iter() {
+            let name = scoped_name(scope, &link.name);
+            let relative_to = relative_to(link.pose.as_ref())
+                .map(|relative_to| Self::resolve(scope, relative_to))
+                .unwrap_or_else(|| model_frame.clone());
+            self.insert(
+                &name,
+                FrameNode {
+                    kind: FrameKind::Link,
+                    relative_to,
+                    pose: isometry(link.pose.as_ref())?,
+                    attached_to: name.clone(),
+                },
+            )?;
+        }
+        for nested in model.model.iter() {
+            self.add_model(nested, scope)?;
+        }
+        for joint in model.joint.iter() {
+            let name = scoped_name(scope, &joint.name);
+            let child = Self::resolve(scope, &joint.child);
+            let relative_to = relative_to(joint.pose.as_ref())
+                .map(|relative_to| Self::resolve(scope, relative_to))
+                .unwrap_or_else(|| child.clone());
+            self.insert(
+                &name,
+                FrameNode {
+                    kind: FrameKind::Joint,
+                    relative_to,
+                    pose: isometry(joint.pose.as_ref())?,
+                    attached_to: child,
+                },
+            )?;
+        }
+        for frame in model.frame.iter() {
+            let name = scoped_name(scope, &frame.name);
+            let attached_to = match frame.attached_to.as_deref() {
+                Some(attached_to) if !attached_to.is_empty() => Self::resolve(scope, attached_to),
+                _ => model_frame.clone(),
+            };
+            let relative_to = relative_to(frame.pose.as_ref())
+                .map(|relative_to| Self::resolve(scope, relative_to))
+                .unwrap_or_else(|| attached_to.clone());
+            self.insert(
+                &name,
+                FrameNode {
+                    kind: FrameKind::Frame,
+                    relative_to,
+                    pose: isometry(frame.pose.as_ref())?,
+                    attached_to,
+                },
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Returns the name of the root frame.
+    pub fn root(&self) -> &str {
+        &self.root
+    }
+
+    /// Returns true if the graph contains the frame.
+    pub fn contains(&self, frame: &str) -> bool {
+        self.frames.contains_key(frame)
+    }
+
+    /// Iterates over the names of the frames in the order they were defined.
+    pub fn frames(&self) -> impl Iterator<Item = &str> {
+        self.order.iter().map(String::as_str)
+    }
+
+    /// Returns the kind of element that defines a frame.
+    pub fn kind(&self, frame: &str) -> Option<FrameKind> {
+        self.frames.get(frame).map(|node| node.kind)
+    }
+
+    /// Returns the frame the pose of a frame is expressed in.
+    pub fn relative_to(&self, frame: &str) -> Option<&str> {
+        self.frames.get(frame).map(|node| node.relative_to.as_str())
+    }
+
+    /// Returns the frame a frame is directly attached to.
+    pub fn attached_to(&self, frame: &str) -> Option<&str> {
+        self.frames.get(frame).map(|node| node.attached_to.as_str())
+    }
+
+    fn node(&self, frame: &str) -> Result<&FrameNode, String> {
+        self.frames
+            .get(frame)
+            .ok_or_else(|| format!("Frame {} not found", frame))
+    }
+
+    /// Returns the link a frame moves with by following its `attached_to` chain. Frames attached
+    /// to the root, such as the frames of a model without links, return the root.
+    pub fn attached_link(&self, frame: &str) -> Result<String, String> {
+        let mut current = frame.to_string();
+        let mut visited = HashSet::new();
+        loop {
+            let node = self.node(&current)?;
+            if node.kind == FrameKind::Link || node.attached_to.is_empty() {
+                return Ok(current);
+            }
+            if !visited.insert(current.clone()) {
+                return Err(format!("Cycle in the attached_to chain of {}", frame));
+            }
+            current = node.attached_to.clone();
+        }
+    }
+
+    /// Returns the pose of a frame relative to the root of the graph.
+    pub fn pose_in_root(&self, frame: &str) -> Result<Isometry3<f64>, String> {
+        let mut pose = Isometry3::identity();
+        let mut current = frame;
+        let mut visited = HashSet::new();
+        while current != self.root {
+            if !visited.insert(current) {
+                return Err(format!("Cycle in the relative_to chain of {}", frame));
+            }
+            let node = self.node(current)?;
+            pose = node.pose * pose;
+            current = &node.relative_to;
+        }
+        Ok(pose)
+    }
+
+    /// Returns the pose of `frame` relative to `relative_to`.
+    pub fn pose(&self, frame: &str, relative_to: &str) -> Result<Isometry3<f64>, String> {
+        Ok(self.pose_in_root(relative_to)?.inverse() * self.pose_in_root(frame)?)
+    }
+
+    /// Returns the pose, relative to the root, of an element that is not a frame itself such as
+    /// a visual. `scope` is the scope of the model containing the element and `default` the frame
+    /// its pose is relative to when `relative_to` is not set.
+    pub fn resolve_pose(
+        &self,
+        pose: Option<&SdfPose>,
+        scope: &str,
+        default: &str,
+    ) -> Result<Isometry3<f64>, String> {
+        let frame = match relative_to(pose) {
+            Some(relative_to) => self.resolve_in_parent(scope, relative_to),
+            None => default.to_string(),
+        };
+        Ok(self.pose_in_root(&frame)? * isometry(pose)?)
+    }
+}
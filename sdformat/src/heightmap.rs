@@ -0,0 +1,149 @@
+//! Heightmaps: grids of heights sampled from grayscale images.
+//!
+//! The grid covers `size.x` by `size.y` centered on `pos`, the first row of the image is the edge
+//! at the largest y and the first column the edge at the smallest x. Heights go from `pos.z` for
+//! black to `pos.z + size.z` for white, the largest value of the samples of the image, as in
+//! Gazebo. With the `heightmap` feature, [`load_heightmap`] reads the grid of a heightmap
+//! geometry from a PNG or PGM image.
+
+use nalgebra::Vector3;
+
+#[cfg(feature = "heightmap")]
+use crate::mesh::MeshResolver;
+use crate::mesh::TriangleMesh;
+use crate::SdfHeightmapShape;
+
+#[cfg(feature = "heightmap")]
+mod image;
+
+/// A grid of heights placed in the frame of a heightmap geometry.
+#[derive(Clone, Debug, PartialEq)]
+pub struct HeightGrid {
+    pub columns: usize,
+    pub rows: usize,
+    /// The values of the grid, row by row, between 0 and 1.
+    pub values: Vec<f64>,
+    pub size: Vector3<f64>,
+    pub pos: Vector3<f64>,
+    /// The number of mesh vertices per datum along each axis.
+    pub sampling: usize,
+}
+
+impl HeightGrid {
+    /// Places a grid of values between 0 and 1 with the size, position and sampling of a
+    /// heightmap geometry.
+    pub fn from_shape(
+        shape: &SdfHeightmapShape,
+        columns: usize,
+        rows: usize,
+        values: Vec<f64>,
+    ) -> Result<Self, String> {
+        if columns < 2 || rows < 2 || values.len() != columns * rows {
+            return Err(format!(
+                "A heightmap needs at least 2 by 2 values, found {} values for {} by {}",
+                values.len(),
+                columns,
+                rows
+            ));
+        }
+        let sampling = match shape.sampling.as_deref() {
+            Some(sampling) => sampling
+                .trim()
+                .parse::<usize>()
+                .ok()
+                .filter(|sampling| *sampling > 0)
+                .ok_or_else(|| format!("Invalid heightmap sampling {}", sampling))?,
+            None => 1,
+        };
+        Ok(Self {
+            columns,
+            rows,
+            values,
+            size: shape
+                .size
+                .as_ref()
+                .map_or(Vector3::repeat(1.0), |size| size.0),
+            pos: shape.pos.as_ref().map_or(Vector3::zeros(), |pos| pos.0),
+            sampling,
+        })
+    }
+
+    /// Returns the height of the grid at a column and row, which may be fractional.
+    fn sample(&self, column: f64, row: f64) -> f64 {
+        let (left, top) = (
+            (column.floor() as usize).min(self.columns - 2),
+            (row.floor() as usize).min(self.rows - 2),
+        );
+        let (u, v) = (column - left as f64, row - top as f64);
+        let value = |column: usize, row: usize| self.values[row * self.columns + column];
+        let upper = value(left, top) * (1.0 - u) + value(left + 1, top) * u;
+        let lower = value(left, top + 1) * (1.0 - u) + value(left + 1, top + 1) * u;
+        self.pos.z + (upper * (1.0 - v) + lower * v) * self.size.z
+    }
+
+    /// Returns the height of the surface above a point of the geometry frame, interpolated
+    /// between the values of the grid, or `None` outside of the grid.
+    pub fn height_at(&self, x: f64, y: f64) -> Option<f64> {
+        let u = (x - self.pos.x) / self.size.x + 0.5;
+        let v = 0.5 - (y - self.pos.y) / self.size.y;
+        if !(0.0..=1.0).contains(&u) || !(0.0..=1.0).contains(&v) {
+            return None;
+        }
+        Some(self.sample(u * (self.columns - 1) as f64, v * (self.rows - 1) as f64))
+    }
+
+    /// Returns the surface of the grid, with `sampling` vertices per datum along each axis and
+    /// triangles facing up.
+    pub fn to_mesh(&self) -> TriangleMesh {
+        let columns = (self.columns - 1) * self.sampling + 1;
+        let rows = (self.rows - 1) * self.sampling + 1;
+        let mut mesh = TriangleMesh::default();
+        for row in 0..rows {
+            for column in 0..columns {
+                let (u, v) = (
+                    column as f64 / (columns - 1) as f64,
+                    row as f64 / (rows - 1) as f64,
+                );
+                mesh.vertices.push(Vector3::new(
+                    self.pos.x + (u - 0.5) * self.size.x,
+                    self.pos.y + (0.5 - v) * self.size.y,
+                    self.sample(u * (self.columns - 1) as f64, v * (self.rows - 1) as f64),
+                ));
+            }
+        }
+        for row in 0..rows - 1 {
+            for column in 0..columns - 1 {
+                // Rows go towards -y, so this order is counterclockwise seen from above
+                let corner = row * columns + column;
+                let below = corner + columns;
+                mesh.triangles.push([corner, below, corner + 1]);
+                mesh.triangles.push([corner + 1, below, below + 1]);
+            }
+        }
+        mesh
+    }
+}
+
+/// Loads the grid of a heightmap geometry from a PNG or PGM image. Color images use their first
+/// channel. Digital elevation models are not supported.
+#[cfg(feature = "heightmap")]
+pub fn load_heightmap(
+    shape: &SdfHeightmapShape,
+    resolver: &dyn MeshResolver,
+) -> Result<HeightGrid, String> {
+    let path = resolver.resolve(&shape.uri)?;
+    let bytes =
+        std::fs::read(&path).map_err(|e| format!("Could not read {}: {}", path.display(), e))?;
+    let extension = path
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .unwrap_or_default()
+        .to_lowercase();
+    let image = match extension.as_str() {
+        "png" => image::read_png(&bytes),
+        "pgm" => image::read_pgm(&bytes),
+        _ => Err(format!("Unsupported heightmap format {}", extension)),
+    }
+    .map_err(|e| format!("Could not load {}: {}", path.display(), e))?;
+    HeightGrid::from_shape(shape, image.width, image.height, image.values)
+}
@@ -0,0 +1,115 @@
+//! Grayscale values of PNG and PGM images.
+
+use png::{BitDepth, Decoder, Transformations};
+
+/// The values of the pixels of an image, row by row from the top, between 0 and 1.
+pub(super) struct Image {
+    pub(super) width: usize,
+    pub(super) height: usize,
+    pub(super) values: Vec<f64>,
+}
+
+/// Reads a PNG image with `png`, whose default limits bound the memory of the decoded image.
+pub(super) fn read_png(bytes: &[u8]) -> Result<Image, String> {
+    let mut decoder = Decoder::new(bytes);
+    // Palettes become colors and samples of less than 8 bits become bytes
+    decoder.set_transformations(Transformations::EXPAND);
+    let mut reader = decoder.read_info().map_err(|e| e.to_string())?;
+    let mut buffer = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buffer).map_err(|e| e.to_string())?;
+    let (width, height) = (info.width as usize, info.height as usize);
+    let channels = info.color_type.samples();
+    // Only the first channel is read and values are relative to the largest sample value, as
+    // in Gazebo
+    let maximum = ((1u32 << info.bit_depth as u32) - 1) as f64;
+    let sample = |row: &[u8], index: usize| match info.bit_depth {
+        BitDepth::Sixteen => u16::from_be_bytes([row[index * 2], row[index * 2 + 1]]) as f64,
+        _ => row[index] as f64,
+    };
+    let mut values = Vec::with_capacity(width * height);
+    for row in buffer.chunks_exact(info.line_size).take(height) {
+        for column in 0..width {
+            values.push(sample(row, column * channels) / maximum);
+        }
+    }
+    Ok(Image {
+        width,
+        height,
+        values,
+    })
+}
+
+/// Reads a binary or ASCII PGM image.
+pub(super) fn read_pgm(bytes: &[u8]) -> Result<Image, String> {
+    // The header is made of four tokens, comments start with #
+    let mut tokens = vec![];
+    let mut offset = 0;
+    while tokens.len() < 4 {
+        while offset < bytes.len() && bytes[offset].is_ascii_whitespace() {
+            offset += 1;
+        }
+        if bytes.get(offset) == Some(&b'#') {
+            while offset < bytes.len() && bytes[offset] != b'\n' {
+                offset += 1;
+            }
+            continue;
+        }
+        let start = offset;
+        while offset < bytes.len() && !bytes[offset].is_ascii_whitespace() {
+            offset += 1;
+        }
+        if start == offset {
+            return Err("Truncated PGM header".to_string());
+        }
+        tokens.push(String::from_utf8_lossy(&bytes[start..offset]).into_owned());
+    }
+    let number = |token: &str| {
+        token
+            .parse::<usize>()
+            .map_err(|_| format!("Invalid PGM header value {}", token))
+    };
+    let (width, height, maximum) = (
+        number(&tokens[1])?,
+        number(&tokens[2])?,
+        number(&tokens[3])?,
+    );
+    if maximum == 0 || maximum > 65535 {
+        return Err(format!("Invalid PGM maximum value {}", maximum));
+    }
+    let count = width * height;
+    let values: Vec<f64> = match tokens[0].as_str() {
+        "P5" => {
+            let data = &bytes[(offset + 1).min(bytes.len())..];
+            let size = if maximum > 255 { 2 } else { 1 };
+            if data.len() < count * size {
+                return Err("Truncated PGM data".to_string());
+            }
+            data.chunks_exact(size)
+                .take(count)
+                .map(|value| match size {
+                    2 => u16::from_be_bytes([value[0], value[1]]) as f64,
+                    _ => value[0] as f64,
+                })
+                .collect()
+        }
+        "P2" => String::from_utf8_lossy(&bytes[offset..])
+            .split_whitespace()
+            .take(count)
+            .map(|value| {
+                value
+                    .parse::<f64>()
+                    .map_err(|_| format!("Invalid PGM value {}", value))
+            })
+            .collect::<Result<_, _>>()?,
+        other => return Err(format!("Unsupported PGM format {}", other)),
+    };
+    if values.len() != count {
+        return Err("Truncated PGM data".to_string());
+    }
+    // Values are relative to the maximum value of the header
+    Ok(Image {
+        width,
+        height,
+        values: values.iter().map(|value| value / maximum as f64).collect(),
+    })
+}
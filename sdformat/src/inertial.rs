@@ -0,0 +1,504 @@
+//! Mass properties of links: physical checks and their computation from collision geometries.
+//!
+//! A link must have a positive mass and an inertia matrix that is positive definite with
+//! principal moments satisfying the triangle inequality, `a + b >= c`, as the inertia of any
+//! rigid body does. Inertias that break these rules make simulations unstable.
+//!
+//! Like `<inertial auto="true">` of SDFormat 1.11, [`SdfLink::auto_inertial`] computes the mass
+//! properties of a link from its collisions and a density. [`model_mass_properties`] and
+//! [`subtree_mass_properties`] combine the mass properties of several links.
+
+use std::collections::HashMap;
+use std::f64::consts::PI;
+
+use nalgebra::{Isometry3, Matrix3, Translation3, UnitQuaternion, Vector3};
+
+use crate::frames::{scoped_name, FrameGraph};
+use crate::kinematics::{place, JointPosition, KinematicTree, WorldPoses};
+use crate::mesh::TriangleMesh;
+use crate::polyline::extrude;
+use crate::{
+    SdfGeometry, SdfInertial, SdfInertialInertia, SdfInertialPose, SdfLink, SdfMeshShape, SdfModel,
+    SdfWorld,
+};
+
+/// The relative tolerance of the checks on principal moments.
+const TOLERANCE: f64 = 1e-9;
+
+/// A problem with the mass properties of a link.
+#[derive(Clone, Debug, PartialEq)]
+pub struct InertialIssue {
+    /// The scoped name of the link, e.g. `robot::arm::upper`.
+    pub link: String,
+    pub message: String,
+    /// A corrected inertial, when one can be derived from the original.
+    pub fix: Option<SdfInertial>,
+}
+
+impl SdfInertialInertia {
+    /// Returns the inertia matrix. Only the upper triangle is written in SDFormat, so the
+    /// matrix is symmetric.
+    pub fn matrix(&self) -> Matrix3<f64> {
+        let (ixy, ixz, iyz) = (
+            self.ixy.unwrap_or_default(),
+            self.ixz.unwrap_or_default(),
+            self.iyz.unwrap_or_default(),
+        );
+        Matrix3::new(
+            self.ixx, ixy, ixz, //
+            ixy, self.iyy, iyz, //
+            ixz, iyz, self.izz,
+        )
+    }
+
+    /// Builds an inertia from the upper triangle of a matrix.
+    pub fn from_matrix(matrix: &Matrix3<f64>) -> Self {
+        Self {
+            ixx: matrix[(0, 0)],
+            ixy: Some(matrix[(0, 1)]),
+            ixz: Some(matrix[(0, 2)]),
+            iyy: matrix[(1, 1)],
+            iyz: Some(matrix[(1, 2)]),
+            izz: matrix[(2, 2)],
+            ..Default::default()
+        }
+    }
+}
+
+/// Mass properties expressed in some frame, the inertia is taken at the center of mass about
+/// the axes of that frame.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MassProperties {
+    pub mass: f64,
+    pub center: Vector3<f64>,
+    pub inertia: Matrix3<f64>,
+}
+
+impl MassProperties {
+    /// Returns the properties of an inertial in the frame its pose is relative to. Missing
+    /// values take their defaults from the specification.
+    pub fn from_inertial(inertial: &SdfInertial) -> Result<Self, String> {
+        let pose = match inertial.pose.as_ref() {
+            Some(pose) => pose.to_pose().get_pose()?.isometry(),
+            None => Isometry3::identity(),
+        };
+        let matrix = match inertial.inertia.as_ref() {
+            Some(inertia) => inertia.matrix(),
+            None => Matrix3::identity(),
+        };
+        let rotation = pose.rotation.to_rotation_matrix();
+        Ok(Self {
+            mass: inertial.mass.unwrap_or(1.0),
+            center: pose.translation.vector,
+            inertia: rotation.matrix() * matrix * rotation.matrix().transpose(),
+        })
+    }
+
+    /// Returns an inertial with these properties, its pose has no rotation.
+    pub fn to_inertial(&self) -> SdfInertial {
+        let pose =
+            Isometry3::from_parts(Translation3::from(self.center), UnitQuaternion::identity());
+        SdfInertial {
+            mass: Some(self.mass),
+            pose: Some(SdfInertialPose::from_isometry(&pose)),
+            inertia: Some(SdfInertialInertia::from_matrix(&self.inertia)),
+            ..Default::default()
+        }
+    }
+
+    /// Expresses the properties in the frame `pose` is relative to.
+    pub fn transformed(&self, pose: &Isometry3<f64>) -> Self {
+        let rotation = pose.rotation.to_rotation_matrix();
+        Self {
+            mass: self.mass,
+            center: pose.transform_point(&self.center.into()).coords,
+            inertia: rotation.matrix() * self.inertia * rotation.matrix().transpose(),
+        }
+    }
+
+    /// Combines the properties of two bodies expressed in the same frame.
+    pub fn combined(&self, other: &Self) -> Self {
+        let mass = self.mass + other.mass;
+        if mass <= 0.0 {
+            return self.clone();
+        }
+        let center = (self.center * self.mass + other.center * other.mass) / mass;
+        // Parallel axis theorem for each body
+        let shifted = |body: &Self| {
+            let d = body.center - center;
+            body.inertia + (Matrix3::identity() * d.dot(&d) - d * d.transpose()) * body.mass
+        };
+        Self {
+            mass,
+            center,
+            inertia: shifted(self) + shifted(other),
+        }
+    }
+
+    /// Returns the properties of a solid of uniform density with principal moments along the
+    /// axes of its frame, centered on its origin.
+    fn centered(mass: f64, moments: Vector3<f64>) -> Self {
+        Self {
+            mass,
+            center: Vector3::zeros(),
+            inertia: Matrix3::from_diagonal(&moments),
+        }
+    }
+
+    /// Returns the properties of a solid geometry of uniform density in the geometry frame.
+    /// Meshes must be loaded first, see [`MassProperties::of_mesh`].
+    pub fn of_geometry(geometry: &SdfGeometry, density: f64) -> Result<Self, String> {
+        match geometry {
+            SdfGeometry::Box(shape) => {
+                let [x, y, z] = shape.size.0.map(|size| size * size).into();
+                let mass = density * shape.size.0.product();
+                Ok(Self::centered(
+                    mass,
+                    Vector3::new(y + z, x + z, x + y) * mass / 12.0,
+                ))
+            }
+            SdfGeometry::Cylinder(shape) => {
+                let (r2, l2) = (shape.radius.powi(2), shape.length.powi(2));
+                let mass = density * PI * r2 * shape.length;
+                let side = mass * (3.0 * r2 + l2) / 12.0;
+                Ok(Self::centered(
+                    mass,
+                    Vector3::new(side, side, mass * r2 / 2.0),
+                ))
+            }
+            SdfGeometry::Sphere(shape) => {
+                let mass = density * 4.0 / 3.0 * PI * shape.radius.powi(3);
+                let moment = 0.4 * mass * shape.radius.powi(2);
+                Ok(Self::centered(mass, Vector3::repeat(moment)))
+            }
+            SdfGeometry::Capsule(shape) => {
+                // A cylinder along z capped by two hemispheres
+                let (r, l) = (shape.radius, shape.length);
+                let cylinder = density * PI * r * r * l;
+                let spheres = density * 4.0 / 3.0 * PI * r.powi(3);
+                let side = cylinder * (l * l / 12.0 + r * r / 4.0)
+                    + spheres * (0.4 * r * r + l * l / 4.0 + 3.0 * l * r / 8.0);
+                let axial = cylinder * r * r / 2.0 + spheres * 0.4 * r * r;
+                Ok(Self::centered(
+                    cylinder + spheres,
+                    Vector3::new(side, side, axial),
+                ))
+            }
+            SdfGeometry::Ellipsoid(shape) => {
+                let [a, b, c] = shape.radii.0.map(|radius| radius * radius).into();
+                let mass = density * 4.0 / 3.0 * PI * shape.radii.0.product();
+                Ok(Self::centered(
+                    mass,
+                    Vector3::new(b + c, a + c, a + b) * mass / 5.0,
+                ))
+            }
+            SdfGeometry::Mesh(shape) => Err(format!(
+                "The mesh {} must be loaded to compute its mass properties",
+                shape.uri
+            )),
+            SdfGeometry::Polyline(shapes) => Self::of_mesh(&extrude(shapes)?, density),
+            other => Err(format!(
+                "Mass properties of {} geometries are not supported",
+                geometry_name(other)
+            )),
+        }
+    }
+
+    /// Returns the properties of the solid of uniform density bounded by a closed mesh, in the
+    /// frame of its vertices. Meshes whose triangles face inwards are accepted.
+    pub fn of_mesh(mesh: &TriangleMesh, density: f64) -> Result<Self, String> {
+        if !mesh.is_closed() {
+            return Err("The mesh is not closed".to_string());
+        }
+        // Sum over the tetrahedra joining the origin to each triangle, each weighted by six
+        // times its signed volume
+        let mut volume = 0.0;
+        let mut moment = Vector3::zeros();
+        let mut covariance = Matrix3::zeros();
+        for triangle in mesh.triangles.iter() {
+            let [a, b, c] = triangle.map(|index| mesh.vertices[index]);
+            let weight = a.dot(&b.cross(&c));
+            let sum = a + b + c;
+            volume += weight / 6.0;
+            moment += sum * weight / 24.0;
+            covariance +=
+                (a * a.transpose() + b * b.transpose() + c * c.transpose() + sum * sum.transpose())
+                    * weight
+                    / 120.0;
+        }
+        if volume.abs() < 1e-15 {
+            return Err("The mesh has no volume".to_string());
+        }
+        let (mass, center) = (density * volume, moment / volume);
+        // The inertia about the origin, moved to the center of mass
+        let covariance = covariance * density;
+        let origin = Matrix3::identity() * covariance.trace() - covariance;
+        let inertia = origin
+            - (Matrix3::identity() * center.dot(&center) - center * center.transpose()) * mass;
+        Ok(Self {
+            mass: mass.abs(),
+            center,
+            inertia: inertia * mass.signum(),
+        })
+    }
+}
+
+fn geometry_name(geometry: &SdfGeometry) -> &'static str {
+    match geometry {
+        SdfGeometry::Empty => "empty",
+        SdfGeometry::Box(_) => "box",
+        SdfGeometry::Capsule(_) => "capsule",
+        SdfGeometry::Cylinder(_) => "cylinder",
+        SdfGeometry::Ellipsoid(_) => "ellipsoid",
+        SdfGeometry::Heightmap(_) => "heightmap",
+        SdfGeometry::Image(_) => "image",
+        SdfGeometry::Mesh(_) => "mesh",
+        SdfGeometry::Plane(_) => "plane",
+        SdfGeometry::Polyline(_) => "polyline",
+        SdfGeometry::Sphere(_) => "sphere",
+    }
+}
+
+impl SdfLink {
+    /// Computes the inertial of the link from its collisions, as solids of uniform density.
+    /// Collisions with empty geometries are ignored. Links with meshes must use
+    /// [`SdfLink::auto_inertial_with_meshes`].
+    pub fn auto_inertial(&self, density: f64) -> Result<SdfInertial, String> {
+        self.auto_inertial_with_meshes(density, |shape| {
+            Err(format!(
+                "The mesh {} must be loaded to compute its mass properties",
+                shape.uri
+            ))
+        })
+    }
+
+    /// Computes the inertial of the link from its collisions, `load` returns the closed surface
+    /// of mesh geometries before their scale is applied.
+    pub fn auto_inertial_with_meshes(
+        &self,
+        density: f64,
+        mut load: impl FnMut(&SdfMeshShape) -> Result<TriangleMesh, String>,
+    ) -> Result<SdfInertial, String> {
+        let mut total = MassProperties::default();
+        for collision in self.collision.iter() {
+            let pose = match collision.pose.as_ref() {
+                Some(pose) => {
+                    if pose
+                        .relative_to
+                        .as_deref()
+                        .is_some_and(|frame| !frame.is_empty() && frame != self.name)
+                    {
+                        return Err(format!(
+                            "The pose of collision {} is relative to another frame than its link",
+                            collision.name
+                        ));
+                    }
+                    pose.get_pose()?.isometry()
+                }
+                None => Isometry3::identity(),
+            };
+            let properties = match &collision.geometry {
+                SdfGeometry::Empty => continue,
+                SdfGeometry::Mesh(shape) => {
+                    let mut mesh = load(shape)?;
+                    if let Some(scale) = shape.scale.as_ref() {
+                        mesh = mesh.scaled(&scale.0);
+                    }
+                    MassProperties::of_mesh(&mesh, density)
+                        .map_err(|e| format!("{} in collision {}", e, collision.name))?
+                }
+                geometry => MassProperties::of_geometry(geometry, density)?,
+            };
+            total = total.combined(&properties.transformed(&pose));
+        }
+        if total.mass <= 0.0 {
+            return Err(format!("Link {} has no collision with a volume", self.name));
+        }
+        Ok(total.to_inertial())
+    }
+}
+
+/// Collects the links of a model and of its nested models by scoped name.
+fn scoped_links<'a>(model: &'a SdfModel, scope: &str, links: &mut HashMap<String, &'a SdfLink>) {
+    for link in model.link.iter() {
+        links.insert(scoped_name(scope, &link.name), link);
+    }
+    for nested in model.model.iter() {
+        scoped_links(nested, &scoped_name(scope, &nested.name), links);
+    }
+}
+
+/// Combines the mass properties of links, by scoped name, expressed in `frame`.
+fn combined_links<'a>(
+    model: &SdfModel,
+    poses: &WorldPoses,
+    links: impl Iterator<Item = &'a String>,
+    frame: &str,
+) -> Result<MassProperties, String> {
+    let frame = FrameGraph::resolve("", frame);
+    let frame_pose = match frame.as_str() {
+        "world" => Isometry3::identity(),
+        frame => *poses
+            .links
+            .get(frame)
+            .or_else(|| poses.frames.get(frame))
+            .ok_or_else(|| format!("Frame {} not found", frame))?,
+    };
+    let mut elements = HashMap::new();
+    scoped_links(model, "", &mut elements);
+    let mut total = MassProperties::default();
+    for name in links {
+        // Links without an inertial have the default mass properties of the specification
+        let properties = match elements[name].inertial.as_ref() {
+            Some(inertial) => MassProperties::from_inertial(inertial)?,
+            None => MassProperties::from_inertial(&SdfInertial::default())?,
+        };
+        let pose = frame_pose.inverse() * poses.links[name];
+        total = total.combined(&properties.transformed(&pose));
+    }
+    Ok(total)
+}
+
+/// Computes the total mass, center of mass and inertia of all the links of a model and of its
+/// nested models, expressed in `frame`, for the given joint positions by scoped joint name.
+/// The frame is a scoped frame of the model, `__model__` or `world`.
+pub fn model_mass_properties(
+    model: &SdfModel,
+    positions: &HashMap<String, JointPosition>,
+    frame: &str,
+) -> Result<MassProperties, String> {
+    let tree = KinematicTree::from_model(model)?;
+    let poses = place(model, &tree, positions)?;
+    combined_links(model, &poses, tree.links().iter(), frame)
+}
+
+/// Computes the mass properties of the links moved by a joint: its child link and all the links
+/// below it in the kinematic tree. See [`model_mass_properties`].
+pub fn subtree_mass_properties(
+    model: &SdfModel,
+    positions: &HashMap<String, JointPosition>,
+    joint: &str,
+    frame: &str,
+) -> Result<MassProperties, String> {
+    let tree = KinematicTree::from_model(model)?;
+    let joint = tree
+        .joint(joint)
+        .ok_or_else(|| format!("Joint {} not found", joint))?;
+    let mut subtree = vec![joint.child.clone()];
+    let mut next = 0;
+    while next < subtree.len() {
+        let children: Vec<_> = tree.children(&subtree[next]).map(str::to_string).collect();
+        subtree.extend(children);
+        next += 1;
+    }
+    let poses = place(model, &tree, positions)?;
+    combined_links(model, &poses, subtree.iter(), frame)
+}
+
+/// Returns the closest physically valid matrix with the same principal axes: principal moments
+/// are raised to a small positive value and the largest one is lowered to the sum of the other
+/// two. There is no fix for matrices without a positive principal moment.
+fn fixed_matrix(matrix: &Matrix3<f64>) -> Option<Matrix3<f64>> {
+    let eigen = matrix.symmetric_eigen();
+    let largest = eigen.eigenvalues.max();
+    if largest <= 0.0 {
+        return None;
+    }
+    let mut moments = eigen.eigenvalues.map(|moment| moment.max(largest * 1e-3));
+    let (index, _) = moments.argmax();
+    let others = moments.sum() - moments[index];
+    moments[index] = moments[index].min(others);
+    Some(eigen.eigenvectors * Matrix3::from_diagonal(&moments) * eigen.eigenvectors.transpose())
+}
+
+/// Checks the mass properties of a link, `link` is its scoped name. Missing values take their
+/// defaults from the specification: a mass of 1 and a unit inertia.
+pub fn check_inertial(inertial: &SdfInertial, link: &str) -> Vec<InertialIssue> {
+    let mut issues = vec![];
+    let mut issue = |message: String, fix: Option<SdfInertial>| {
+        issues.push(InertialIssue {
+            link: link.to_string(),
+            message,
+            fix,
+        })
+    };
+
+    let mass = inertial.mass.unwrap_or(1.0);
+    if !(mass.is_finite() && mass > 0.0) {
+        issue(
+            format!("Link {} has a mass of {}, it must be positive", link, mass),
+            None,
+        );
+    }
+
+    let matrix = match inertial.inertia.as_ref() {
+        Some(inertia) => inertia.matrix(),
+        None => return issues,
+    };
+    if matrix.iter().any(|value| !value.is_finite()) {
+        issue(
+            format!(
+                "The inertia of link {} has values that are not finite",
+                link
+            ),
+            None,
+        );
+        return issues;
+    }
+    let fix = fixed_matrix(&matrix).map(|matrix| SdfInertial {
+        inertia: Some(SdfInertialInertia::from_matrix(&matrix)),
+        ..inertial.clone()
+    });
+    let moments: Vector3<f64> = matrix.symmetric_eigenvalues();
+    let tolerance = moments.amax() * TOLERANCE;
+    if moments.min() <= tolerance {
+        issue(
+            format!(
+                "The inertia of link {} is not positive definite, its principal moments are {}, \
+                 {} and {}",
+                link, moments[0], moments[1], moments[2]
+            ),
+            fix,
+        );
+    } else if (0..3).any(|index| 2.0 * moments[index] > moments.sum() + tolerance) {
+        issue(
+            format!(
+                "The principal moments of inertia of link {}, {}, {} and {}, do not satisfy the \
+                 triangle inequality",
+                link, moments[0], moments[1], moments[2]
+            ),
+            fix,
+        );
+    }
+    issues
+}
+
+/// Checks the mass properties of the links of a model and of its nested models, `scope` is
+/// prepended to the names of the links.
+fn check_scope(model: &SdfModel, scope: &str, issues: &mut Vec<InertialIssue>) {
+    for link in model.link.iter() {
+        if let Some(inertial) = link.inertial.as_ref() {
+            issues.extend(check_inertial(inertial, &scoped_name(scope, &link.name)));
+        }
+    }
+    for nested in model.model.iter() {
+        check_scope(nested, &scoped_name(scope, &nested.name), issues);
+    }
+}
+
+/// Checks the mass properties of the links of a model. Links are named relative to the model.
+pub fn check_model(model: &SdfModel) -> Vec<InertialIssue> {
+    let mut issues = vec![];
+    check_scope(model, "", &mut issues);
+    issues
+}
+
+/// Checks the mass properties of the links of all the models of a world. Links are named with
+/// the names of their models, e.g. `robot::base`.
+pub fn check_world(world: &SdfWorld) -> Vec<InertialIssue> {
+    let mut issues = vec![];
+    for model in world.model.iter() {
+        check_scope(model, &model.name, &mut issues);
+    }
+    issues
+}
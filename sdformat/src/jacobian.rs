@@ -0,0 +1,175 @@
+//! Jacobians of the frames of a model, relating the velocities of the joints to the velocity of
+//! a frame.
+//!
+//! Each column of a Jacobian is a degree of freedom of a joint on the chain from the root of the
+//! tree to the link the frame is attached to, in order from the root. The first three rows are
+//! the linear velocity and the last three the angular velocity, both in world coordinates.
+
+use std::collections::HashMap;
+
+use nalgebra::{Isometry3, Matrix6xX, Unit, UnitQuaternion, Vector3, Vector6};
+
+use crate::frames::FrameGraph;
+use crate::kinematics::{place, JointPosition, KinematicTree, TreeJoint};
+use crate::SdfModel;
+
+/// A degree of freedom of a joint, a column of a Jacobian.
+#[derive(Clone, Debug, PartialEq)]
+pub struct JointDof {
+    /// The scoped name of the joint.
+    pub joint: String,
+    /// The index of the axis: 0 for the axis, 1 for the second axis of universal and revolute2
+    /// joints, and 0 to 2 for rotations of ball joints about the x, y and z axes of the joint.
+    pub axis: usize,
+}
+
+/// The geometric Jacobian of a frame for a set of joint positions.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Jacobian {
+    pub columns: Vec<JointDof>,
+    /// The linear velocity of the origin of the frame, then its angular velocity.
+    pub matrix: Matrix6xX<f64>,
+    /// The world pose of the frame.
+    pub pose: Isometry3<f64>,
+}
+
+impl Jacobian {
+    /// Returns the spatial Jacobian, whose linear rows are the velocity of the point of the
+    /// moving frame that is at the world origin.
+    pub fn spatial(&self) -> Matrix6xX<f64> {
+        let origin = self.pose.translation.vector;
+        let mut spatial = self.matrix.clone();
+        for mut column in spatial.column_iter_mut() {
+            let angular = Vector3::new(column[3], column[4], column[5]);
+            let linear = Vector3::new(column[0], column[1], column[2]) + origin.cross(&angular);
+            column.fixed_rows_mut::<3>(0).copy_from(&linear);
+        }
+        spatial
+    }
+
+    /// Returns the velocity of the frame, linear then angular, for the joint velocities in the
+    /// order of the columns.
+    pub fn velocity(&self, velocities: &[f64]) -> Result<Vector6<f64>, String> {
+        if velocities.len() != self.columns.len() {
+            return Err(format!(
+                "Expected {} joint velocities, found {}",
+                self.columns.len(),
+                velocities.len()
+            ));
+        }
+        Ok(velocities
+            .iter()
+            .zip(self.matrix.column_iter())
+            .map(|(velocity, column)| column * *velocity)
+            .sum())
+    }
+}
+
+/// Returns the columns of a joint for a frame at `point`. `parent` is the world pose of the
+/// joint before its motion.
+fn joint_columns(
+    joint: &TreeJoint,
+    position: Option<&JointPosition>,
+    parent: &Isometry3<f64>,
+    point: &Vector3<f64>,
+) -> Result<Vec<Vector6<f64>>, String> {
+    let lever = point - parent.translation.vector;
+    let rotation = |axis: Vector3<f64>| {
+        let mut column = Vector6::zeros();
+        column.fixed_rows_mut::<3>(0).copy_from(&axis.cross(&lever));
+        column.fixed_rows_mut::<3>(3).copy_from(&axis);
+        column
+    };
+    let axis = |index: usize| -> Result<Vector3<f64>, String> {
+        let axis = match index {
+            0 => joint.axis.as_ref(),
+            _ => joint.axis2.as_ref(),
+        };
+        let xyz = axis.map(|axis| axis.xyz).unwrap_or_else(Vector3::z);
+        xyz.try_normalize(1e-12)
+            .map(|xyz| parent.rotation * xyz)
+            .ok_or_else(|| format!("Joint {} has an axis of zero length", joint.name))
+    };
+    match joint.r#type.as_str() {
+        "fixed" => Ok(vec![]),
+        "revolute" | "continuous" => Ok(vec![rotation(axis(0)?)]),
+        "prismatic" => {
+            let mut column = Vector6::zeros();
+            column.fixed_rows_mut::<3>(0).copy_from(&axis(0)?);
+            Ok(vec![column])
+        }
+        "screw" => {
+            let axis = axis(0)?;
+            let pitch = joint.screw_thread_pitch.unwrap_or(1.0);
+            let mut column = rotation(axis);
+            let linear = column.fixed_rows::<3>(0) + axis * pitch / (2.0 * std::f64::consts::PI);
+            column.fixed_rows_mut::<3>(0).copy_from(&linear);
+            Ok(vec![column])
+        }
+        "universal" | "revolute2" => {
+            // The second axis turns with the first one
+            let first = match position {
+                Some(JointPosition::Pair(first, _)) => *first,
+                _ => 0.0,
+            };
+            let first_axis = axis(0)?;
+            let turn = UnitQuaternion::from_axis_angle(&Unit::new_unchecked(first_axis), first);
+            Ok(vec![rotation(first_axis), rotation(turn * axis(1)?)])
+        }
+        "ball" => Ok((0..3)
+            .map(|index| rotation(parent.rotation * Vector3::ith(index, 1.0)))
+            .collect()),
+        other => Err(format!(
+            "Joints of type {} are not supported, found in joint {}",
+            other, joint.name
+        )),
+    }
+}
+
+/// Computes the geometric Jacobian of a frame of a model, given by scoped name, for the given
+/// joint positions. Joints without a position are at zero.
+pub fn jacobian(
+    model: &SdfModel,
+    positions: &HashMap<String, JointPosition>,
+    frame: &str,
+) -> Result<Jacobian, String> {
+    let tree = KinematicTree::from_model(model)?;
+    let graph = tree.frames();
+    let frame = FrameGraph::resolve("", frame);
+    let poses = place(model, &tree, positions)?;
+    let pose = *poses
+        .links
+        .get(&frame)
+        .or_else(|| poses.frames.get(&frame))
+        .ok_or_else(|| format!("Frame {} not found", frame))?;
+
+    let mut chain = vec![];
+    let mut link = graph.attached_link(&frame)?;
+    while let Some(joint) = tree.parent_joint(&link) {
+        chain.push(joint);
+        link = joint.parent.clone();
+    }
+
+    let mut columns = vec![];
+    let mut vectors = vec![];
+    for joint in chain.into_iter().rev() {
+        let position = positions.get(&joint.name);
+        // Joint frames move with the child link, undo the motion to find the parent side
+        let parent = poses.frames[&joint.name] * joint.motion(position)?.inverse();
+        for (axis, column) in joint_columns(joint, position, &parent, &pose.translation.vector)?
+            .into_iter()
+            .enumerate()
+        {
+            columns.push(JointDof {
+                joint: joint.name.clone(),
+                axis,
+            });
+            vectors.push(column);
+        }
+    }
+    Ok(Jacobian {
+        columns,
+        matrix: Matrix6xX::from_columns(&vectors),
+        pose,
+    })
+}
@@ -0,0 +1,596 @@
+//! The kinematic structure of models: the tree of links connected by joints.
+//!
+//! Links and joints of nested models are named with their scope, e.g. `arm::upper`, and joints
+//! whose parent or child is an explicit frame or a model are connected to the link that frame is
+//! attached to. Joints to `world` attach their child to the world. [`forward_kinematics`] places
+//! the frames of a model in the world for a set of joint positions.
+
+use std::collections::{HashMap, HashSet};
+
+use nalgebra::{Isometry3, Translation3, Unit, UnitQuaternion, Vector3};
+
+use crate::frames::{scoped_name, FrameGraph, FrameKind};
+use crate::{SdfJoint, SdfModel, SdfSensor, Vector3d};
+
+/// The limits of a joint axis.
+#[derive(Clone, Debug, PartialEq)]
+pub struct JointLimit {
+    pub lower: f64,
+    pub upper: f64,
+    pub effort: Option<f64>,
+    pub velocity: Option<f64>,
+}
+
+/// An axis of a joint, expressed in the joint frame.
+#[derive(Clone, Debug, PartialEq)]
+pub struct JointAxis {
+    pub xyz: Vector3<f64>,
+    pub limit: Option<JointLimit>,
+}
+
+/// A joint of the tree, between two links or between the world and a link.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TreeJoint {
+    /// The scoped name of the joint.
+    pub name: String,
+    pub r#type: String,
+    /// The parent link, or `world`.
+    pub parent: String,
+    pub child: String,
+    pub axis: Option<JointAxis>,
+    pub axis2: Option<JointAxis>,
+    /// The distance travelled along the axis of screw joints for each revolution, in meters.
+    /// Positive values are right-handed threads.
+    pub screw_thread_pitch: Option<f64>,
+}
+
+/// The links of a model and the joints connecting them.
+///
+/// Every link has at most one parent joint. Joints that would give a link a second parent, or
+/// close a cycle, are loop joints and are not part of the tree.
+#[derive(Clone, Debug)]
+pub struct KinematicTree {
+    links: Vec<String>,
+    joints: Vec<TreeJoint>,
+    /// The index of the parent joint of each link.
+    parents: HashMap<String, usize>,
+    /// The indices of the child joints of each link and of the world.
+    children: HashMap<String, Vec<usize>>,
+    loops: Vec<usize>,
+    order: Vec<String>,
+    graph: FrameGraph,
+}
+
+/// Returns the rotation from the frame an axis is expressed in to the frame of its joint.
+/// `joint` is the scoped name of the joint and `scope` the scope of its model.
+fn axis_rotation(
+    graph: &FrameGraph,
+    scope: &str,
+    joint: &str,
+    expressed_in: Option<&str>,
+) -> Result<UnitQuaternion<f64>, String> {
+    match expressed_in.filter(|frame| !frame.is_empty()) {
+        Some(frame) => Ok(graph
+            .pose(&FrameGraph::resolve(scope, frame), joint)?
+            .rotation),
+        None => Ok(UnitQuaternion::identity()),
+    }
+}
+
+fn tree_joint(
+    joint: &SdfJoint,
+    graph: &FrameGraph,
+    scope: &str,
+    parent: String,
+    child: String,
+) -> Result<TreeJoint, String> {
+    let name = scoped_name(scope, &joint.name);
+    // The deprecated thread pitch is in radians per meter, positive for left-handed threads
+    let screw_thread_pitch = match (joint.screw_thread_pitch, joint.thread_pitch) {
+        (Some(pitch), _) => pitch,
+        (None, Some(pitch)) if pitch != 0.0 => -2.0 * std::f64::consts::PI / pitch,
+        _ => 1.0,
+    };
+    let axis = match joint.axis.as_ref() {
+        Some(axis) => Some(JointAxis {
+            xyz: axis_rotation(graph, scope, &name, axis.xyz.expressed_in.as_deref())?
+                * axis.xyz.data.0,
+            limit: axis.limit.as_ref().map(|limit| JointLimit {
+                lower: limit.lower,
+                upper: limit.upper,
+                effort: limit.effort,
+                velocity: limit.velocity,
+            }),
+        }),
+        None => None,
+    };
+    let axis2 = match joint.axis2.as_ref() {
+        Some(axis) => Some(JointAxis {
+            xyz: axis_rotation(graph, scope, &name, axis.xyz.expressed_in.as_deref())?
+                * axis.xyz.data.0,
+            limit: (axis.limit.lower.is_some() || axis.limit.upper.is_some()).then(|| JointLimit {
+                lower: axis.limit.lower.unwrap_or(-1e16),
+                upper: axis.limit.upper.unwrap_or(1e16),
+                effort: axis.limit.effort,
+                velocity: axis.limit.velocity,
+            }),
+        }),
+        None => None,
+    };
+    Ok(TreeJoint {
+        screw_thread_pitch: (joint.r#type == "screw").then_some(screw_thread_pitch),
+        name,
+        r#type: joint.r#type.clone(),
+        parent,
+        child,
+        axis,
+        axis2,
+    })
+}
+
+impl SdfJoint {
+    /// Returns the normalized axis expressed in the joint frame. `model` is the model that
+    /// defines the joint, the frame the axis is expressed in is resolved in it.
+    pub fn axis_in_joint_frame(&self, model: &SdfModel) -> Result<Vector3<f64>, String> {
+        self.axis_in(model, &self.name)
+    }
+
+    /// Returns the normalized axis expressed in a frame of the model that defines the joint.
+    pub fn axis_in(&self, model: &SdfModel, frame: &str) -> Result<Vector3<f64>, String> {
+        let axis = self
+            .axis
+            .as_ref()
+            .ok_or_else(|| format!("Joint {} has no axis", self.name))?;
+        self.resolve_axis(
+            model,
+            &axis.xyz.data,
+            axis.xyz.expressed_in.as_deref(),
+            frame,
+        )
+    }
+
+    /// Returns the normalized second axis expressed in the joint frame.
+    pub fn axis2_in_joint_frame(&self, model: &SdfModel) -> Result<Vector3<f64>, String> {
+        self.axis2_in(model, &self.name)
+    }
+
+    /// Returns the normalized second axis expressed in a frame of the model that defines the
+    /// joint.
+    pub fn axis2_in(&self, model: &SdfModel, frame: &str) -> Result<Vector3<f64>, String> {
+        let axis = self
+            .axis2
+            .as_ref()
+            .ok_or_else(|| format!("Joint {} has no axis2", self.name))?;
+        self.resolve_axis(
+            model,
+            &axis.xyz.data,
+            axis.xyz.expressed_in.as_deref(),
+            frame,
+        )
+    }
+
+    fn resolve_axis(
+        &self,
+        model: &SdfModel,
+        xyz: &Vector3d,
+        expressed_in: Option<&str>,
+        frame: &str,
+    ) -> Result<Vector3<f64>, String> {
+        let xyz = Unit::try_new(xyz.0, 1e-12)
+            .ok_or_else(|| format!("Joint {} has an axis of zero length", self.name))?;
+        let graph = FrameGraph::from_model(model)?;
+        let in_joint = axis_rotation(&graph, "", &self.name, expressed_in)? * xyz;
+        let frame = FrameGraph::resolve("", frame);
+        Ok(graph.pose(&self.name, &frame)?.rotation * in_joint.into_inner())
+    }
+}
+
+impl KinematicTree {
+    /// Builds the tree of a model and of the models nested in it.
+    pub fn from_model(model: &SdfModel) -> Result<Self, String> {
+        let mut tree = Self {
+            links: vec![],
+            joints: vec![],
+            parents: HashMap::new(),
+            children: HashMap::new(),
+            loops: vec![],
+            order: vec![],
+            graph: FrameGraph::from_model(model)?,
+        };
+        tree.add_model(model, "")?;
+        for (idx, joint) in tree.joints.iter().enumerate() {
+            if joint.parent == joint.child {
+                return Err(format!(
+                    "Joint {} connects {} to itself",
+                    joint.name, joint.child
+                ));
+            }
+            if tree.parents.contains_key(&joint.child) {
+                tree.loops.push(idx);
+                continue;
+            }
+            tree.parents.insert(joint.child.clone(), idx);
+            tree.children
+                .entry(joint.parent.clone())
+                .or_default()
+                .push(idx);
+        }
+        tree.sort();
+        Ok(tree)
+    }
+
+    fn add_model(&mut self, model: &SdfModel, scope: &str) -> Result<(), String> {
+        let graph = &self.graph;
+        let mut joints = vec![];
+        for joint in model.joint.iter() {
+            let name = scoped_name(scope, &joint.name);
+            let link = |frame: &str| -> Result<String, String> {
+                if frame == "world" {
+                    return Ok(frame.to_string());
+                }
+                let resolved = FrameGraph::resolve(scope, frame);
+                match graph.kind(&resolved) {
+                    Some(FrameKind::Link) => Ok(resolved),
+                    Some(_) => graph.attached_link(&resolved),
+                    None => Err(format!("Joint {} refers to unknown frame {}", name, frame)),
+                }
+            };
+            let parent = link(&joint.parent)?;
+            let child = link(&joint.child)?;
+            if child == "world" {
+                return Err(format!("Joint {} has the world as its child", name));
+            }
+            joints.push(tree_joint(joint, graph, scope, parent, child)?);
+        }
+        self.joints.extend(joints);
+        for link in model.link.iter() {
+            self.links.push(scoped_name(scope, &link.name));
+        }
+        for nested in model.model.iter() {
+            self.add_model(nested, &scoped_name(scope, &nested.name))?;
+        }
+        Ok(())
+    }
+
+    /// Orders the links so that parents come before their children. Links that cannot be
+    /// reached from a root are part of a cycle, the parent joint of the first one is treated as
+    /// a loop joint.
+    fn sort(&mut self) {
+        let mut visited = HashSet::new();
+        loop {
+            let mut stack: Vec<String> = self
+                .links
+                .iter()
+                .filter(|link| !visited.contains(*link))
+                .filter(|link| match self.parents.get(*link) {
+                    Some(idx) => self.joints[*idx].parent == "world",
+                    None => true,
+                })
+                .rev()
+                .cloned()
+                .collect();
+            while let Some(link) = stack.pop() {
+                if !visited.insert(link.clone()) {
+                    continue;
+                }
+                if let Some(children) = self.children.get(&link) {
+                    for idx in children.iter().rev() {
+                        stack.push(self.joints[*idx].child.clone());
+                    }
+                }
+                self.order.push(link);
+            }
+            let Some(unreached) = self.links.iter().find(|link| !visited.contains(*link)) else {
+                break;
+            };
+            let idx = self
+                .parents
+                .remove(unreached)
+                .expect("Unreached links have a parent");
+            let parent = &self.joints[idx].parent;
+            if let Some(children) = self.children.get_mut(parent) {
+                children.retain(|child| *child != idx);
+            }
+            self.loops.push(idx);
+        }
+    }
+
+    /// Returns the scoped names of the links, in the order they are defined.
+    pub fn links(&self) -> &[String] {
+        &self.links
+    }
+
+    /// Returns the joints of the model, including loop joints.
+    pub fn joints(&self) -> &[TreeJoint] {
+        &self.joints
+    }
+
+    /// Returns the joint with a scoped name.
+    pub fn joint(&self, name: &str) -> Option<&TreeJoint> {
+        self.joints.iter().find(|joint| joint.name == name)
+    }
+
+    /// Returns the joint connecting a link to its parent, `None` for floating links.
+    pub fn parent_joint(&self, link: &str) -> Option<&TreeJoint> {
+        self.parents.get(link).map(|idx| &self.joints[*idx])
+    }
+
+    /// Iterates over the joints connecting a link, or `world`, to its children.
+    pub fn child_joints(&self, link: &str) -> impl Iterator<Item = &TreeJoint> {
+        self.children
+            .get(link)
+            .into_iter()
+            .flatten()
+            .map(|idx| &self.joints[*idx])
+    }
+
+    /// Iterates over the children of a link, or of `world`.
+    pub fn children(&self, link: &str) -> impl Iterator<Item = &str> {
+        self.child_joints(link).map(|joint| joint.child.as_str())
+    }
+
+    /// Iterates over the links at the top of the tree: the links attached to the world and the
+    /// floating links.
+    pub fn roots(&self) -> impl Iterator<Item = &str> {
+        self.links
+            .iter()
+            .filter(|link| {
+                self.parent_joint(link)
+                    .is_none_or(|joint| joint.parent == "world")
+            })
+            .map(String::as_str)
+    }
+
+    /// Iterates over the links without a parent joint, which move freely.
+    pub fn floating(&self) -> impl Iterator<Item = &str> {
+        self.links
+            .iter()
+            .filter(|link| !self.parents.contains_key(*link))
+            .map(String::as_str)
+    }
+
+    /// Iterates over the joints that close kinematic loops and are not part of the tree.
+    pub fn loop_joints(&self) -> impl Iterator<Item = &TreeJoint> {
+        self.loops.iter().map(|idx| &self.joints[*idx])
+    }
+
+    /// Returns true if some joints close kinematic loops.
+    pub fn has_loops(&self) -> bool {
+        !self.loops.is_empty()
+    }
+
+    /// Returns the links in topological order, every link comes after its parent.
+    pub fn order(&self) -> &[String] {
+        &self.order
+    }
+
+    /// Clamps positions, by scoped joint name, to the limits of their joints. Positions of
+    /// unknown joints are left untouched.
+    pub fn clamp_positions(&self, positions: &mut HashMap<String, JointPosition>) {
+        for (name, position) in positions.iter_mut() {
+            if let Some(joint) = self.joint(name) {
+                *position = joint.clamp(*position);
+            }
+        }
+    }
+
+    /// Returns the frames of the model, with the poses they have when all joints are at zero.
+    pub fn frames(&self) -> &FrameGraph {
+        &self.graph
+    }
+}
+
+/// The position of a joint, the variant depends on the number of degrees of freedom of the joint.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum JointPosition {
+    /// The angle of revolute, continuous and screw joints, or the offset of prismatic joints.
+    Scalar(f64),
+    /// The angles about the two axes of universal and revolute2 joints.
+    Pair(f64, f64),
+    /// The rotation of ball joints, in the joint frame.
+    Rotation(UnitQuaternion<f64>),
+}
+
+impl From<f64> for JointPosition {
+    fn from(position: f64) -> Self {
+        JointPosition::Scalar(position)
+    }
+}
+
+/// The world poses of the frames of a model for a set of joint positions.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct WorldPoses {
+    /// The poses of the links, by scoped name.
+    pub links: HashMap<String, Isometry3<f64>>,
+    /// The poses of the other frames: explicit frames, joints, nested models and `__model__`.
+    pub frames: HashMap<String, Isometry3<f64>>,
+    /// The poses of the sensors, by name scoped with their link or joint, e.g. `head::camera`.
+    pub sensors: HashMap<String, Isometry3<f64>>,
+}
+
+fn unit_axis(joint: &TreeJoint, axis: Option<&JointAxis>) -> Result<Unit<Vector3<f64>>, String> {
+    let xyz = axis.map(|axis| axis.xyz).unwrap_or_else(Vector3::z);
+    Unit::try_new(xyz, 1e-12)
+        .ok_or_else(|| format!("Joint {} has an axis of zero length", joint.name))
+}
+
+impl TreeJoint {
+    /// Returns the transform from the frame of the joint on the parent link to the frame of the
+    /// joint on the child link. Axes are expressed in the joint frame.
+    pub fn motion(&self, position: Option<&JointPosition>) -> Result<Isometry3<f64>, String> {
+        let scalar = || match position {
+            None => Ok(0.0),
+            Some(JointPosition::Scalar(position)) => Ok(*position),
+            Some(_) => Err(format!("Joint {} expects a single position", self.name)),
+        };
+        let axis = || unit_axis(self, self.axis.as_ref());
+        let rotation = |angle: f64| -> Result<Isometry3<f64>, String> {
+            Ok(Isometry3::from_parts(
+                Translation3::identity(),
+                UnitQuaternion::from_axis_angle(&axis()?, angle),
+            ))
+        };
+        match self.r#type.as_str() {
+            "fixed" => Ok(Isometry3::identity()),
+            "revolute" | "continuous" => rotation(scalar()?),
+            "prismatic" => Ok(Translation3::from(axis()?.into_inner() * scalar()?).into()),
+            "screw" => {
+                let angle = scalar()?;
+                let pitch = self.screw_thread_pitch.unwrap_or(1.0);
+                let offset = axis()?.into_inner() * angle * pitch / (2.0 * std::f64::consts::PI);
+                Ok(Translation3::from(offset) * rotation(angle)?)
+            }
+            "universal" | "revolute2" => {
+                let (first, second) = match position {
+                    None => (0.0, 0.0),
+                    Some(JointPosition::Pair(first, second)) => (*first, *second),
+                    Some(_) => return Err(format!("Joint {} expects two positions", self.name)),
+                };
+                let axis2 = unit_axis(self, self.axis2.as_ref())?;
+                Ok(Isometry3::from_parts(
+                    Translation3::identity(),
+                    UnitQuaternion::from_axis_angle(&axis()?, first)
+                        * UnitQuaternion::from_axis_angle(&axis2, second),
+                ))
+            }
+            "ball" => match position {
+                None => Ok(Isometry3::identity()),
+                Some(JointPosition::Rotation(rotation)) => {
+                    Ok(Isometry3::from_parts(Translation3::identity(), *rotation))
+                }
+                Some(_) => Err(format!("Joint {} expects a rotation", self.name)),
+            },
+            other => Err(format!(
+                "Joints of type {} are not supported, found in joint {}",
+                other, self.name
+            )),
+        }
+    }
+
+    /// Returns the limits of the axis and of the second axis that bound positions of the joint.
+    /// Continuous joints are not limited.
+    fn limits(&self) -> (Option<&JointLimit>, Option<&JointLimit>) {
+        if self.r#type == "continuous" {
+            return (None, None);
+        }
+        (
+            self.axis.as_ref().and_then(|axis| axis.limit.as_ref()),
+            self.axis2.as_ref().and_then(|axis| axis.limit.as_ref()),
+        )
+    }
+
+    /// Returns the position clamped to the limits of the joint axes. Rotations of ball joints
+    /// are not limited.
+    pub fn clamp(&self, position: JointPosition) -> JointPosition {
+        let clamp = |value: f64, limit: Option<&JointLimit>| match limit {
+            Some(limit) => value.max(limit.lower).min(limit.upper),
+            None => value,
+        };
+        let (first, second) = self.limits();
+        match position {
+            JointPosition::Scalar(value) => JointPosition::Scalar(clamp(value, first)),
+            JointPosition::Pair(value, value2) => {
+                JointPosition::Pair(clamp(value, first), clamp(value2, second))
+            }
+            rotation => rotation,
+        }
+    }
+
+    /// Returns whether the position is within the limits of the joint axes.
+    pub fn within_limits(&self, position: &JointPosition) -> bool {
+        self.clamp(*position) == *position
+    }
+}
+
+/// Computes the world poses of the links, frames and sensors of a model for the given joint
+/// positions, by scoped joint name. Joints without a position are at zero.
+///
+/// The model is placed in the world by its pose. Floating links keep the pose they have in the
+/// model and loop joints are ignored.
+pub fn forward_kinematics(
+    model: &SdfModel,
+    positions: &HashMap<String, JointPosition>,
+) -> Result<WorldPoses, String> {
+    place(model, &KinematicTree::from_model(model)?, positions)
+}
+
+/// Computes the world poses of a model whose tree is already built.
+pub(crate) fn place(
+    model: &SdfModel,
+    tree: &KinematicTree,
+    positions: &HashMap<String, JointPosition>,
+) -> Result<WorldPoses, String> {
+    let graph = tree.frames();
+    let placement = match model.pose.as_ref() {
+        Some(pose) => pose.get_pose()?.isometry(),
+        None => Isometry3::identity(),
+    };
+
+    let mut poses = WorldPoses::default();
+    for link in tree.order() {
+        let pose = match tree.parent_joint(link) {
+            None => placement * graph.pose_in_root(link)?,
+            Some(joint) => {
+                let parent = match joint.parent.as_str() {
+                    "world" => placement * graph.pose_in_root(&joint.name)?,
+                    parent => poses.links[parent] * graph.pose(&joint.name, parent)?,
+                };
+                parent
+                    * joint.motion(positions.get(&joint.name))?
+                    * graph.pose(link, &joint.name)?
+            }
+        };
+        poses.links.insert(link.clone(), pose);
+    }
+
+    // Other frames move with the link they are attached to
+    for frame in graph.frames() {
+        if poses.links.contains_key(frame) {
+            continue;
+        }
+        let link = graph.attached_link(frame)?;
+        let pose = match poses.links.get(&link) {
+            Some(link_pose) => link_pose * graph.pose(frame, &link)?,
+            None => placement * graph.pose_in_root(frame)?,
+        };
+        poses.frames.insert(frame.to_string(), pose);
+    }
+    add_sensors(model, "", graph, &mut poses)?;
+    Ok(poses)
+}
+
+fn add_sensors(
+    model: &SdfModel,
+    scope: &str,
+    graph: &FrameGraph,
+    poses: &mut WorldPoses,
+) -> Result<(), String> {
+    let add = |frame: String, sensors: &[SdfSensor], poses: &mut WorldPoses| {
+        // The pose of the frame when all joints are at zero, and its pose in the world
+        let zero = graph.pose_in_root(&frame)?;
+        let world = poses
+            .links
+            .get(&frame)
+            .or_else(|| poses.frames.get(&frame))
+            .copied()
+            .ok_or_else(|| format!("Frame {} not found", frame))?;
+        for sensor in sensors.iter() {
+            let pose = graph.resolve_pose(sensor.pose.as_ref(), scope, &frame)?;
+            poses.sensors.insert(
+                scoped_name(&frame, &sensor.name),
+                world * zero.inverse() * pose,
+            );
+        }
+        Ok::<_, String>(())
+    };
+    for link in model.link.iter() {
+        add(scoped_name(scope, &link.name), &link.sensor, poses)?;
+    }
+    for joint in model.joint.iter() {
+        add(scoped_name(scope, &joint.name), &joint.sensor, poses)?;
+    }
+    for nested in model.model.iter() {
+        add_sensors(nested, &scoped_name(scope, &nested.name), graph, poses)?;
+    }
+    Ok(())
+}
@@ -12,6 +12,25 @@ pub use yaserde;
 
 use yaserde::{YaDeserialize, YaSerialize};
 
+pub mod bounds;
+mod convert;
+pub mod cst;
+mod field_io;
+pub mod flatten;
+pub mod frames;
+pub mod heightmap;
+pub mod inertial;
+pub mod jacobian;
+pub mod kinematics;
+mod lookup;
+pub mod mesh;
+pub mod mjcf;
+pub mod polyline;
+mod pose;
+mod spec;
+pub mod urdf;
+mod writer;
+
 // Most of the structs are generated automatically from the
 include!(concat!(env!("OUT_DIR"), "/sdf.rs"));
 
@@ -151,17 +170,6 @@ pub struct ElementMap {
     elements: Vec<XmlElement>,
 }
 
-#[derive(Default, PartialEq, Clone, Debug)]
-pub struct SdfParams(pub ElementMap);
-
-// Manually declare plugin
-#[derive(Default, PartialEq, Clone, Debug)]
-pub struct SdfPlugin {
-    pub name: String,
-    pub filename: String,
-    pub elements: ElementMap,
-}
-
 impl ElementMap {
     /// Returns a reference to the first element with the specified name
     pub fn get(&self, name: &str) -> Option<&XmlElement> {
@@ -219,12 +227,11 @@ impl ElementMap {
 fn deserialize_element<R: Read>(
     reader: &mut yaserde::de::Deserializer<R>,
 ) -> Result<XmlElement, String> {
-    let (name, attributes) = match reader.next_event()? {
-        xml::reader::XmlEvent::StartElement {
-            name, attributes, ..
-        } => (name.local_name, attributes),
+    let name = match reader.peek()? {
+        xml::reader::XmlEvent::StartElement { name, .. } => field_io::qualified_name(name),
         _ => return Err("Unexpected event found when deserializing plugin element".to_string()),
     };
+    let attributes = field_io::read_start(reader, &name)?;
     let mut element = XmlElement {
         name: name.into(),
         ..Default::default()
@@ -232,7 +239,7 @@ fn deserialize_element<R: Read>(
     for attr in attributes.iter() {
         element
             .attributes
-            .insert(attr.name.local_name.clone(), attr.value.to_owned());
+            .insert(field_io::qualified_name(&attr.name), attr.value.to_owned());
     }
     match reader.peek()? {
         xml::reader::XmlEvent::Characters(value) => {
@@ -261,56 +268,11 @@ fn deserialize_element<R: Read>(
     Ok(element)
 }
 
-impl YaDeserialize for SdfPlugin {
-    fn deserialize<R: Read>(reader: &mut yaserde::de::Deserializer<R>) -> Result<Self, String> {
-        let mut plugin = SdfPlugin::default();
-        let read_attribute = |attributes: &Vec<OwnedAttribute>, name: &str| {
-            attributes
-                .iter()
-                .find(|attr| attr.name.local_name == name)
-                .map(|attr| attr.value.clone())
-                .unwrap_or_default()
-        };
-        // deserializer code
-        if let Ok(xml::reader::XmlEvent::StartElement { attributes, .. }) = reader.next_event() {
-            plugin.name = read_attribute(&attributes, "name");
-            plugin.filename = read_attribute(&attributes, "filename");
-            while !matches!(reader.peek()?, xml::reader::XmlEvent::EndElement { .. }) {
-                let elem = deserialize_element(reader)?;
-                plugin.elements.push(elem);
-            }
-            Ok(plugin)
-        } else {
-            Err("Element not found when parsing plugin".to_string())
-        }
-    }
-}
-
-impl YaDeserialize for SdfParams {
-    fn deserialize<R: Read>(reader: &mut yaserde::de::Deserializer<R>) -> Result<Self, String> {
-        let mut params = SdfParams::default();
-        // deserializer code
-        if let Ok(xml::reader::XmlEvent::StartElement { .. }) = reader.next_event() {
-            while !matches!(reader.peek()?, xml::reader::XmlEvent::EndElement { .. }) {
-                let elem = deserialize_element(reader)?;
-                params.0.push(elem);
-            }
-            Ok(params)
-        } else {
-            Err("Element not found when parsing plugin".to_string())
-        }
-    }
-}
-
 fn serialize_element<W: Write>(
     elem: &XmlElement,
     serializer: &mut yaserde::ser::Serializer<W>,
 ) -> Result<(), String> {
-    let mut builder = xml::writer::XmlEvent::start_element(&*elem.name);
-    for (name, data) in elem.attributes.iter() {
-        builder = builder.attr(xml::name::Name::local(name), data);
-    }
-    serializer.write(builder).map_err(|e| e.to_string())?;
+    field_io::write_start_event(serializer, &elem.name, &[], &elem.attributes)?;
     match &elem.data {
         ElementData::String(s) => {
             serializer
@@ -329,169 +291,30 @@ fn serialize_element<W: Write>(
     Ok(())
 }
 
-impl YaSerialize for SdfPlugin {
-    fn serialize<W: Write>(
-        &self,
-        serializer: &mut yaserde::ser::Serializer<W>,
-    ) -> Result<(), String> {
-        serializer
-            .write(
-                xml::writer::XmlEvent::start_element("plugin")
-                    .attr("name", &self.name)
-                    .attr("filename", &self.filename),
-            )
-            .map_err(|e| e.to_string())?;
-        for element in self.elements.elements.iter() {
-            serialize_element(element, serializer)?;
+impl From<Vector3d> for SdfJointAxisXyz {
+    fn from(xyz: Vector3d) -> Self {
+        SdfJointAxisXyz {
+            data: xyz,
+            ..Default::default()
         }
-        serializer
-            .write(xml::writer::XmlEvent::end_element())
-            .map_err(|e| e.to_string())?;
-        Ok(())
-    }
-
-    fn serialize_attributes(
-        &self,
-        attributes: Vec<OwnedAttribute>,
-        namespace: Namespace,
-    ) -> Result<(Vec<OwnedAttribute>, Namespace), String> {
-        Ok((attributes, namespace))
-    }
-}
-
-impl YaSerialize for SdfParams {
-    fn serialize<W: Write>(
-        &self,
-        serializer: &mut yaserde::ser::Serializer<W>,
-    ) -> Result<(), String> {
-        serializer
-            .write(xml::writer::XmlEvent::start_element("experimental:params"))
-            .map_err(|e| e.to_string())?;
-        for element in self.0.elements.iter() {
-            serialize_element(element, serializer)?;
-        }
-        serializer
-            .write(xml::writer::XmlEvent::end_element())
-            .map_err(|e| e.to_string())?;
-        Ok(())
-    }
-
-    fn serialize_attributes(
-        &self,
-        attributes: Vec<OwnedAttribute>,
-        namespace: Namespace,
-    ) -> Result<(Vec<OwnedAttribute>, Namespace), String> {
-        Ok((attributes, namespace))
     }
 }
 
-// Frame is another wierdo. For some reason it refuses to serialize/deserialize automatically
-// Hence the manual definition
-// Todo(arjo): Actually implement Frame.
-#[derive(Default, PartialEq, Clone, Debug, YaSerialize, YaDeserialize)]
-#[yaserde(rename = "frame")]
-pub struct SdfFrame {}
-
-// Geometry should really be an enum rather than a list of Options, redefine it here
-/// The shape of the visual or collision object.
-#[derive(Default, PartialEq, Clone, Debug, YaSerialize, YaDeserialize)]
-#[yaserde(rename = "geometry")]
-pub enum SdfGeometry {
-    #[yaserde(rename = "empty")]
-    #[default]
-    Empty,
-    #[yaserde(rename = "box")]
-    r#Box(SdfBoxShape),
-    #[yaserde(rename = "capsule")]
-    Capsule(SdfCapsuleShape),
-    #[yaserde(rename = "cylinder")]
-    Cylinder(SdfCylinderShape),
-    #[yaserde(rename = "ellipsoid")]
-    Ellipsoid(SdfEllipsoidShape),
-    #[yaserde(rename = "heightmap")]
-    Heightmap(SdfHeightmapShape),
-    #[yaserde(rename = "image")]
-    Image(SdfImageShape),
-    #[yaserde(rename = "mesh")]
-    Mesh(SdfMeshShape),
-    #[yaserde(rename = "plane")]
-    Plane(SdfPlaneShape),
-    #[yaserde(rename = "polyline")]
-    Polyline(SdfPolylineShape),
-    #[yaserde(rename = "sphere")]
-    Sphere(SdfSphereShape),
-}
-
-/// Simple implementation of pose
-pub struct Pose {
-    /// Translation vector
-    pub translation: Vector3<f64>,
-    /// Rotation
-    pub rotation: Rotation3<f64>,
-    /// Relative pose
-    pub relative_to: String,
-}
-
-impl SdfPose {
-    /// Lazily retrieve the pose as an Isometry
-    /// In the event the pose is not parseable it returns a String based error.
-    pub fn get_pose(&self) -> Result<Pose, String> {
-        let digits = self
-            .data
-            .split_whitespace()
-            .map(|dig| dig.parse::<f64>())
-            .collect::<Result<Vec<_>, _>>()
-            .map_err(|_| format!("Failed to parse pose values from {:?}", self.data))?;
-
-        let relative_to = self.relative_to.clone().unwrap_or_default();
-
-        if digits.len() == 6 {
-            let translation = Vector3::new(digits[0], digits[1], digits[2]);
-            if let Some(degrees) = self.degrees {
-                let rotation = if degrees {
-                    // TODO(arjo): Pose parsing code
-                    use std::f64::consts::PI;
-                    Rotation3::from_euler_angles(
-                        digits[3] / PI * 180.0,
-                        digits[4] / PI * 180.0,
-                        digits[5] / PI * 180.0,
-                    )
-                } else {
-                    Rotation3::from_euler_angles(digits[3], digits[4], digits[5])
-                };
-                return Ok(Pose {
-                    translation,
-                    rotation,
-                    relative_to,
-                });
-            } else {
-                let rotation = Rotation3::from_euler_angles(digits[3], digits[4], digits[5]);
-
-                return Ok(Pose {
-                    translation,
-                    rotation,
-                    relative_to,
-                });
-            }
-        } else if digits.len() == 7 {
-            let translation = Vector3::new(digits[0], digits[1], digits[2]);
-            let (_norm, half_angle, axis) =
-                Quaternion::new(digits[3], digits[4], digits[5], digits[6]).polar_decomposition();
-            let rotation = if let Some(axis) = axis {
-                Rotation3::from_axis_angle(&axis, half_angle * 2.0)
-            } else {
-                Rotation3::from_axis_angle(&Vector3::y_axis(), half_angle * 2.0)
-            };
-            return Ok(Pose {
-                translation,
-                rotation,
-                relative_to,
-            });
+impl From<Vector3d> for SdfJointAxis2Xyz {
+    fn from(xyz: Vector3d) -> Self {
+        SdfJointAxis2Xyz {
+            data: xyz,
+            ..Default::default()
         }
-        Err("Failed to parse pose".to_string())
     }
 }
 
+pub use convert::{
+    to_version, to_version_with_options, ConversionIssue, ConvertOptions, Converted,
+};
+pub use lookup::Scoped;
+pub use pose::Pose;
+pub use writer::{to_string_pretty, AttributeOrder, WriteOptions};
 pub use yaserde::de::from_str;
 
 #[derive(Clone, Debug, Default, PartialEq)]
@@ -0,0 +1,105 @@
+//! Lookup of the elements of a world by scoped name, e.g. `robot::arm::link3`.
+
+use crate::{SdfFrame, SdfJoint, SdfLink, SdfModel, SdfSensor, SdfWorld};
+
+/// An element found by its scoped name.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Scoped<'a, T> {
+    pub element: &'a T,
+    /// The absolute scope the element is defined in: the scoped name of its model, or of its link
+    /// or joint for sensors. Empty for elements of the world.
+    pub scope: &'a str,
+}
+
+/// Splits a scoped name into its scope and the name of the element, the scope is empty for
+/// unscoped names.
+fn split(name: &str) -> (&str, &str) {
+    name.rsplit_once("::").unwrap_or(("", name))
+}
+
+impl SdfWorld {
+    /// Returns the model with a scoped name, descending through nested models.
+    pub fn find_model<'a>(&'a self, name: &'a str) -> Option<Scoped<'a, SdfModel>> {
+        let mut segments = name.split("::");
+        let first = segments.next()?;
+        let mut model = self.model.iter().find(|model| model.name == first)?;
+        for segment in segments {
+            model = model.model.iter().find(|nested| nested.name == segment)?;
+        }
+        Some(Scoped {
+            element: model,
+            scope: split(name).0,
+        })
+    }
+
+    /// Returns the model that defines the elements of `scope`.
+    fn scope_model<'a>(&'a self, scope: &'a str) -> Option<&'a SdfModel> {
+        self.find_model(scope).map(|model| model.element)
+    }
+
+    /// Returns the link with a scoped name such as `robot::arm::link3`.
+    pub fn find_link<'a>(&'a self, name: &'a str) -> Option<Scoped<'a, SdfLink>> {
+        let (scope, name) = split(name);
+        let link = self
+            .scope_model(scope)?
+            .link
+            .iter()
+            .find(|link| link.name == name)?;
+        Some(Scoped {
+            element: link,
+            scope,
+        })
+    }
+
+    /// Returns the joint with a scoped name, unscoped names refer to the joints of the world.
+    pub fn find_joint<'a>(&'a self, name: &'a str) -> Option<Scoped<'a, SdfJoint>> {
+        let (scope, name) = split(name);
+        let joints = match scope {
+            "" => &self.joint,
+            scope => &self.scope_model(scope)?.joint,
+        };
+        let joint = joints.iter().find(|joint| joint.name == name)?;
+        Some(Scoped {
+            element: joint,
+            scope,
+        })
+    }
+
+    /// Returns the explicit frame with a scoped name, unscoped names refer to the frames of the
+    /// world.
+    pub fn find_frame<'a>(&'a self, name: &'a str) -> Option<Scoped<'a, SdfFrame>> {
+        let (scope, name) = split(name);
+        let frames = match scope {
+            "" => &self.frame,
+            scope => &self.scope_model(scope)?.frame,
+        };
+        let frame = frames.iter().find(|frame| frame.name == name)?;
+        Some(Scoped {
+            element: frame,
+            scope,
+        })
+    }
+
+    /// Returns the sensor with a scoped name such as `robot::camera_link::camera`, where the
+    /// sensor is defined in a link or a joint.
+    pub fn find_sensor<'a>(&'a self, name: &'a str) -> Option<Scoped<'a, SdfSensor>> {
+        let (scope, name) = split(name);
+        let (model, parent) = split(scope);
+        let model = self.scope_model(model)?;
+        let sensors = match model.link.iter().find(|link| link.name == parent) {
+            Some(link) => &link.sensor,
+            None => {
+                &model
+                    .joint
+                    .iter()
+                    .find(|joint| joint.name == parent)?
+                    .sensor
+            }
+        };
+        let sensor = sensors.iter().find(|sensor| sensor.name == name)?;
+        Some(Scoped {
+            element: sensor,
+            scope,
+        })
+    }
+}
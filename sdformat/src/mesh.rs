@@ -0,0 +1,179 @@
+//! Triangle meshes, the surfaces of mesh geometries.
+//!
+//! With the `mesh` feature, [`load_mesh`] reads the meshes of mesh geometries from STL, OBJ,
+//! COLLADA and glTF files. Their URIs are resolved to paths by a [`MeshResolver`] such as
+//! [`PathResolver`], which heightmaps use as well and which need no feature.
+
+use std::collections::HashMap;
+#[cfg(feature = "mesh")]
+use std::path::Path;
+
+use nalgebra::{Matrix4, Vector3};
+
+#[cfg(feature = "mesh")]
+use crate::SdfMeshShape;
+
+#[cfg(feature = "mesh")]
+mod collada;
+#[cfg(feature = "mesh")]
+mod gltf;
+#[cfg(feature = "mesh")]
+mod obj;
+mod resolve;
+#[cfg(feature = "mesh")]
+mod stl;
+
+pub use resolve::{MeshResolver, PathResolver};
+
+/// A surface made of triangles over a list of vertices.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TriangleMesh {
+    pub vertices: Vec<Vector3<f64>>,
+    /// The indices of the vertices of each triangle, counterclockwise when seen from outside.
+    pub triangles: Vec<[usize; 3]>,
+}
+
+impl TriangleMesh {
+    /// Returns the mesh with its vertices scaled along each axis, as by the `scale` of mesh
+    /// geometries.
+    pub fn scaled(&self, scale: &Vector3<f64>) -> Self {
+        Self {
+            vertices: self
+                .vertices
+                .iter()
+                .map(|vertex| vertex.component_mul(scale))
+                .collect(),
+            triangles: self.triangles.clone(),
+        }
+    }
+
+    /// Returns the mesh with its vertices transformed by an affine transform.
+    pub fn transformed(&self, transform: &Matrix4<f64>) -> Self {
+        Self {
+            vertices: self
+                .vertices
+                .iter()
+                .map(|vertex| transform.transform_point(&(*vertex).into()).coords)
+                .collect(),
+            triangles: self.triangles.clone(),
+        }
+    }
+
+    /// Adds the vertices and triangles of another mesh to this one.
+    pub fn append(&mut self, other: &Self) {
+        let offset = self.vertices.len();
+        self.vertices.extend(other.vertices.iter());
+        self.triangles.extend(
+            other
+                .triangles
+                .iter()
+                .map(|triangle| triangle.map(|index| index + offset)),
+        );
+    }
+
+    /// Returns whether the mesh bounds a volume: every edge is shared by exactly two triangles
+    /// that traverse it in opposite directions.
+    pub fn is_closed(&self) -> bool {
+        let mut edges = HashMap::new();
+        for triangle in self.triangles.iter() {
+            for corner in 0..3 {
+                let edge = (triangle[corner], triangle[(corner + 1) % 3]);
+                *edges.entry(edge).or_insert(0) += 1;
+            }
+        }
+        !edges.is_empty()
+            && edges
+                .iter()
+                .all(|(&(from, to), &count)| count == 1 && edges.get(&(to, from)) == Some(&1))
+    }
+
+    /// Returns the unit normal of each triangle, pointing to the side its corners are
+    /// counterclockwise from. Degenerate triangles have a zero normal.
+    pub fn normals(&self) -> Vec<Vector3<f64>> {
+        self.triangles
+            .iter()
+            .map(|triangle| {
+                let [a, b, c] = triangle.map(|index| self.vertices[index]);
+                (b - a)
+                    .cross(&(c - a))
+                    .try_normalize(0.0)
+                    .unwrap_or_else(Vector3::zeros)
+            })
+            .collect()
+    }
+}
+
+/// Reads the meshes of a file, by name, choosing the format from the extension of the file.
+#[cfg(feature = "mesh")]
+pub fn read_meshes(path: &Path) -> Result<Vec<(String, TriangleMesh)>, String> {
+    let bytes =
+        std::fs::read(path).map_err(|e| format!("Could not read {}: {}", path.display(), e))?;
+    let extension = path
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .unwrap_or_default()
+        .to_lowercase();
+    let meshes = match extension.as_str() {
+        "stl" => stl::read(&bytes),
+        "obj" => obj::read(&bytes),
+        "dae" => collada::read(&bytes),
+        "gltf" | "glb" => gltf::read(&bytes, path),
+        _ => Err(format!("Unsupported mesh format {}", extension)),
+    };
+    meshes.map_err(|e| format!("Could not load {}: {}", path.display(), e))
+}
+
+/// Loads the surface of a mesh geometry before its scale is applied: the named submesh, centered
+/// on the origin if requested, or all the meshes of the file. This is the loader expected by
+/// [`crate::SdfLink::auto_inertial_with_meshes`] and [`crate::bounds`].
+#[cfg(feature = "mesh")]
+pub fn load_submesh(
+    shape: &SdfMeshShape,
+    resolver: &dyn MeshResolver,
+) -> Result<TriangleMesh, String> {
+    let meshes = read_meshes(&resolver.resolve(&shape.uri)?)?;
+    let mut surface = TriangleMesh::default();
+    match shape.submesh.as_ref() {
+        Some(submesh) => {
+            for (_, mesh) in meshes.iter().filter(|(name, _)| *name == submesh.name) {
+                surface.append(mesh);
+            }
+            if surface.vertices.is_empty() {
+                return Err(format!(
+                    "Submesh {} not found in {}",
+                    submesh.name, shape.uri
+                ));
+            }
+            if submesh.center == Some(true) {
+                // Centers the bounding box of the submesh, as Gazebo does
+                let (min, max) = surface.vertices.iter().fold(
+                    (surface.vertices[0], surface.vertices[0]),
+                    |(min, max), vertex| (min.inf(vertex), max.sup(vertex)),
+                );
+                let center = (min + max) / 2.0;
+                for vertex in surface.vertices.iter_mut() {
+                    *vertex -= center;
+                }
+            }
+        }
+        None => {
+            for (_, mesh) in meshes.iter() {
+                surface.append(mesh);
+            }
+        }
+    }
+    Ok(surface)
+}
+
+/// Loads the surface of a mesh geometry with its submesh and scale applied.
+#[cfg(feature = "mesh")]
+pub fn load_mesh(
+    shape: &SdfMeshShape,
+    resolver: &dyn MeshResolver,
+) -> Result<TriangleMesh, String> {
+    let surface = load_submesh(shape, resolver)?;
+    Ok(match shape.scale.as_ref() {
+        Some(scale) => surface.scaled(&scale.0),
+        None => surface,
+    })
+}
@@ -0,0 +1,261 @@
+//! COLLADA files. Geometries are meshes named after them, placed by the nodes of the visual
+//! scene that instantiate them and scaled to meters by the unit of the document.
+
+use std::collections::HashMap;
+
+use nalgebra::{Matrix4, Rotation3, Unit, Vector3};
+
+use super::TriangleMesh;
+use crate::{ElementData, XmlElement};
+
+fn elements(element: &XmlElement) -> &[XmlElement] {
+    match &element.data {
+        ElementData::Nested(elements) => elements.all(),
+        ElementData::String(_) => &[],
+    }
+}
+
+/// Returns the child elements with a name.
+fn children<'a>(
+    element: &'a XmlElement,
+    name: &'a str,
+) -> impl Iterator<Item = &'a XmlElement> + 'a {
+    elements(element)
+        .iter()
+        .filter(move |element| &*element.name == name)
+}
+
+fn child<'a>(element: &'a XmlElement, name: &str) -> Option<&'a XmlElement> {
+    match &element.data {
+        ElementData::Nested(elements) => elements.get(name),
+        ElementData::String(_) => None,
+    }
+}
+
+fn attribute<'a>(element: &'a XmlElement, name: &str) -> Option<&'a str> {
+    element.attributes.get(name).map(String::as_str)
+}
+
+fn numbers<T: std::str::FromStr>(element: &XmlElement) -> Result<Vec<T>, String> {
+    let text = match &element.data {
+        ElementData::String(text) => text.as_str(),
+        ElementData::Nested(_) => "",
+    };
+    text.split_whitespace()
+        .map(|value| {
+            value
+                .parse()
+                .map_err(|_| format!("Invalid number {} in {}", value, element.name))
+        })
+        .collect()
+}
+
+/// Returns the id an URL such as `#geometry` refers to.
+fn reference<'a>(element: &'a XmlElement, name: &str) -> Option<&'a str> {
+    attribute(element, name).map(|url| url.trim_start_matches('#'))
+}
+
+/// Reads the triangles of a `<mesh>`. Positions are shared by all primitives.
+fn read_mesh(mesh: &XmlElement) -> Result<TriangleMesh, String> {
+    let mut sources = HashMap::new();
+    for source in children(mesh, "source") {
+        let floats = child(source, "float_array")
+            .map(numbers::<f64>)
+            .transpose()?
+            .unwrap_or_default();
+        let stride = child(source, "technique_common")
+            .and_then(|technique| child(technique, "accessor"))
+            .and_then(|accessor| attribute(accessor, "stride"))
+            .and_then(|stride| stride.parse::<usize>().ok())
+            .unwrap_or(3);
+        sources.insert(
+            attribute(source, "id").unwrap_or_default(),
+            (floats, stride),
+        );
+    }
+    let vertices = child(mesh, "vertices").ok_or("Mesh without vertices")?;
+    let position = children(vertices, "input")
+        .find(|input| attribute(input, "semantic") == Some("POSITION"))
+        .and_then(|input| reference(input, "source"))
+        .ok_or("Mesh without positions")?;
+    let (floats, stride) = sources
+        .get(position)
+        .ok_or_else(|| format!("Source {} not found", position))?;
+    if *stride < 3 {
+        return Err(format!(
+            "Source {} has less than 3 values per vertex",
+            position
+        ));
+    }
+    let mut surface = TriangleMesh {
+        vertices: floats
+            .chunks_exact(*stride)
+            .map(|values| Vector3::new(values[0], values[1], values[2]))
+            .collect(),
+        triangles: vec![],
+    };
+
+    for primitive in elements(mesh) {
+        let polygon = match &*primitive.name {
+            polygon @ ("triangles" | "polylist" | "polygons") => polygon,
+            _ => continue,
+        };
+        let inputs: Vec<_> = children(primitive, "input").collect();
+        let offset = |input: &XmlElement| {
+            attribute(input, "offset")
+                .and_then(|offset| offset.parse::<usize>().ok())
+                .unwrap_or(0)
+        };
+        let stride = inputs
+            .iter()
+            .map(|input| offset(input) + 1)
+            .max()
+            .unwrap_or(1);
+        let vertex = inputs
+            .iter()
+            .find(|input| attribute(input, "semantic") == Some("VERTEX"))
+            .map(|input| offset(input))
+            .ok_or_else(|| format!("{} without vertices", polygon))?;
+        // Each <p> of <polygons> is one polygon, the other primitives list them all in one <p>
+        let mut polygons = vec![];
+        for p in children(primitive, "p") {
+            let indices: Vec<usize> = numbers(p)?;
+            let corners: Vec<_> = indices
+                .chunks_exact(stride)
+                .map(|corner| corner[vertex])
+                .collect();
+            let counts = match polygon {
+                "polygons" => vec![corners.len()],
+                "polylist" => child(primitive, "vcount")
+                    .map(numbers::<usize>)
+                    .transpose()?
+                    .unwrap_or_default(),
+                _ => vec![3; corners.len() / 3],
+            };
+            let mut start = 0;
+            for count in counts {
+                let polygon = corners
+                    .get(start..start + count)
+                    .ok_or("Too few indices for the polygons")?;
+                polygons.push(polygon.to_vec());
+                start += count;
+            }
+        }
+        for polygon in polygons.iter().filter(|polygon| polygon.len() >= 3) {
+            if polygon.iter().any(|index| *index >= surface.vertices.len()) {
+                return Err("Vertex index out of range".to_string());
+            }
+            for index in 1..polygon.len() - 1 {
+                surface
+                    .triangles
+                    .push([polygon[0], polygon[index], polygon[index + 1]]);
+            }
+        }
+    }
+    Ok(surface)
+}
+
+/// Returns the transform of a node relative to its parent, the product of its transform
+/// elements in order.
+fn node_transform(node: &XmlElement) -> Result<Matrix4<f64>, String> {
+    let mut transform = Matrix4::identity();
+    for element in elements(node) {
+        let values: Vec<f64> = match &*element.name {
+            "matrix" | "translate" | "rotate" | "scale" => numbers(element)?,
+            _ => continue,
+        };
+        let local = match (&*element.name, &values[..]) {
+            // Matrices are written row by row
+            ("matrix", values) if values.len() == 16 => Matrix4::from_row_slice(values),
+            ("translate", [x, y, z]) => Matrix4::new_translation(&Vector3::new(*x, *y, *z)),
+            ("rotate", [x, y, z, angle]) => Rotation3::from_axis_angle(
+                &Unit::new_normalize(Vector3::new(*x, *y, *z)),
+                angle.to_radians(),
+            )
+            .to_homogeneous(),
+            ("scale", [x, y, z]) => Matrix4::new_nonuniform_scaling(&Vector3::new(*x, *y, *z)),
+            (name, _) => return Err(format!("Invalid {} in node", name)),
+        };
+        transform *= local;
+    }
+    Ok(transform)
+}
+
+fn add_node(
+    node: &XmlElement,
+    parent: &Matrix4<f64>,
+    geometries: &HashMap<&str, (&str, TriangleMesh)>,
+    meshes: &mut Vec<(String, TriangleMesh)>,
+) -> Result<(), String> {
+    let transform = parent * node_transform(node)?;
+    for element in elements(node) {
+        match &*element.name {
+            "instance_geometry" => {
+                let id = reference(element, "url").unwrap_or_default();
+                let (name, mesh) = geometries
+                    .get(id)
+                    .ok_or_else(|| format!("Geometry {} not found", id))?;
+                meshes.push((name.to_string(), mesh.transformed(&transform)));
+            }
+            "node" => add_node(element, &transform, geometries, meshes)?,
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+pub(super) fn read(bytes: &[u8]) -> Result<Vec<(String, TriangleMesh)>, String> {
+    let mut reader = yaserde::de::Deserializer::new_from_reader(bytes);
+    let root = crate::deserialize_element(&mut reader)?;
+    let meter = child(&root, "asset")
+        .and_then(|asset| child(asset, "unit"))
+        .and_then(|unit| attribute(unit, "meter"))
+        .and_then(|meter| meter.parse::<f64>().ok())
+        .unwrap_or(1.0);
+
+    let mut geometries = HashMap::new();
+    let mut order = vec![];
+    for library in children(&root, "library_geometries") {
+        for geometry in children(library, "geometry") {
+            let Some(mesh) = child(geometry, "mesh") else {
+                continue;
+            };
+            let id = attribute(geometry, "id").unwrap_or_default();
+            let name = attribute(geometry, "name").unwrap_or(id);
+            order.push(id);
+            geometries.insert(id, (name, read_mesh(mesh)?));
+        }
+    }
+
+    // The scene that is instantiated, or the first one
+    let scenes: Vec<_> = children(&root, "library_visual_scenes")
+        .flat_map(|library| children(library, "visual_scene"))
+        .collect();
+    let instantiated = child(&root, "scene")
+        .and_then(|scene| child(scene, "instance_visual_scene"))
+        .and_then(|instance| reference(instance, "url"));
+    let scene = scenes
+        .iter()
+        .find(|scene| attribute(scene, "id") == instantiated)
+        .or(scenes.first());
+
+    let mut meshes = vec![];
+    match scene {
+        Some(scene) => {
+            for node in children(scene, "node") {
+                add_node(node, &Matrix4::identity(), &geometries, &mut meshes)?;
+            }
+        }
+        None => {
+            for id in order.iter() {
+                let (name, mesh) = &geometries[id];
+                meshes.push((name.to_string(), mesh.clone()));
+            }
+        }
+    }
+    let unit = Matrix4::new_scaling(meter);
+    Ok(meshes
+        .into_iter()
+        .map(|(name, mesh)| (name, mesh.transformed(&unit)))
+        .collect())
+}
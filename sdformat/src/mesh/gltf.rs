@@ -0,0 +1,90 @@
+//! glTF files, as JSON with external or embedded buffers, or binary, read with the `gltf` crate.
+//! Meshes are named after them and placed by the nodes of the default scene.
+
+use std::path::Path;
+
+use gltf::mesh::Mode;
+use gltf::{Gltf, Node};
+use nalgebra::{Matrix4, Vector3};
+
+use super::TriangleMesh;
+
+fn read_mesh(
+    mesh: gltf::Mesh,
+    buffers: &[gltf::buffer::Data],
+) -> Result<(String, TriangleMesh), String> {
+    let name = mesh
+        .name()
+        .map_or_else(|| format!("mesh{}", mesh.index()), str::to_string);
+    let mut surface = TriangleMesh::default();
+    for primitive in mesh.primitives() {
+        // Only triangle lists have a surface
+        if primitive.mode() != Mode::Triangles {
+            continue;
+        }
+        let reader = primitive.reader(|buffer| buffers.get(buffer.index()).map(|data| &data[..]));
+        let vertices: Vec<_> = reader
+            .read_positions()
+            .ok_or("Primitive without positions")?
+            .map(|position| Vector3::from(position.map(f64::from)))
+            .collect();
+        let indices: Vec<usize> = match reader.read_indices() {
+            Some(indices) => indices.into_u32().map(|index| index as usize).collect(),
+            None => (0..vertices.len()).collect(),
+        };
+        if indices.iter().any(|index| *index >= vertices.len()) {
+            return Err("Vertex index out of range".to_string());
+        }
+        surface.append(&TriangleMesh {
+            vertices,
+            triangles: indices
+                .chunks_exact(3)
+                .map(|triangle| [triangle[0], triangle[1], triangle[2]])
+                .collect(),
+        });
+    }
+    Ok((name, surface))
+}
+
+fn add_node(
+    node: Node,
+    parent: &Matrix4<f64>,
+    buffers: &[gltf::buffer::Data],
+    meshes: &mut Vec<(String, TriangleMesh)>,
+) -> Result<(), String> {
+    // Matrices are written column by column
+    let local = Matrix4::from_fn(|row, column| node.transform().matrix()[column][row] as f64);
+    let transform = parent * local;
+    if let Some(mesh) = node.mesh() {
+        let (name, mesh) = read_mesh(mesh, buffers)?;
+        meshes.push((name, mesh.transformed(&transform)));
+    }
+    for child in node.children() {
+        add_node(child, &transform, buffers, meshes)?;
+    }
+    Ok(())
+}
+
+pub(super) fn read(bytes: &[u8], path: &Path) -> Result<Vec<(String, TriangleMesh)>, String> {
+    let Gltf { document, blob } = Gltf::from_slice(bytes).map_err(|e| e.to_string())?;
+    let buffers =
+        gltf::import_buffers(&document, path.parent(), blob).map_err(|e| e.to_string())?;
+
+    let mut meshes = vec![];
+    match document
+        .default_scene()
+        .or_else(|| document.scenes().next())
+    {
+        Some(scene) => {
+            for node in scene.nodes() {
+                add_node(node, &Matrix4::identity(), &buffers, &mut meshes)?;
+            }
+        }
+        None => {
+            for mesh in document.meshes() {
+                meshes.push(read_mesh(mesh, &buffers)?);
+            }
+        }
+    }
+    Ok(meshes)
+}
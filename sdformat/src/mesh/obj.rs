@@ -0,0 +1,48 @@
+//! Wavefront OBJ files, read with `tobj`. Objects and groups are meshes named after them, faces
+//! are triangulated.
+
+use nalgebra::Vector3;
+
+use super::TriangleMesh;
+
+pub(super) fn read(bytes: &[u8]) -> Result<Vec<(String, TriangleMesh)>, String> {
+    let options = tobj::LoadOptions {
+        triangulate: true,
+        ..Default::default()
+    };
+    // Materials do not change the surface, they are not loaded
+    let (models, _) = tobj::load_obj_buf(&mut &bytes[..], &options, |_| {
+        Err(tobj::LoadError::OpenFileFailed)
+    })
+    .map_err(|e| e.to_string())?;
+    models
+        .into_iter()
+        .filter(|model| !model.mesh.indices.is_empty())
+        .map(|model| {
+            let vertices: Vec<_> = model
+                .mesh
+                .positions
+                .chunks_exact(3)
+                .map(|values| Vector3::new(values[0], values[1], values[2]))
+                .collect();
+            let indices = &model.mesh.indices;
+            if indices
+                .iter()
+                .any(|index| *index as usize >= vertices.len())
+            {
+                return Err("Vertex index out of range".to_string());
+            }
+            let triangles = indices
+                .chunks_exact(3)
+                .map(|triangle| [0, 1, 2].map(|corner| triangle[corner] as usize))
+                .collect();
+            Ok((
+                model.name,
+                TriangleMesh {
+                    vertices,
+                    triangles,
+                },
+            ))
+        })
+        .collect()
+}
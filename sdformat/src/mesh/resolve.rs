@@ -0,0 +1,54 @@
+//! Resolution of the URIs of meshes to paths.
+
+use std::path::PathBuf;
+
+/// Resolves the URI of a mesh to the path of its file.
+pub trait MeshResolver {
+    fn resolve(&self, uri: &str) -> Result<PathBuf, String>;
+}
+
+impl<F: Fn(&str) -> Result<PathBuf, String>> MeshResolver for F {
+    fn resolve(&self, uri: &str) -> Result<PathBuf, String> {
+        self(uri)
+    }
+}
+
+/// Resolves paths, `file://` URIs and `model://` URIs.
+///
+/// Relative paths are relative to `base`, usually the directory of the SDFormat file, and
+/// `model://name/path` is looked up as `name/path` in each of the `model_paths` in turn, like
+/// the resource paths of Gazebo.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PathResolver {
+    pub base: PathBuf,
+    pub model_paths: Vec<PathBuf>,
+}
+
+impl PathResolver {
+    pub fn new(base: impl Into<PathBuf>) -> Self {
+        Self {
+            base: base.into(),
+            model_paths: vec![],
+        }
+    }
+}
+
+impl MeshResolver for PathResolver {
+    fn resolve(&self, uri: &str) -> Result<PathBuf, String> {
+        if let Some(path) = uri.strip_prefix("file://") {
+            return Ok(self.base.join(path));
+        }
+        if let Some(path) = uri.strip_prefix("model://") {
+            return self
+                .model_paths
+                .iter()
+                .map(|directory| directory.join(path))
+                .find(|candidate| candidate.exists())
+                .ok_or_else(|| format!("Could not find {} in the model paths", uri));
+        }
+        if let Some((scheme, _)) = uri.split_once("://") {
+            return Err(format!("Unsupported scheme {} in {}", scheme, uri));
+        }
+        Ok(self.base.join(uri))
+    }
+}
@@ -0,0 +1,66 @@
+//! STL files, binary or ASCII, read with `stl_io`. Each solid of ASCII files is a mesh named
+//! after it.
+
+use std::io::Cursor;
+
+use nalgebra::Vector3;
+
+use super::TriangleMesh;
+
+/// Reads one solid, whose vertices `stl_io` merges.
+fn read_solid(bytes: Vec<u8>) -> Result<TriangleMesh, String> {
+    let solid = stl_io::read_stl(&mut Cursor::new(bytes)).map_err(|e| e.to_string())?;
+    Ok(TriangleMesh {
+        vertices: solid
+            .vertices
+            .iter()
+            .map(|vertex| Vector3::from(vertex.0.map(f64::from)))
+            .collect(),
+        triangles: solid.faces.iter().map(|face| face.vertices).collect(),
+    })
+}
+
+pub(super) fn read(bytes: &[u8]) -> Result<Vec<(String, TriangleMesh)>, String> {
+    // Binary files may also start with "solid", their size is the reliable sign
+    if bytes.len() >= 84 {
+        let count = u32::from_le_bytes(bytes[80..84].try_into().unwrap()) as usize;
+        if bytes.len() == 84 + count * 50 {
+            // Clears the header so that stl_io does not take it for ASCII
+            let mut binary = bytes.to_vec();
+            binary[..80].fill(0);
+            return Ok(vec![(String::new(), read_solid(binary)?)]);
+        }
+    }
+    let text = std::str::from_utf8(bytes).map_err(|_| "Invalid STL file".to_string())?;
+    if !text.trim_start().starts_with("solid") {
+        return Err("Invalid STL file".to_string());
+    }
+    // stl_io reads a single solid, so each is given to it separately
+    let mut meshes = vec![];
+    let mut solid: Option<(String, String)> = None;
+    for line in text.lines() {
+        let mut tokens = line.split_whitespace();
+        match tokens.next() {
+            Some("solid") => {
+                let name = tokens.collect::<Vec<_>>().join(" ");
+                solid = Some((name.clone(), format!("solid {}\n", name)));
+            }
+            Some("endsolid") => {
+                let (name, mut text) = solid.take().ok_or("Unexpected endsolid")?;
+                text.push_str("endsolid\n");
+                meshes.push((name, read_solid(text.into_bytes())?));
+            }
+            Some(_) => {
+                let (_, text) = solid.as_mut().ok_or("Facet outside of a solid")?;
+                text.push_str(line.trim());
+                text.push('\n');
+            }
+            None => {}
+        }
+    }
+    if let Some((name, mut text)) = solid {
+        text.push_str("endsolid\n");
+        meshes.push((name, read_solid(text.into_bytes())?));
+    }
+    Ok(meshes)
+}
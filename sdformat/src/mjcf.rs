@@ -0,0 +1,1107 @@
+//! Conversion from and to MJCF, the XML format of MuJoCo.
+//!
+//! Links become bodies nested following the kinematic tree, joints are placed in the body of
+//! their child link and links without a parent joint get a free joint unless their model is
+//! static. Visuals are written as geoms that take no part in collisions and have no mass. Mesh
+//! URIs must be paths to files MuJoCo can load: STL, OBJ or MSH. See [`from_mjcf`] for the
+//! conversion in the other direction.
+
+use std::collections::{HashMap, HashSet};
+
+use nalgebra::{Isometry3, Matrix3, UnitQuaternion, Vector3};
+
+use crate::cst::{Document, Element};
+use crate::frames::{scoped_name, FrameGraph, FrameKind};
+use crate::inertial::MassProperties;
+use crate::writer::{format_numbers, write_root, WriteOptions};
+use crate::{
+    SdfBoxShape, SdfCapsuleShape, SdfCollision, SdfCylinderShape, SdfEllipsoidShape, SdfFrame,
+    SdfGeometry, SdfInertial, SdfInertialInertia, SdfInertialPose, SdfJoint, SdfJointAxis,
+    SdfJointAxisDynamics, SdfJointAxisLimit, SdfLink, SdfMaterial, SdfMeshShape, SdfModel,
+    SdfPlaneShape, SdfPose, SdfSphereShape, SdfVisual, SdfWorld, Vector3d,
+};
+
+/// Converts a world into an MJCF document.
+pub fn world_to_mjcf(world: &SdfWorld) -> Result<String, String> {
+    let graph = FrameGraph::from_world(world)?;
+    let mut exporter = Exporter::new(&graph);
+    for model in world.model.iter() {
+        exporter.add_model(model, &model.name)?;
+    }
+    for light in world.light.iter() {
+        exporter.add_light(light.name.as_str(), light)?;
+    }
+
+    let mut option = Element::new("option");
+    let physics = world
+        .physics
+        .iter()
+        .find(|physics| physics.default == Some(true))
+        .or(world.physics.first());
+    if let Some(physics) = physics {
+        option.set_attribute("timestep", &format_numbers(&[physics.max_step_size]));
+    }
+    let gravity = world.gravity.0;
+    option.set_attribute(
+        "gravity",
+        &format_numbers(&[gravity.x, gravity.y, gravity.z]),
+    );
+    Ok(exporter.finish(&world.name, Some(option)))
+}
+
+/// Converts a model into an MJCF document, the model frame is placed at the origin of the world.
+pub fn model_to_mjcf(model: &SdfModel) -> Result<String, String> {
+    let graph = FrameGraph::from_model(model)?;
+    let mut exporter = Exporter::new(&graph);
+    exporter.add_model(model, "")?;
+    Ok(exporter.finish(&model.name, None))
+}
+
+fn pose_attributes(element: &mut Element, pose: &Isometry3<f64>) {
+    let t = pose.translation.vector;
+    if t.norm() > 1e-12 {
+        element.set_attribute("pos", &format_numbers(&[t.x, t.y, t.z]));
+    }
+    let q = pose.rotation;
+    if q.angle() > 1e-12 {
+        element.set_attribute("quat", &format_numbers(&[q.w, q.i, q.j, q.k]));
+    }
+}
+
+fn rgba(material: Option<&SdfMaterial>) -> Option<String> {
+    let material = material?;
+    material.diffuse.clone().or(material.ambient.clone())
+}
+
+/// A model being exported along with the joint connecting each link to its parent link.
+struct ModelTree<'a> {
+    model: &'a SdfModel,
+    scope: &'a str,
+    parents: HashMap<&'a str, (&'a SdfJoint, String)>,
+    is_static: bool,
+}
+
+struct Exporter<'a> {
+    graph: &'a FrameGraph,
+    assets: Vec<Element>,
+    worldbody: Vec<Element>,
+}
+
+impl<'a> Exporter<'a> {
+    fn new(graph: &'a FrameGraph) -> Self {
+        Self {
+            graph,
+            assets: vec![],
+            worldbody: vec![],
+        }
+    }
+
+    fn finish(self, name: &str, option: Option<Element>) -> String {
+        let mut mujoco = Element::new("mujoco");
+        mujoco.set_attribute("model", name);
+        let mut compiler = Element::new("compiler");
+        compiler.set_attribute("angle", "radian");
+        mujoco.push_element(compiler);
+        if let Some(option) = option {
+            mujoco.push_element(option);
+        }
+        if !self.assets.is_empty() {
+            let mut asset = Element::new("asset");
+            for element in self.assets {
+                asset.push_element(element);
+            }
+            mujoco.push_element(asset);
+        }
+        let mut worldbody = Element::new("worldbody");
+        for element in self.worldbody {
+            worldbody.push_element(element);
+        }
+        mujoco.push_element(worldbody);
+        write_root(&mujoco, &WriteOptions::default())
+    }
+
+    fn add_light(&mut self, name: &str, light: &crate::SdfLight) -> Result<(), String> {
+        let pose = self
+            .graph
+            .resolve_pose(light.pose.as_ref(), "", self.graph.root())?;
+        let mut element = Element::new("light");
+        element.set_attribute("name", name);
+        let t = pose.translation.vector;
+        element.set_attribute("pos", &format_numbers(&[t.x, t.y, t.z]));
+        if light.r#type != "point" {
+            let dir = pose.rotation * light.direction.0;
+            element.set_attribute("dir", &format_numbers(&[dir.x, dir.y, dir.z]));
+        }
+        if light.r#type == "directional" {
+            element.set_attribute("directional", "true");
+        }
+        if let Some(diffuse) = light.diffuse.as_ref() {
+            let rgb: Vec<_> = diffuse.split_whitespace().take(3).collect();
+            element.set_attribute("diffuse", &rgb.join(" "));
+        }
+        self.worldbody.push(element);
+        Ok(())
+    }
+
+    /// Adds the bodies of a model whose frame is `scope` in the graph.
+    fn add_model(&mut self, model: &SdfModel, scope: &str) -> Result<(), String> {
+        if !model.model.is_empty() {
+            return Err(format!(
+                "Model {} has nested models, they must be flattened first",
+                model.name
+            ));
+        }
+        if !model.include.is_empty() {
+            return Err(format!(
+                "Model {} has includes, they must be resolved first",
+                model.name
+            ));
+        }
+        let is_static = model.r#static == Some(true);
+
+        // The joint connecting each link to its parent
+        let mut parents: HashMap<&str, (&SdfJoint, String)> = HashMap::new();
+        for joint in model.joint.iter() {
+            let child = scoped_name(scope, &joint.child);
+            if self.graph.kind(&child) != Some(FrameKind::Link) {
+                return Err(format!(
+                    "The child {} of joint {} is not a link",
+                    joint.child, joint.name
+                ));
+            }
+            let parent = match joint.parent.as_str() {
+                "world" => "world".to_string(),
+                parent => self.graph.attached_link(&scoped_name(scope, parent))?,
+            };
+            if let Some((other, _)) = parents.insert(&joint.child, (joint, parent)) {
+                return Err(format!(
+                    "Link {} is the child of joints {} and {}, closed kinematic loops are not \
+                     supported",
+                    joint.child, other.name, joint.name
+                ));
+            }
+        }
+
+        let world = self.graph.root().to_string();
+        let tree = ModelTree {
+            model,
+            scope,
+            parents,
+            is_static,
+        };
+        let mut visited = HashSet::new();
+        for link in model.link.iter() {
+            let root = match tree.parents.get(link.name.as_str()) {
+                None => true,
+                Some((_, parent)) => parent == "world",
+            };
+            if !root {
+                continue;
+            }
+            let body = self.body(&tree, link, &world, &mut visited)?;
+            self.worldbody.push(body);
+        }
+        if let Some(link) = model.link.iter().find(|link| !visited.contains(&link.name)) {
+            return Err(format!(
+                "The joints of link {} form a closed kinematic loop",
+                link.name
+            ));
+        }
+        Ok(())
+    }
+
+    /// Creates the body of a link and of its descendants, `parent` is the frame of the parent
+    /// body.
+    fn body(
+        &mut self,
+        tree: &ModelTree,
+        link: &SdfLink,
+        parent: &str,
+        visited: &mut HashSet<String>,
+    ) -> Result<Element, String> {
+        let scope = tree.scope;
+        visited.insert(link.name.clone());
+        let frame = scoped_name(scope, &link.name);
+        let link_pose = self.graph.pose_in_root(&frame)?;
+        let mut body = Element::new("body");
+        body.set_attribute("name", &frame);
+        pose_attributes(&mut body, &self.graph.pose(&frame, parent)?);
+
+        // Links without an inertial have the default one of SDFormat: a unit mass and inertia
+        let default = SdfInertial::default();
+        let inertial = link.inertial.as_ref().unwrap_or(&default);
+        let pose = match inertial.pose.as_ref() {
+            Some(pose) => pose.to_pose().get_pose()?.isometry(),
+            None => Isometry3::identity(),
+        };
+        let mut element = Element::new("inertial");
+        let t = pose.translation.vector;
+        element.set_attribute("pos", &format_numbers(&[t.x, t.y, t.z]));
+        if pose.rotation.angle() > 1e-12 {
+            let q = pose.rotation;
+            element.set_attribute("quat", &format_numbers(&[q.w, q.i, q.j, q.k]));
+        }
+        element.set_attribute("mass", &format_numbers(&[inertial.mass.unwrap_or(1.0)]));
+        let i = inertial.inertia.clone().unwrap_or_default();
+        let (ixx, iyy, izz) = match inertial.inertia {
+            Some(_) => (i.ixx, i.iyy, i.izz),
+            None => (1.0, 1.0, 1.0),
+        };
+        element.set_attribute(
+            "fullinertia",
+            &format_numbers(&[
+                ixx,
+                iyy,
+                izz,
+                i.ixy.unwrap_or_default(),
+                i.ixz.unwrap_or_default(),
+                i.iyz.unwrap_or_default(),
+            ]),
+        );
+        body.push_element(element);
+
+        match tree.parents.get(link.name.as_str()) {
+            Some((joint, _)) if !tree.is_static => {
+                for element in self.joints(tree.model, joint, scope)? {
+                    body.push_element(element);
+                }
+            }
+            None if !tree.is_static => body.push_element(Element::new("freejoint")),
+            _ => {}
+        }
+
+        let in_link = |pose: Option<&SdfPose>| -> Result<Isometry3<f64>, String> {
+            Ok(link_pose.inverse() * self.graph.resolve_pose(pose, scope, &frame)?)
+        };
+        for collision in link.collision.iter() {
+            let mut geom = self.geom(
+                &scoped_name(&frame, &collision.name),
+                &collision.geometry,
+                &in_link(collision.pose.as_ref())?,
+            )?;
+            geom.set_attribute("group", "3");
+            body.push_element(geom);
+        }
+        for visual in link.visual.iter() {
+            let mut geom = self.geom(
+                &scoped_name(&frame, &visual.name),
+                &visual.geometry,
+                &in_link(visual.pose.as_ref())?,
+            )?;
+            geom.set_attribute("contype", "0");
+            geom.set_attribute("conaffinity", "0");
+            geom.set_attribute("group", "1");
+            geom.set_attribute("density", "0");
+            if let Some(rgba) = rgba(visual.material.as_ref()) {
+                geom.set_attribute("rgba", &rgba);
+            }
+            body.push_element(geom);
+        }
+
+        for child in tree.model.link.iter() {
+            let is_child = tree
+                .parents
+                .get(child.name.as_str())
+                .is_some_and(|(_, parent)| *parent == frame);
+            if is_child && !visited.contains(&child.name) {
+                let element = self.body(tree, child, &frame, visited)?;
+                body.push_element(element);
+            }
+        }
+        Ok(body)
+    }
+
+    /// Creates the joints of the body of the child link of a joint defined by `model`.
+    fn joints(
+        &self,
+        model: &SdfModel,
+        joint: &SdfJoint,
+        scope: &str,
+    ) -> Result<Vec<Element>, String> {
+        let name = scoped_name(scope, &joint.name);
+        let child = scoped_name(scope, &joint.child);
+        let pose = self.graph.pose(&name, &child)?;
+        let hinge = |name: &str,
+                     kind: &str,
+                     axis: Vector3<f64>,
+                     range: Option<(f64, f64)>,
+                     damping: Option<f64>,
+                     friction: Option<f64>| {
+            let mut element = Element::new("joint");
+            element.set_attribute("name", name);
+            element.set_attribute("type", kind);
+            let t = pose.translation.vector;
+            if t.norm() > 1e-12 {
+                element.set_attribute("pos", &format_numbers(&[t.x, t.y, t.z]));
+            }
+            if kind != "ball" {
+                let axis = pose.rotation * axis;
+                element.set_attribute("axis", &format_numbers(&[axis.x, axis.y, axis.z]));
+            }
+            // Very large limits are how unlimited joints are written
+            if let Some((lower, upper)) = range.filter(|(l, u)| *l > -1e15 && *u < 1e15) {
+                element.set_attribute("limited", "true");
+                element.set_attribute("range", &format_numbers(&[lower, upper]));
+            }
+            if let Some(damping) = damping {
+                element.set_attribute("damping", &format_numbers(&[damping]));
+            }
+            if let Some(friction) = friction.filter(|friction| *friction != 0.0) {
+                element.set_attribute("frictionloss", &format_numbers(&[friction]));
+            }
+            element
+        };
+
+        let axis = joint.axis.as_ref();
+        let xyz = match axis {
+            Some(_) => joint.axis_in_joint_frame(model)?,
+            None => Vector3::z(),
+        };
+        let range = axis
+            .and_then(|axis| axis.limit.as_ref())
+            .map(|limit| (limit.lower, limit.upper));
+        let dynamics = axis.and_then(|axis| axis.dynamics.as_ref());
+        let damping = dynamics.and_then(|dynamics| dynamics.damping);
+        let friction = dynamics.and_then(|dynamics| dynamics.friction);
+        let first = |kind| hinge(&name, kind, xyz, range, damping, friction);
+        Ok(match joint.r#type.as_str() {
+            "fixed" => vec![],
+            "revolute" => vec![first("hinge")],
+            "continuous" => vec![hinge(&name, "hinge", xyz, None, damping, friction)],
+            "prismatic" => vec![first("slide")],
+            "ball" => vec![hinge(&name, "ball", xyz, None, damping, friction)],
+            "universal" | "revolute2" => {
+                let axis2 = joint.axis2.as_ref();
+                let xyz2 = match axis2 {
+                    Some(_) => joint.axis2_in_joint_frame(model)?,
+                    None => Vector3::y(),
+                };
+                let range2 = axis2.and_then(|axis| Some((axis.limit.lower?, axis.limit.upper?)));
+                let dynamics2 = axis2.and_then(|axis| axis.dynamics.as_ref());
+                vec![
+                    first("hinge"),
+                    hinge(
+                        &format!("{}_2", name),
+                        "hinge",
+                        xyz2,
+                        range2,
+                        dynamics2.and_then(|dynamics| dynamics.damping),
+                        dynamics2.and_then(|dynamics| dynamics.friction),
+                    ),
+                ]
+            }
+            other => {
+                return Err(format!(
+                    "Joint {} of type {} cannot be represented in MJCF",
+                    joint.name, other
+                ))
+            }
+        })
+    }
+
+    fn geom(
+        &mut self,
+        name: &str,
+        geometry: &SdfGeometry,
+        pose: &Isometry3<f64>,
+    ) -> Result<Element, String> {
+        let mut geom = Element::new("geom");
+        geom.set_attribute("name", name);
+        let mut pose = *pose;
+        let (kind, size) = match geometry {
+            SdfGeometry::Box(shape) => ("box", (shape.size.0 / 2.0).as_slice().to_vec()),
+            SdfGeometry::Sphere(shape) => ("sphere", vec![shape.radius]),
+            SdfGeometry::Cylinder(shape) => ("cylinder", vec![shape.radius, shape.length / 2.0]),
+            SdfGeometry::Capsule(shape) => ("capsule", vec![shape.radius, shape.length / 2.0]),
+            SdfGeometry::Ellipsoid(shape) => ("ellipsoid", shape.radii.0.as_slice().to_vec()),
+            SdfGeometry::Plane(shape) => {
+                // MuJoCo planes face +z, a size of zero makes them infinite
+                let normal = shape.normal.0;
+                if let Some(rotation) = UnitQuaternion::rotation_between(&Vector3::z(), &normal) {
+                    pose.rotation *= rotation;
+                }
+                let size = shape
+                    .size
+                    .split_whitespace()
+                    .map(|v| v.parse::<f64>().map(|v| v / 2.0))
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(|_| format!("Invalid plane size {:?}", shape.size))?;
+                let mut size = size;
+                size.resize(2, 0.0);
+                size.push(1.0);
+                ("plane", size)
+            }
+            SdfGeometry::Mesh(shape) => {
+                let mut mesh = Element::new("mesh");
+                mesh.set_attribute("name", name);
+                mesh.set_attribute("file", mesh_file(name, &shape.uri)?);
+                if let Some(scale) = shape.scale.as_ref() {
+                    mesh.set_attribute(
+                        "scale",
+                        &format_numbers(&[scale.0.x, scale.0.y, scale.0.z]),
+                    );
+                }
+                self.assets.push(mesh);
+                geom.set_attribute("type", "mesh");
+                geom.set_attribute("mesh", name);
+                pose_attributes(&mut geom, &pose);
+                return Ok(geom);
+            }
+            SdfGeometry::Empty => return Err(format!("{}: missing geometry", name)),
+            _ => {
+                return Err(format!(
+                    "{}: heightmap, image and polyline geometries cannot be represented in MJCF",
+                    name
+                ))
+            }
+        };
+        geom.set_attribute("type", kind);
+        geom.set_attribute("size", &format_numbers(&size));
+        pose_attributes(&mut geom, &pose);
+        Ok(geom)
+    }
+}
+
+/// Returns the path of a mesh file that MuJoCo can load. URIs such as `model://` must be resolved
+/// beforehand and only STL, OBJ and MSH files are supported.
+fn mesh_file<'a>(name: &str, uri: &'a str) -> Result<&'a str, String> {
+    let path = uri.strip_prefix("file://").unwrap_or(uri);
+    if path.contains("://") {
+        return Err(format!(
+            "{}: mesh URI {} must be resolved to a file before exporting to MJCF",
+            name, uri
+        ));
+    }
+    let extension = path
+        .rsplit_once('.')
+        .map(|(_, extension)| extension.to_ascii_lowercase());
+    match extension.as_deref() {
+        Some("stl" | "obj" | "msh") => Ok(path),
+        _ => Err(format!(
+            "{}: mesh {} cannot be loaded by MuJoCo, only STL, OBJ and MSH files are supported",
+            name, uri
+        )),
+    }
+}
+
+/// Settings of `<compiler>` that affect how the document is read.
+struct Compiler {
+    degrees: bool,
+    eulerseq: String,
+    meshdir: String,
+}
+
+/// Attributes of the elements of each class of `<default>`, by element name.
+type Defaults = HashMap<String, HashMap<String, HashMap<String, String>>>;
+
+/// Reads the classes of a `<default>` element, nested classes inherit from their parent.
+fn read_defaults(
+    element: &Element,
+    inherited: &HashMap<String, HashMap<String, String>>,
+    defaults: &mut Defaults,
+) {
+    let class = element
+        .attribute("class")
+        .unwrap_or_else(|| "main".to_string());
+    let mut own = inherited.clone();
+    for child in element.elements().filter(|child| child.name() != "default") {
+        let attributes = own.entry(child.name().to_string()).or_default();
+        for name in child.attribute_names() {
+            if let Some(value) = child.attribute(name) {
+                attributes.insert(name.to_string(), value);
+            }
+        }
+    }
+    for child in element.elements().filter(|child| child.name() == "default") {
+        read_defaults(child, &own, defaults);
+    }
+    defaults.insert(class, own);
+}
+
+/// An element along with the default values of its class.
+struct Resolved<'a> {
+    element: &'a Element,
+    defaults: Option<&'a HashMap<String, String>>,
+}
+
+impl Resolved<'_> {
+    fn get(&self, name: &str) -> Option<String> {
+        self.element.attribute(name).or_else(|| {
+            self.defaults
+                .and_then(|defaults| defaults.get(name).cloned())
+        })
+    }
+
+    fn numbers(&self, name: &str) -> Result<Option<Vec<f64>>, String> {
+        self.get(name)
+            .map(|value| {
+                value
+                    .split_whitespace()
+                    .map(|v| v.parse::<f64>())
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(|_| {
+                        format!("Invalid {} {:?} in <{}>", name, value, self.element.name())
+                    })
+            })
+            .transpose()
+    }
+
+    fn vector(&self, name: &str) -> Result<Option<Vector3<f64>>, String> {
+        match self.numbers(name)? {
+            Some(v) if v.len() == 3 => Ok(Some(Vector3::new(v[0], v[1], v[2]))),
+            Some(_) => Err(format!(
+                "Expected 3 values for {} in <{}>",
+                name,
+                self.element.name()
+            )),
+            None => Ok(None),
+        }
+    }
+
+    fn number(&self, name: &str) -> Result<Option<f64>, String> {
+        Ok(self.numbers(name)?.and_then(|v| v.first().copied()))
+    }
+}
+
+struct Importer {
+    compiler: Compiler,
+    defaults: Defaults,
+    meshes: HashMap<String, (String, Option<Vector3<f64>>)>,
+    materials: HashMap<String, String>,
+    model: SdfModel,
+    /// Used to name elements without a name.
+    counter: usize,
+}
+
+impl Importer {
+    fn resolve<'a>(&'a self, element: &'a Element, class: &str) -> Resolved<'a> {
+        let class = element
+            .attribute("class")
+            .unwrap_or_else(|| class.to_string());
+        Resolved {
+            element,
+            defaults: self
+                .defaults
+                .get(&class)
+                .and_then(|classes| classes.get(element.name())),
+        }
+    }
+
+    fn angle(&self, value: f64) -> f64 {
+        match self.compiler.degrees {
+            true => value.to_radians(),
+            false => value,
+        }
+    }
+
+    /// Reads the position and orientation of an element, in any of the forms MJCF allows.
+    fn pose(&self, element: &Resolved) -> Result<Isometry3<f64>, String> {
+        let position = element.vector("pos")?.unwrap_or_else(Vector3::zeros);
+        let rotation = if let Some(q) = element.numbers("quat")? {
+            if q.len() != 4 {
+                return Err("Expected 4 values for quat".to_string());
+            }
+            UnitQuaternion::from_quaternion(nalgebra::Quaternion::new(q[0], q[1], q[2], q[3]))
+        } else if let Some(a) = element.numbers("axisangle")? {
+            if a.len() != 4 {
+                return Err("Expected 4 values for axisangle".to_string());
+            }
+            let axis = nalgebra::Unit::new_normalize(Vector3::new(a[0], a[1], a[2]));
+            UnitQuaternion::from_axis_angle(&axis, self.angle(a[3]))
+        } else if let Some(e) = element.vector("euler")? {
+            let mut rotation = UnitQuaternion::identity();
+            for (idx, axis) in self.compiler.eulerseq.chars().enumerate() {
+                let angle = self.angle(e[idx]);
+                let unit = match axis.to_ascii_lowercase() {
+                    'x' => Vector3::x_axis(),
+                    'y' => Vector3::y_axis(),
+                    _ => Vector3::z_axis(),
+                };
+                let step = UnitQuaternion::from_axis_angle(&unit, angle);
+                // Lower case axes rotate with the frame, upper case axes are fixed
+                rotation = match axis.is_ascii_lowercase() {
+                    true => rotation * step,
+                    false => step * rotation,
+                };
+            }
+            rotation
+        } else if let Some(v) = element.numbers("xyaxes")? {
+            if v.len() != 6 {
+                return Err("Expected 6 values for xyaxes".to_string());
+            }
+            let x = Vector3::new(v[0], v[1], v[2]).normalize();
+            let y = Vector3::new(v[3], v[4], v[5]);
+            let y = (y - x * x.dot(&y)).normalize();
+            let z = x.cross(&y);
+            let matrix = nalgebra::Matrix3::from_columns(&[x, y, z]);
+            UnitQuaternion::from_rotation_matrix(&nalgebra::Rotation3::from_matrix_unchecked(
+                matrix,
+            ))
+        } else if let Some(z) = element.vector("zaxis")? {
+            UnitQuaternion::rotation_between(&Vector3::z(), &z).unwrap_or_else(|| {
+                UnitQuaternion::from_axis_angle(&Vector3::x_axis(), std::f64::consts::PI)
+            })
+        } else {
+            UnitQuaternion::identity()
+        };
+        Ok(Isometry3::from_parts(position.into(), rotation))
+    }
+
+    fn next_name(&mut self, prefix: &str) -> String {
+        self.counter += 1;
+        format!("{}_{}", prefix, self.counter)
+    }
+
+    /// Adds a body and its descendants. `parent` is the link of the parent body, `None` for the
+    /// world body.
+    fn body(&mut self, element: &Element, parent: Option<&str>, class: &str) -> Result<(), String> {
+        let resolved = self.resolve(element, class);
+        let pose = self.pose(&resolved)?;
+        let name = match element.attribute("name") {
+            Some(name) => name,
+            None => self.next_name("body"),
+        };
+        let class = element
+            .attribute("childclass")
+            .unwrap_or_else(|| class.to_string());
+        let mut link = SdfLink {
+            name: name.clone(),
+            pose: Some(SdfPose::from_isometry(&pose, parent.map(str::to_string))),
+            ..Default::default()
+        };
+
+        // Like MuJoCo, bodies without an inertial get the one of their geoms, unless a mesh
+        // would have to be loaded
+        let mut from_geoms = Some(MassProperties::default());
+        for (idx, geom) in element
+            .elements()
+            .filter(|e| e.name() == "geom")
+            .enumerate()
+        {
+            let properties = self.geom(&mut link, geom, &class, idx)?;
+            from_geoms = from_geoms
+                .zip(properties)
+                .map(|(total, properties)| total.combined(&properties));
+        }
+        link.inertial = match element.child("inertial") {
+            Some(inertial) => Some(self.inertial(inertial, &class)?),
+            None => from_geoms
+                .filter(|total| total.mass > 0.0)
+                .map(|total| total.to_inertial()),
+        };
+        for site in element.elements().filter(|e| e.name() == "site") {
+            let resolved = self.resolve(site, &class);
+            let Some(site_name) = site.attribute("name") else {
+                continue;
+            };
+            self.model.frame.push(SdfFrame {
+                name: site_name,
+                attached_to: Some(name.clone()),
+                pose: Some(SdfPose::from_isometry(&self.pose(&resolved)?, None)),
+                ..Default::default()
+            });
+        }
+
+        // Joints are applied in order, intermediate links with a negligible mass connect them
+        let joints: Vec<_> = element
+            .elements()
+            .filter(|e| matches!(e.name(), "joint" | "freejoint"))
+            .collect();
+        let free = joints.iter().any(|joint| {
+            joint.name() == "freejoint"
+                || self.resolve(joint, &class).get("type").as_deref() == Some("free")
+        });
+        if free {
+            if parent.is_some() {
+                return Err(format!(
+                    "Body {} has a free joint but is not at the top",
+                    name
+                ));
+            }
+        } else if joints.is_empty() {
+            self.model.joint.push(SdfJoint {
+                name: format!("{}_fixed", name),
+                r#type: "fixed".to_string(),
+                parent: parent.unwrap_or("world").to_string(),
+                child: name.clone(),
+                ..Default::default()
+            });
+        } else {
+            let mut previous = parent.unwrap_or("world").to_string();
+            for (idx, joint) in joints.iter().enumerate() {
+                let joint_name = match joint.attribute("name") {
+                    Some(joint_name) => joint_name,
+                    None => self.next_name(&format!("{}_joint", name)),
+                };
+                let child = if idx + 1 == joints.len() {
+                    name.clone()
+                } else {
+                    let intermediate = format!("{}_{}", name, joint_name);
+                    // Without an inertial the link would weigh the default 1 kg
+                    let negligible = MassProperties {
+                        mass: INTERMEDIATE_MASS,
+                        center: Vector3::zeros(),
+                        inertia: Matrix3::identity() * INTERMEDIATE_MASS,
+                    };
+                    self.model.link.push(SdfLink {
+                        name: intermediate.clone(),
+                        pose: link.pose.clone(),
+                        inertial: Some(negligible.to_inertial()),
+                        ..Default::default()
+                    });
+                    intermediate
+                };
+                let sdf = self.joint(joint, &class, joint_name, &previous, &child)?;
+                self.model.joint.push(sdf);
+                previous = child;
+            }
+        }
+        self.model.link.push(link);
+
+        for child in element.elements().filter(|e| e.name() == "body") {
+            self.body(child, Some(&name), &class)?;
+        }
+        Ok(())
+    }
+
+    fn joint(
+        &self,
+        element: &Element,
+        class: &str,
+        name: String,
+        parent: &str,
+        child: &str,
+    ) -> Result<SdfJoint, String> {
+        let resolved = self.resolve(element, class);
+        let kind = resolved.get("type").unwrap_or_else(|| "hinge".to_string());
+        let position = resolved.vector("pos")?.unwrap_or_else(Vector3::zeros);
+        let axis = resolved.vector("axis")?.unwrap_or_else(Vector3::z);
+        let range = resolved.numbers("range")?.filter(|range| range.len() == 2);
+        let limited = match resolved.get("limited").as_deref() {
+            Some("true") => true,
+            Some("false") => false,
+            // Limits are enabled by the presence of a range
+            _ => range.is_some(),
+        };
+        let range = range.filter(|_| limited);
+        let r#type = match (kind.as_str(), range.is_some()) {
+            ("hinge", true) => "revolute",
+            ("hinge", false) => "continuous",
+            ("slide", _) => "prismatic",
+            ("ball", _) => "ball",
+            (other, _) => return Err(format!("Unsupported type {} of joint {}", other, name)),
+        };
+        let limit = range.map(|range| {
+            let (lower, upper) = match kind.as_str() {
+                "hinge" => (self.angle(range[0]), self.angle(range[1])),
+                _ => (range[0], range[1]),
+            };
+            SdfJointAxisLimit {
+                lower,
+                upper,
+                ..Default::default()
+            }
+        });
+        let damping = resolved.number("damping")?;
+        let friction = resolved.number("frictionloss")?;
+        let dynamics = (damping.is_some() || friction.is_some()).then(|| SdfJointAxisDynamics {
+            damping,
+            friction,
+            ..Default::default()
+        });
+        let pose = Isometry3::translation(position.x, position.y, position.z);
+        Ok(SdfJoint {
+            name,
+            r#type: r#type.to_string(),
+            parent: parent.to_string(),
+            child: child.to_string(),
+            pose: (position.norm() > 0.0).then(|| SdfPose::from_isometry(&pose, None)),
+            axis: (r#type != "ball").then(|| SdfJointAxis {
+                xyz: Vector3d(axis.normalize()).into(),
+                limit: limit.filter(|_| r#type != "ball"),
+                dynamics,
+                ..Default::default()
+            }),
+            ..Default::default()
+        })
+    }
+
+    fn inertial(&self, element: &Element, class: &str) -> Result<SdfInertial, String> {
+        let resolved = self.resolve(element, class);
+        let pose = self.pose(&resolved)?;
+        let inertia = match (
+            resolved.numbers("diaginertia")?,
+            resolved.numbers("fullinertia")?,
+        ) {
+            (Some(d), _) if d.len() == 3 => Some(SdfInertialInertia {
+                ixx: d[0],
+                iyy: d[1],
+                izz: d[2],
+                ..Default::default()
+            }),
+            (_, Some(f)) if f.len() == 6 => Some(SdfInertialInertia {
+                ixx: f[0],
+                iyy: f[1],
+                izz: f[2],
+                ixy: Some(f[3]),
+                ixz: Some(f[4]),
+                iyz: Some(f[5]),
+                ..Default::default()
+            }),
+            _ => return Err("Inertial requires diaginertia or fullinertia".to_string()),
+        };
+        Ok(SdfInertial {
+            mass: resolved.number("mass")?,
+            pose: Some(SdfInertialPose::from_isometry(&pose)),
+            inertia,
+            ..Default::default()
+        })
+    }
+
+    /// Adds the visual and collision of a geom to a link, returns the mass properties of the geom
+    /// in the link frame unless it is a mesh.
+    fn geom(
+        &mut self,
+        link: &mut SdfLink,
+        element: &Element,
+        class: &str,
+        idx: usize,
+    ) -> Result<Option<MassProperties>, String> {
+        let resolved = self.resolve(element, class);
+        let kind = resolved.get("type").unwrap_or_else(|| "sphere".to_string());
+        let size = resolved.numbers("size")?.unwrap_or_default();
+        let size = |idx: usize| size.get(idx).copied().unwrap_or_default();
+        let mut pose = self.pose(&resolved)?;
+        let mut half_length = size(1);
+
+        // A segment replaces the position, orientation and length of elongated shapes
+        if let Some(fromto) = resolved.numbers("fromto")?.filter(|v| v.len() == 6) {
+            let from = Vector3::new(fromto[0], fromto[1], fromto[2]);
+            let to = Vector3::new(fromto[3], fromto[4], fromto[5]);
+            let rotation = UnitQuaternion::rotation_between(&Vector3::z(), &(to - from))
+                .unwrap_or_else(|| {
+                    UnitQuaternion::from_axis_angle(&Vector3::x_axis(), std::f64::consts::PI)
+                });
+            pose = Isometry3::from_parts(((from + to) / 2.0).into(), rotation);
+            half_length = (to - from).norm() / 2.0;
+        }
+
+        let geometry = match kind.as_str() {
+            "sphere" => SdfGeometry::Sphere(SdfSphereShape {
+                radius: size(0),
+                ..Default::default()
+            }),
+            "capsule" => SdfGeometry::Capsule(SdfCapsuleShape {
+                radius: size(0),
+                length: half_length * 2.0,
+                ..Default::default()
+            }),
+            "cylinder" => SdfGeometry::Cylinder(SdfCylinderShape {
+                radius: size(0),
+                length: half_length * 2.0,
+                ..Default::default()
+            }),
+            "box" => SdfGeometry::Box(SdfBoxShape {
+                size: Vector3d::new(size(0) * 2.0, size(1) * 2.0, size(2) * 2.0),
+                ..Default::default()
+            }),
+            "ellipsoid" => SdfGeometry::Ellipsoid(SdfEllipsoidShape {
+                radii: Vector3d::new(size(0), size(1), size(2)),
+                ..Default::default()
+            }),
+            "plane" => SdfGeometry::Plane(SdfPlaneShape {
+                normal: Vector3d::new(0.0, 0.0, 1.0),
+                // Planes with a size of zero are infinite in MuJoCo
+                size: match (size(0), size(1)) {
+                    (x, y) if x > 0.0 && y > 0.0 => format_numbers(&[x * 2.0, y * 2.0]),
+                    _ => "1 1".to_string(),
+                },
+                ..Default::default()
+            }),
+            "mesh" => {
+                let mesh = resolved
+                    .get("mesh")
+                    .ok_or_else(|| "Mesh geom without a mesh".to_string())?;
+                let (uri, scale) = self
+                    .meshes
+                    .get(&mesh)
+                    .cloned()
+                    .ok_or_else(|| format!("Unknown mesh {}", mesh))?;
+                SdfGeometry::Mesh(SdfMeshShape {
+                    uri,
+                    scale: scale.map(Vector3d),
+                    ..Default::default()
+                })
+            }
+            other => return Err(format!("Unsupported geom type {}", other)),
+        };
+
+        let name = match element.attribute("name") {
+            Some(name) => name,
+            None => format!("{}_geom_{}", link.name, idx),
+        };
+        let rgba = match resolved.get("material") {
+            Some(material) => self.materials.get(&material).cloned(),
+            None => None,
+        };
+        let rgba = resolved.get("rgba").or(rgba);
+
+        // The mass of a geom is given by its density unless it is set explicitly, planes have
+        // no mass
+        let properties = match &geometry {
+            SdfGeometry::Mesh(_) => None,
+            SdfGeometry::Plane(_) => Some(MassProperties::default()),
+            geometry => {
+                let density = resolved.number("density")?.unwrap_or(1000.0);
+                let properties = MassProperties::of_geometry(geometry, density)?;
+                let properties = match resolved.number("mass")? {
+                    Some(mass) if properties.mass > 0.0 => MassProperties {
+                        mass,
+                        inertia: properties.inertia * mass / properties.mass,
+                        ..properties
+                    },
+                    _ => properties,
+                };
+                Some(properties.transformed(&pose))
+            }
+        };
+
+        let pose = Some(SdfPose::from_isometry(&pose, None));
+        link.visual.push(SdfVisual {
+            name: name.clone(),
+            pose: pose.clone(),
+            geometry: geometry.clone(),
+            material: rgba.map(|rgba| SdfMaterial {
+                ambient: Some(rgba.clone()),
+                diffuse: Some(rgba),
+                ..Default::default()
+            }),
+            ..Default::default()
+        });
+        let collides = resolved.get("contype").as_deref() != Some("0")
+            || resolved.get("conaffinity").as_deref() != Some("0");
+        if collides {
+            link.collision.push(SdfCollision {
+                name,
+                pose,
+                geometry,
+                ..Default::default()
+            });
+        }
+        Ok(properties)
+    }
+}
+
+/// Mass and principal moments of inertia of the links between the joints of a body.
+const INTERMEDIATE_MASS: f64 = 1e-9;
+
+/// Converts an MJCF document into a model.
+///
+/// Every body becomes a link, connected to its parent by its joints. Bodies with several joints are
+/// connected through intermediate links with a negligible mass, bodies without joints are attached
+/// with a fixed joint and bodies with a free joint are left unconnected. Geoms become visuals, and
+/// collisions unless they cannot collide, and sites become frames. Bodies without an inertial get
+/// the mass properties of their geoms as MuJoCo computes them, with a density of 1000 by default,
+/// except for bodies with mesh geoms which keep the default inertial of SDFormat. Geoms attached
+/// directly to the world body are placed in a link named `world_body` fixed to the world.
+pub fn from_mjcf(mjcf: &str) -> Result<SdfModel, String> {
+    let document = Document::parse(mjcf)?;
+    let mujoco = document.root();
+    if mujoco.name() != "mujoco" {
+        return Err(format!("Expected <mujoco>, found <{}>", mujoco.name()));
+    }
+    let compiler = mujoco.child("compiler");
+    let compiler = Compiler {
+        degrees: compiler.and_then(|c| c.attribute("angle")).as_deref() != Some("radian"),
+        eulerseq: compiler
+            .and_then(|c| c.attribute("eulerseq"))
+            .unwrap_or_else(|| "xyz".to_string()),
+        // The asset directory applies to meshes unless they have their own directory
+        meshdir: compiler
+            .and_then(|c| c.attribute("meshdir").or_else(|| c.attribute("assetdir")))
+            .unwrap_or_default(),
+    };
+    if compiler.eulerseq.len() != 3 {
+        return Err(format!("Invalid eulerseq {:?}", compiler.eulerseq));
+    }
+
+    let mut defaults = Defaults::new();
+    for default in mujoco.elements().filter(|e| e.name() == "default") {
+        read_defaults(default, &HashMap::new(), &mut defaults);
+    }
+
+    let mut meshes = HashMap::new();
+    let mut materials = HashMap::new();
+    for asset in mujoco.elements().filter(|e| e.name() == "asset") {
+        for mesh in asset.elements().filter(|e| e.name() == "mesh") {
+            let Some(file) = mesh.attribute("file") else {
+                continue;
+            };
+            // Meshes are named after their file by default
+            let name = mesh.attribute("name").unwrap_or_else(|| {
+                let stem = file.rsplit('/').next().unwrap_or(&file);
+                stem.rsplit_once('.')
+                    .map_or(stem, |(stem, _)| stem)
+                    .to_string()
+            });
+            let uri = match compiler.meshdir.as_str() {
+                "" => file,
+                dir => format!("{}/{}", dir.trim_end_matches('/'), file),
+            };
+            let scale = Resolved {
+                element: mesh,
+                defaults: None,
+            }
+            .vector("scale")?;
+            meshes.insert(name, (uri, scale));
+        }
+        for material in asset.elements().filter(|e| e.name() == "material") {
+            if let (Some(name), Some(rgba)) =
+                (material.attribute("name"), material.attribute("rgba"))
+            {
+                materials.insert(name, rgba);
+            }
+        }
+    }
+
+    let mut importer = Importer {
+        compiler,
+        defaults,
+        meshes,
+        materials,
+        model: SdfModel {
+            name: mujoco
+                .attribute("model")
+                .unwrap_or_else(|| "mujoco".to_string()),
+            ..Default::default()
+        },
+        counter: 0,
+    };
+    for worldbody in mujoco.elements().filter(|e| e.name() == "worldbody") {
+        let geoms: Vec<_> = worldbody
+            .elements()
+            .filter(|e| e.name() == "geom")
+            .collect();
+        if !geoms.is_empty() {
+            let mut link = SdfLink {
+                name: "world_body".to_string(),
+                ..Default::default()
+            };
+            for (idx, geom) in geoms.into_iter().enumerate() {
+                importer.geom(&mut link, geom, "main", idx)?;
+            }
+            importer.model.link.push(link);
+            importer.model.joint.push(SdfJoint {
+                name: "world_body_fixed".to_string(),
+                r#type: "fixed".to_string(),
+                parent: "world".to_string(),
+                child: "world_body".to_string(),
+                ..Default::default()
+            });
+        }
+        for body in worldbody.elements().filter(|e| e.name() == "body") {
+            importer.body(body, None, "main")?;
+        }
+    }
+    Ok(importer.model)
+}
@@ -0,0 +1,320 @@
+//! Extrusions of polylines into closed triangle meshes.
+//!
+//! As in Gazebo, the polylines of an extrusion are closed polygons and a polygon inside an odd
+//! number of others is a hole. Polygons are extruded upwards from z = 0 to their height, holes
+//! go through the polygon directly around them.
+
+use nalgebra::{Vector2, Vector3};
+
+use crate::mesh::TriangleMesh;
+use crate::SdfPolylineShape;
+
+/// Parses the points of a polyline, `x y` pairs.
+pub(crate) fn parse_point(point: &str) -> Result<Vector2<f64>, String> {
+    let values: Vec<f64> = point
+        .split_whitespace()
+        .map(|value| value.parse::<f64>())
+        .collect::<Result<_, _>>()
+        .map_err(|e| format!("Invalid polyline point {}: {}", point, e))?;
+    match values[..] {
+        [x, y] => Ok(Vector2::new(x, y)),
+        _ => Err(format!("Invalid polyline point {}", point)),
+    }
+}
+
+impl SdfPolylineShape {
+    /// Returns the points of the polyline.
+    pub fn points(&self) -> Result<Vec<Vector2<f64>>, String> {
+        self.point.iter().map(|point| parse_point(point)).collect()
+    }
+
+    /// Returns the closed surface of the extruded polyline.
+    pub fn to_mesh(&self) -> Result<TriangleMesh, String> {
+        extrude(std::slice::from_ref(self))
+    }
+}
+
+/// The z component of the cross product of two vectors of the plane.
+fn cross(a: &Vector2<f64>, b: &Vector2<f64>) -> f64 {
+    a.x * b.y - a.y * b.x
+}
+
+/// Returns the points of a polyline without repeated or collinear points, which do not change
+/// the polygon but would make ears degenerate.
+fn ring(polyline: &SdfPolylineShape) -> Result<Vec<Vector2<f64>>, String> {
+    let mut points = polyline.points()?;
+    let mut index = 0;
+    while index < points.len() && points.len() >= 3 {
+        let count = points.len();
+        let (a, b, c) = (
+            points[(index + count - 1) % count],
+            points[index],
+            points[(index + 1) % count],
+        );
+        let (ab, bc) = (b - a, c - b);
+        if cross(&ab, &bc).abs() <= 1e-12 * ab.norm() * bc.norm() && ab.dot(&bc) >= 0.0 {
+            points.remove(index);
+            // The previous point may have become collinear
+            index = index.saturating_sub(1);
+        } else {
+            index += 1;
+        }
+    }
+    if points.len() < 3 {
+        return Err("A polyline needs at least 3 points that are not aligned".to_string());
+    }
+    Ok(points)
+}
+
+/// Twice the signed area of a polygon, positive when counterclockwise.
+fn signed_area(points: &[Vector2<f64>], polygon: &[usize]) -> f64 {
+    (0..polygon.len())
+        .map(|index| {
+            cross(
+                &points[polygon[index]],
+                &points[polygon[(index + 1) % polygon.len()]],
+            )
+        })
+        .sum()
+}
+
+/// Returns whether a point is inside a polygon, by the parity of the edges crossed by a ray
+/// along +x.
+fn contains(polygon: &[Vector2<f64>], point: &Vector2<f64>) -> bool {
+    let mut inside = false;
+    for index in 0..polygon.len() {
+        let (p, q) = (polygon[index], polygon[(index + 1) % polygon.len()]);
+        if (p.y > point.y) != (q.y > point.y)
+            && point.x < p.x + (point.y - p.y) * (q.x - p.x) / (q.y - p.y)
+        {
+            inside = !inside;
+        }
+    }
+    inside
+}
+
+/// Returns whether a point is inside a triangle or on its edges, whatever its orientation.
+fn in_triangle(point: &Vector2<f64>, a: &Vector2<f64>, b: &Vector2<f64>, c: &Vector2<f64>) -> bool {
+    let sides = [
+        cross(&(b - a), &(point - a)),
+        cross(&(c - b), &(point - b)),
+        cross(&(a - c), &(point - c)),
+    ];
+    sides.iter().all(|side| *side >= 0.0) || sides.iter().all(|side| *side <= 0.0)
+}
+
+/// Joins a clockwise hole to a counterclockwise polygon around it by a pair of opposite edges
+/// from the rightmost point of the hole to a point of the polygon it sees.
+fn bridge(points: &[Vector2<f64>], polygon: &mut Vec<usize>, hole: &[usize]) -> Result<(), String> {
+    let start = (0..hole.len())
+        .max_by(|a, b| points[hole[*a]].x.total_cmp(&points[hole[*b]].x))
+        .unwrap();
+    let m = points[hole[start]];
+
+    // The nearest edge of the polygon crossed by a ray along +x from the hole
+    let count = polygon.len();
+    let mut nearest: Option<(f64, usize)> = None;
+    for index in 0..count {
+        let next = (index + 1) % count;
+        let (p, q) = (points[polygon[index]], points[polygon[next]]);
+        let candidate = if p.y == m.y && p.x >= m.x {
+            (p.x, index)
+        } else if (p.y > m.y) == (q.y > m.y) {
+            continue;
+        } else {
+            let x = p.x + (m.y - p.y) * (q.x - p.x) / (q.y - p.y);
+            // The end of the edge furthest along the ray
+            (x, if p.x > q.x { index } else { next })
+        };
+        if candidate.0 >= m.x && nearest.is_none_or(|(x, _)| candidate.0 < x) {
+            nearest = Some(candidate);
+        }
+    }
+    let (x, mut target) = nearest.ok_or("A hole of a polyline is not inside it")?;
+
+    // Other points of the polygon may hide the end of the edge, the one closest to the ray
+    // inside the triangle they form with the hole is visible
+    let (hit, end) = (Vector2::new(x, m.y), points[polygon[target]]);
+    if end != hit {
+        let angle = |point: &Vector2<f64>| ((point.y - m.y).abs() / (point.x - m.x), point.x);
+        for (index, point) in polygon.iter().map(|index| points[*index]).enumerate() {
+            if point.x > m.x
+                && point != end
+                && in_triangle(&point, &m, &hit, &end)
+                && angle(&point) < angle(&points[polygon[target]])
+            {
+                target = index;
+            }
+        }
+    }
+
+    let mut joined = polygon[..=target].to_vec();
+    joined.extend(hole[start..].iter().chain(hole[..=start].iter()));
+    joined.extend(polygon[target..].iter());
+    *polygon = joined;
+    Ok(())
+}
+
+/// Returns whether the corner of a counterclockwise polygon at an index is an ear: a convex
+/// corner whose triangle contains no other point of the polygon.
+fn is_ear(points: &[Vector2<f64>], polygon: &[usize], index: usize) -> bool {
+    let count = polygon.len();
+    let corners = [(index + count - 1) % count, index, (index + 1) % count];
+    let [a, b, c] = corners.map(|corner| points[polygon[corner]]);
+    if cross(&(b - a), &(c - b)) <= 0.0 {
+        return false;
+    }
+    // Points of the polygon repeated by bridges are corners of the triangle
+    !polygon
+        .iter()
+        .map(|index| points[*index])
+        .any(|point| point != a && point != b && point != c && in_triangle(&point, &a, &b, &c))
+}
+
+/// Triangulates a counterclockwise polygon by clipping its ears.
+fn clip_ears(
+    points: &[Vector2<f64>],
+    mut polygon: Vec<usize>,
+    triangles: &mut Vec<[usize; 3]>,
+) -> Result<(), String> {
+    while polygon.len() >= 3 {
+        let count = polygon.len();
+        let index = match (0..count).find(|index| is_ear(points, &polygon, *index)) {
+            Some(index) => index,
+            // Rounding errors may leave flat corners only, they become degenerate triangles
+            None => (0..count)
+                .find(|index| {
+                    let [a, b, c] =
+                        [count - 1, 0, 1].map(|offset| points[polygon[(index + offset) % count]]);
+                    cross(&(b - a), &(c - b)).abs() <= 1e-12 * (b - a).norm() * (c - b).norm()
+                })
+                .ok_or("The polylines cannot be triangulated, they may intersect")?,
+        };
+        triangles.push([
+            polygon[(index + count - 1) % count],
+            polygon[index],
+            polygon[(index + 1) % count],
+        ]);
+        polygon.remove(index);
+    }
+    Ok(())
+}
+
+/// Returns the number of polygons around each polygon, odd for holes.
+fn depths(polygons: &[Vec<Vector2<f64>>]) -> Vec<usize> {
+    (0..polygons.len())
+        .map(|polygon| {
+            (0..polygons.len())
+                .filter(|other| inside(polygons, polygon, *other))
+                .count()
+        })
+        .collect()
+}
+
+/// Returns whether a polygon is inside another one. Polygons do not intersect, so testing one
+/// of their points is enough.
+fn inside(polygons: &[Vec<Vector2<f64>>], polygon: usize, other: usize) -> bool {
+    other != polygon && contains(&polygons[other], &polygons[polygon][0])
+}
+
+/// Returns the polygon directly around a polygon.
+fn parent(polygons: &[Vec<Vector2<f64>>], depths: &[usize], polygon: usize) -> Option<usize> {
+    (0..polygons.len())
+        .find(|other| depths[*other] + 1 == depths[polygon] && inside(polygons, polygon, *other))
+}
+
+/// Triangulates the area inside polygons, where polygons inside an odd number of others are
+/// holes. Triangles index the points of all the polygons one after the other and are
+/// counterclockwise. Polygons must not intersect nor repeat points.
+pub fn triangulate(polygons: &[Vec<Vector2<f64>>]) -> Result<Vec<[usize; 3]>, String> {
+    if polygons.iter().any(|polygon| polygon.len() < 3) {
+        return Err("A polygon needs at least 3 points".to_string());
+    }
+    let points: Vec<_> = polygons.iter().flatten().copied().collect();
+    let mut offset = 0;
+    let mut indices = vec![];
+    for polygon in polygons.iter() {
+        indices.push((offset..offset + polygon.len()).collect::<Vec<_>>());
+        offset += polygon.len();
+    }
+    let depths = depths(polygons);
+
+    let mut triangles = vec![];
+    for outer in (0..polygons.len()).filter(|polygon| depths[*polygon].is_multiple_of(2)) {
+        let mut polygon = indices[outer].clone();
+        if signed_area(&points, &polygon) < 0.0 {
+            polygon.reverse();
+        }
+        let mut holes: Vec<_> = (0..polygons.len())
+            .filter(|hole| parent(polygons, &depths, *hole) == Some(outer))
+            .map(|hole| {
+                let mut hole = indices[hole].clone();
+                if signed_area(&points, &hole) > 0.0 {
+                    hole.reverse();
+                }
+                hole
+            })
+            .collect();
+        // Holes are joined from right to left so that bridges do not cross holes to join
+        let right = |hole: &Vec<usize>| {
+            hole.iter()
+                .map(|index| points[*index].x)
+                .fold(f64::NEG_INFINITY, f64::max)
+        };
+        holes.sort_by(|a, b| right(b).total_cmp(&right(a)));
+        for hole in holes.iter() {
+            bridge(&points, &mut polygon, hole)?;
+        }
+        clip_ears(&points, polygon, &mut triangles)?;
+    }
+    Ok(triangles)
+}
+
+/// Returns the closed surface of the extrusion of polylines, whose triangles face outwards.
+/// Polylines inside an odd number of others are holes, they go up to the height of the polyline
+/// around them.
+pub fn extrude(polylines: &[SdfPolylineShape]) -> Result<TriangleMesh, String> {
+    let rings = polylines.iter().map(ring).collect::<Result<Vec<_>, _>>()?;
+    let triangles = triangulate(&rings)?;
+    let depths = depths(&rings);
+
+    let count = rings.iter().map(Vec::len).sum::<usize>();
+    let mut mesh = TriangleMesh::default();
+    for ring in rings.iter() {
+        mesh.vertices
+            .extend(ring.iter().map(|point| Vector3::new(point.x, point.y, 0.0)));
+    }
+    for (polyline, ring) in rings.iter().enumerate() {
+        let outer = match depths[polyline].is_multiple_of(2) {
+            true => Some(polyline),
+            false => parent(&rings, &depths, polyline),
+        };
+        let height = polylines[outer.unwrap_or(polyline)].height;
+        mesh.vertices.extend(
+            ring.iter()
+                .map(|point| Vector3::new(point.x, point.y, height)),
+        );
+    }
+    for [a, b, c] in triangles.iter().copied() {
+        mesh.triangles.push([c, b, a]);
+        mesh.triangles.push([count + a, count + b, count + c]);
+    }
+
+    // Walls go along outlines with the solid on their left: counterclockwise around polygons
+    // and clockwise around holes
+    let mut offset = 0;
+    for (polyline, ring) in rings.iter().enumerate() {
+        let mut outline: Vec<_> = (offset..offset + ring.len()).collect();
+        let clockwise = signed_area(ring, &(0..ring.len()).collect::<Vec<_>>()) < 0.0;
+        if clockwise == depths[polyline].is_multiple_of(2) {
+            outline.reverse();
+        }
+        for index in 0..outline.len() {
+            let (a, b) = (outline[index], outline[(index + 1) % outline.len()]);
+            mesh.triangles.push([a, b, count + b]);
+            mesh.triangles.push([a, count + b, count + a]);
+        }
+        offset += ring.len();
+    }
+    Ok(mesh)
+}
@@ -0,0 +1,126 @@
+//! Poses of elements: translations with a rotation written as Euler angles, in radians or
+//! degrees, or as an `x y z w` quaternion.
+
+use nalgebra::{Isometry3, Quaternion, Rotation3, Translation3, UnitQuaternion, Vector3};
+
+use crate::{SdfInertialPose, SdfPose};
+
+/// Simple implementation of pose
+pub struct Pose {
+    /// Translation vector
+    pub translation: Vector3<f64>,
+    /// Rotation
+    pub rotation: Rotation3<f64>,
+    /// Relative pose
+    pub relative_to: String,
+}
+
+impl Pose {
+    /// Returns the transform described by the pose, regardless of the frame it is relative to.
+    pub fn isometry(&self) -> Isometry3<f64> {
+        Isometry3::from_parts(
+            Translation3::from(self.translation),
+            UnitQuaternion::from_rotation_matrix(&self.rotation),
+        )
+    }
+}
+
+impl SdfPose {
+    /// Lazily retrieve the pose as an Isometry
+    /// In the event the pose is not parseable it returns a String based error.
+    pub fn get_pose(&self) -> Result<Pose, String> {
+        let digits = self
+            .data
+            .split_whitespace()
+            .map(|dig| dig.parse::<f64>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| format!("Failed to parse pose values from {:?}", self.data))?;
+
+        let relative_to = self.relative_to.clone().unwrap_or_default();
+
+        // An empty pose is the identity, as is the default value of the element
+        if digits.is_empty() {
+            return Ok(Pose {
+                translation: Vector3::zeros(),
+                rotation: Rotation3::identity(),
+                relative_to,
+            });
+        }
+
+        let rotation_format = self.rotation_format.as_deref().unwrap_or("euler_rpy");
+        if digits.len() == 6 && rotation_format == "euler_rpy" {
+            let translation = Vector3::new(digits[0], digits[1], digits[2]);
+            let scale = if self.degrees == Some(true) {
+                std::f64::consts::PI / 180.0
+            } else {
+                1.0
+            };
+            let rotation = Rotation3::from_euler_angles(
+                digits[3] * scale,
+                digits[4] * scale,
+                digits[5] * scale,
+            );
+            return Ok(Pose {
+                translation,
+                rotation,
+                relative_to,
+            });
+        } else if digits.len() == 7 && rotation_format != "euler_rpy" {
+            let translation = Vector3::new(digits[0], digits[1], digits[2]);
+            // Quaternions are written as x y z w
+            let quaternion = Quaternion::new(digits[6], digits[3], digits[4], digits[5]);
+            if quaternion.norm() == 0.0 {
+                return Err(format!("Invalid quaternion in pose {:?}", self.data));
+            }
+            let rotation = UnitQuaternion::from_quaternion(quaternion).to_rotation_matrix();
+            return Ok(Pose {
+                translation,
+                rotation,
+                relative_to,
+            });
+        }
+        Err("Failed to parse pose".to_string())
+    }
+
+    /// Creates a pose from a transform, written as `x y z roll pitch yaw` in radians.
+    pub fn from_isometry(isometry: &Isometry3<f64>, relative_to: Option<String>) -> Self {
+        let translation = isometry.translation.vector;
+        let (roll, pitch, yaw) = isometry.rotation.euler_angles();
+        // Avoid writing negative zeros
+        let value = |v: f64| if v == 0.0 { 0.0 } else { v };
+        SdfPose {
+            relative_to,
+            data: format!(
+                "{} {} {} {} {} {}",
+                value(translation.x),
+                value(translation.y),
+                value(translation.z),
+                value(roll),
+                value(pitch),
+                value(yaw)
+            ),
+            ..Default::default()
+        }
+    }
+}
+
+impl SdfInertialPose {
+    /// Returns the pose as the pose of any other element. Inertial poses are always relative to
+    /// their link.
+    pub fn to_pose(&self) -> SdfPose {
+        SdfPose {
+            rotation_format: self.rotation_format.clone(),
+            degrees: self.degrees,
+            data: self.data.clone(),
+            ..Default::default()
+        }
+    }
+
+    /// Creates a pose from a transform, written as `x y z roll pitch yaw` in radians.
+    pub fn from_isometry(isometry: &Isometry3<f64>) -> Self {
+        SdfInertialPose {
+            data: SdfPose::from_isometry(isometry, None).data,
+            ..Default::default()
+        }
+    }
+}
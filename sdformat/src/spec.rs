@@ -0,0 +1,89 @@
+//! Tables describing the elements and attributes of each specification version, generated from
+//! the spec files.
+
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+/// A child element or attribute of an element of the specification.
+pub(crate) struct SpecRow {
+    /// The element the row belongs to, named after the spec file and the path of nested elements.
+    pub scope: &'static str,
+    pub name: &'static str,
+    /// The scope describing the children of this element.
+    pub child_scope: &'static str,
+    pub rtype: &'static str,
+    pub default: Option<&'static str>,
+    pub required: bool,
+    pub attribute: bool,
+}
+
+include!(concat!(env!("OUT_DIR"), "/spec.rs"));
+
+/// The rows of one version of the specification, indexed by scope and name.
+pub(crate) struct Spec {
+    pub version: &'static str,
+    rows: &'static [SpecRow],
+    index: HashMap<(&'static str, &'static str, bool), &'static SpecRow>,
+}
+
+impl Spec {
+    /// Returns the specification of a version, if its tables are available.
+    pub(crate) fn get(version: &str) -> Option<&'static Spec> {
+        static SPECS: OnceLock<Vec<Spec>> = OnceLock::new();
+        SPECS
+            .get_or_init(|| {
+                SPEC_VERSIONS
+                    .iter()
+                    .map(|(version, rows)| Spec {
+                        version,
+                        rows,
+                        index: rows
+                            .iter()
+                            .map(|row| ((row.scope, row.name, row.attribute), row))
+                            .collect(),
+                    })
+                    .collect()
+            })
+            .iter()
+            .find(|spec| spec.version == version)
+    }
+
+    /// Returns the specification the types of this crate are generated from.
+    pub(crate) fn latest() -> &'static Spec {
+        Self::get("1.10").expect("Tables for the generated types are always available")
+    }
+
+    /// Returns the description of an element at the top of a document. Some files share the name
+    /// of their top level element, e.g. `model_state.sdf`, the one named after it is preferred.
+    pub(crate) fn root(&self, name: &str) -> Option<&'static SpecRow> {
+        let mut roots = self
+            .rows
+            .iter()
+            .filter(|row| row.scope.is_empty() && row.name == name);
+        let first = roots.next()?;
+        Some(
+            std::iter::once(first)
+                .chain(roots)
+                .find(|row| row.child_scope.strip_suffix(".sdf") == Some(name))
+                .unwrap_or(first),
+        )
+    }
+
+    /// Iterates over the child elements and attributes of a scope.
+    pub(crate) fn children<'a>(
+        &'a self,
+        scope: &'a str,
+    ) -> impl Iterator<Item = &'static SpecRow> + 'a {
+        self.rows.iter().filter(move |row| row.scope == scope)
+    }
+
+    /// Returns the description of a child element.
+    pub(crate) fn element(&self, scope: &str, name: &str) -> Option<&'static SpecRow> {
+        self.index.get(&(scope, name, false)).copied()
+    }
+
+    /// Returns the description of an attribute.
+    pub(crate) fn attribute(&self, scope: &str, name: &str) -> Option<&'static SpecRow> {
+        self.index.get(&(scope, name, true)).copied()
+    }
+}
@@ -0,0 +1,1110 @@
+//! Conversion from and to URDF robot descriptions. Imports follow the conversion done by
+//! libsdformat.
+//!
+//! Links whose parent joint is fixed are merged into the parent link by default, as libsdformat
+//! does. Their visuals, collisions, sensors and lights are moved to the parent link and explicit
+//! frames named after the joint and the link keep them available as frames. Settings such as
+//! `gravity` or `self_collide` are taken over by the parent link, lumping fails when they differ
+//! from the settings of the parent. Lumping can be disabled for
+//! all joints with [`UrdfOptions::lump_fixed_joints`] or for a single joint with the
+//! `preserveFixedJoint` or `disableFixedJointLumping` gazebo extensions.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+use nalgebra::{Isometry3, Translation3, UnitQuaternion, Vector3};
+
+use crate::cst::{Document, Element};
+use crate::frames::{FrameGraph, FrameKind};
+use crate::inertial::MassProperties;
+use crate::writer::{format_numbers, write_root, WriteOptions};
+use crate::{
+    ElementData, ElementMap, SdfBoxShape, SdfCollision, SdfCylinderShape, SdfFrame, SdfGeometry,
+    SdfInertial, SdfInertialInertia, SdfInertialPose, SdfJoint, SdfJointAxis, SdfJointAxisDynamics,
+    SdfJointAxisLimit, SdfJointPhysics, SdfJointPhysicsOde, SdfLink, SdfMaterial,
+    SdfMaterialScript, SdfMeshShape, SdfModel, SdfPlugin, SdfPose, SdfSensor, SdfSphereShape,
+    SdfSurface, SdfSurfaceContact, SdfSurfaceContactOde, SdfSurfaceFriction, SdfSurfaceFrictionOde,
+    SdfVisual, Vector3d, XmlElement,
+};
+
+/// Options for [`from_urdf_with_options`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct UrdfOptions {
+    /// Merge links attached with fixed joints into their parent link, enabled by default.
+    pub lump_fixed_joints: bool,
+}
+
+impl Default for UrdfOptions {
+    fn default() -> Self {
+        Self {
+            lump_fixed_joints: true,
+        }
+    }
+}
+
+/// Joint limits used for continuous joints, as libsdformat does.
+const CONTINUOUS_LIMIT: f64 = 1e16;
+
+/// Converts a URDF robot into a model.
+pub fn from_urdf(urdf: &str) -> Result<SdfModel, String> {
+    from_urdf_with_options(urdf, &UrdfOptions::default())
+}
+
+/// Converts a URDF robot into a model.
+pub fn from_urdf_with_options(urdf: &str, options: &UrdfOptions) -> Result<SdfModel, String> {
+    let document = Document::parse(urdf)?;
+    let robot = document.root();
+    if robot.name() != "robot" {
+        return Err(format!("Expected <robot>, found <{}>", robot.name()));
+    }
+    let mut materials = HashMap::new();
+    for material in robot.elements().filter(|e| e.name() == "material") {
+        let name = required_attribute(material, "name")?;
+        materials.insert(name, parse_material(material, &HashMap::new())?);
+    }
+
+    let mut model = SdfModel {
+        name: required_attribute(robot, "name")?,
+        ..Default::default()
+    };
+    let mut links = vec![];
+    for link in robot.elements().filter(|e| e.name() == "link") {
+        let link = parse_link(link, &materials)?;
+        if links
+            .iter()
+            .any(|other: &UrdfLink| other.sdf.name == link.sdf.name)
+        {
+            return Err(format!("Link {} is defined more than once", link.sdf.name));
+        }
+        links.push(link);
+    }
+    let mut joints = vec![];
+    for joint in robot.elements().filter(|e| e.name() == "joint") {
+        if let Some(joint) = parse_joint(joint)? {
+            joints.push(joint);
+        }
+    }
+
+    // Apply the gazebo extensions
+    for gazebo in robot.elements().filter(|e| e.name() == "gazebo") {
+        match gazebo.attribute("reference") {
+            None => apply_model_extension(&mut model, gazebo)?,
+            Some(reference) => {
+                if let Some(link) = links.iter_mut().find(|link| link.sdf.name == reference) {
+                    apply_link_extension(link, &mut model, gazebo)?;
+                } else if let Some(joint) = joints.iter_mut().find(|j| j.sdf.name == reference) {
+                    apply_joint_extension(joint, gazebo)?;
+                } else {
+                    model.extra.push(xml_element(gazebo));
+                }
+            }
+        }
+    }
+
+    // The world is not a link of the model, joints to it attach the model to the world
+    links.retain(|link| link.sdf.name != "world");
+    for joint in joints.iter() {
+        for body in [&joint.sdf.parent, &joint.sdf.child] {
+            if body != "world" && !links.iter().any(|link| &link.sdf.name == body) {
+                return Err(format!(
+                    "Joint {} refers to the unknown link {}",
+                    joint.sdf.name, body
+                ));
+            }
+        }
+        if joint.sdf.child == "world" {
+            return Err(format!("Joint {} has the world as child", joint.sdf.name));
+        }
+    }
+    let order = tree_order(&links, &joints)?;
+
+    // Walk the tree from the roots so that parents are lumped before their children
+    let mut lumped: HashMap<String, (String, Isometry3<f64>)> = HashMap::new();
+    let mut link_joints = vec![];
+    for child in order.iter() {
+        let Some(joint_idx) = joints.iter().position(|j| &j.sdf.child == child) else {
+            continue;
+        };
+        let joint = &joints[joint_idx];
+        let (parent, parent_pose) = match lumped.get(&joint.sdf.parent) {
+            Some((parent, pose)) => (parent.clone(), *pose),
+            None => (joint.sdf.parent.clone(), Isometry3::identity()),
+        };
+        let origin = parent_pose * joint.origin;
+        let lump = options.lump_fixed_joints
+            && joint.sdf.r#type == "fixed"
+            && !joint.preserve
+            && parent != "world";
+        if lump {
+            let child_idx = links.iter().position(|l| &l.sdf.name == child).unwrap();
+            let parent_idx = links.iter().position(|l| l.sdf.name == parent).unwrap();
+            let child_link = std::mem::take(&mut links[child_idx]);
+            lump_link(&mut links[parent_idx], child_link, &origin)?;
+            lumped.insert(child.clone(), (parent.clone(), origin));
+            model.frame.push(SdfFrame {
+                name: joint.sdf.name.clone(),
+                attached_to: Some(joint.sdf.parent.clone()),
+                pose: Some(SdfPose::from_isometry(&joint.origin, None)),
+                ..Default::default()
+            });
+            model.frame.push(SdfFrame {
+                name: child.clone(),
+                attached_to: Some(joint.sdf.name.clone()),
+                ..Default::default()
+            });
+            continue;
+        }
+        let mut sdf = joint.sdf.clone();
+        sdf.parent = parent.clone();
+        let child_idx = links.iter().position(|l| &l.sdf.name == child).unwrap();
+        if parent == "world" {
+            // Without a parent link the child is placed relative to the model
+            links[child_idx].sdf.pose = Some(SdfPose::from_isometry(&origin, None));
+        } else {
+            sdf.pose = Some(SdfPose::from_isometry(&origin, Some(parent)));
+            links[child_idx].sdf.pose = Some(SdfPose {
+                relative_to: Some(sdf.name.clone()),
+                data: "0 0 0 0 0 0".to_string(),
+                ..Default::default()
+            });
+        }
+        link_joints.push(sdf);
+    }
+
+    for name in order.iter() {
+        if lumped.contains_key(name) {
+            continue;
+        }
+        let idx = links.iter().position(|l| &l.sdf.name == name).unwrap();
+        let mut link = std::mem::take(&mut links[idx]);
+        link.sdf.inertial = link.inertial.map(|inertial| inertial.into_sdf());
+        model.link.push(link.sdf);
+    }
+    model.joint = link_joints;
+    Ok(model)
+}
+
+/// A link along with the data needed to merge other links into it.
+#[derive(Default)]
+struct UrdfLink {
+    sdf: SdfLink,
+    inertial: Option<Inertial>,
+}
+
+struct UrdfJoint {
+    sdf: SdfJoint,
+    origin: Isometry3<f64>,
+    /// Whether the joint is kept even if it is fixed.
+    preserve: bool,
+}
+
+/// The inertial of a link, either as written in the file or merged from several links.
+#[derive(Clone, Debug)]
+enum Inertial {
+    Original(Box<SdfInertial>),
+    Merged(MassProperties),
+}
+
+impl Inertial {
+    fn mass_properties(&self) -> Result<MassProperties, String> {
+        match self {
+            Inertial::Merged(properties) => Ok(properties.clone()),
+            Inertial::Original(inertial) => MassProperties::from_inertial(inertial),
+        }
+    }
+
+    fn into_sdf(self) -> SdfInertial {
+        match self {
+            Inertial::Original(inertial) => *inertial,
+            Inertial::Merged(properties) => properties.to_inertial(),
+        }
+    }
+}
+
+/// Moves the content of `child` into `parent`, `pose` is the pose of the child in the parent.
+fn lump_link(parent: &mut UrdfLink, child: UrdfLink, pose: &Isometry3<f64>) -> Result<(), String> {
+    let rename = |name: &str| format!("{}_fixed_joint_lump__{}", parent.sdf.name, name);
+    let moved = |p: &Option<SdfPose>| -> Result<Option<SdfPose>, String> {
+        let local = match p.as_ref() {
+            Some(p) => p.get_pose()?.isometry(),
+            None => Isometry3::identity(),
+        };
+        Ok(Some(SdfPose::from_isometry(&(pose * local), None)))
+    };
+    for mut visual in child.sdf.visual {
+        visual.name = rename(&visual.name);
+        visual.pose = moved(&visual.pose)?;
+        parent.sdf.visual.push(visual);
+    }
+    for mut collision in child.sdf.collision {
+        collision.name = rename(&collision.name);
+        collision.pose = moved(&collision.pose)?;
+        parent.sdf.collision.push(collision);
+    }
+    for mut sensor in child.sdf.sensor {
+        sensor.pose = moved(&sensor.pose)?;
+        parent.sdf.sensor.push(sensor);
+    }
+    for mut light in child.sdf.light {
+        light.pose = moved(&light.pose)?;
+        parent.sdf.light.push(light);
+    }
+    // The settings of the child apply to the whole merged link, they must agree with the parent
+    let parent_name = parent.sdf.name.clone();
+    let settings = [
+        ("gravity", &mut parent.sdf.gravity, child.sdf.gravity),
+        (
+            "enable_wind",
+            &mut parent.sdf.enable_wind,
+            child.sdf.enable_wind,
+        ),
+        (
+            "self_collide",
+            &mut parent.sdf.self_collide,
+            child.sdf.self_collide,
+        ),
+        ("kinematic", &mut parent.sdf.kinematic, child.sdf.kinematic),
+    ];
+    for (setting, value, child_value) in settings {
+        match (*value, child_value) {
+            (Some(value), Some(child_value)) if value != child_value => {
+                return Err(format!(
+                    "Link {} cannot be lumped into link {}, their {} settings differ",
+                    child.sdf.name, parent_name, setting
+                ));
+            }
+            (None, Some(_)) => *value = child_value,
+            _ => {}
+        }
+    }
+    for element in child.sdf.extra.all() {
+        parent.sdf.extra.push(element.clone());
+    }
+    if let Some(inertial) = child.inertial {
+        let child_mass = inertial.mass_properties()?.transformed(pose);
+        let merged = match parent.inertial.as_ref() {
+            Some(inertial) => inertial.mass_properties()?.combined(&child_mass),
+            None => child_mass,
+        };
+        parent.inertial = Some(Inertial::Merged(merged));
+    }
+    Ok(())
+}
+
+/// Orders the links with parents before their children, the roots come first in the order they
+/// are defined.
+fn tree_order(links: &[UrdfLink], joints: &[UrdfJoint]) -> Result<Vec<String>, String> {
+    let mut parents = HashMap::new();
+    for joint in joints.iter() {
+        if let Some(other) = parents.insert(joint.sdf.child.as_str(), joint.sdf.parent.as_str()) {
+            return Err(format!(
+                "Link {} has more than one parent, {} and {}",
+                joint.sdf.child, other, joint.sdf.parent
+            ));
+        }
+    }
+    let mut order = vec![];
+    let mut visited = HashSet::new();
+    let mut stack: Vec<&str> = links
+        .iter()
+        .map(|link| link.sdf.name.as_str())
+        .filter(|name| parents.get(name).is_none_or(|parent| *parent == "world"))
+        .rev()
+        .collect();
+    if stack.is_empty() && !links.is_empty() {
+        return Err("The links of the robot form a loop".to_string());
+    }
+    while let Some(link) = stack.pop() {
+        if !visited.insert(link) {
+            continue;
+        }
+        order.push(link.to_string());
+        let children = joints
+            .iter()
+            .filter(|joint| joint.sdf.parent == link)
+            .map(|joint| joint.sdf.child.as_str());
+        let children: Vec<_> = children.collect();
+        stack.extend(children.into_iter().rev());
+    }
+    if order.len() != links.len() {
+        return Err("The links of the robot do not form a tree".to_string());
+    }
+    Ok(order)
+}
+
+fn required_attribute(element: &Element, name: &str) -> Result<String, String> {
+    element
+        .attribute(name)
+        .ok_or_else(|| format!("Missing attribute {} of <{}>", name, element.name()))
+}
+
+fn parse_numbers(value: &str, count: usize) -> Result<Vec<f64>, String> {
+    let numbers = value
+        .split_whitespace()
+        .map(|v| v.parse::<f64>())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|_| format!("Unable to parse {:?} into floats", value))?;
+    if numbers.len() != count {
+        return Err(format!("Expected {} values, found {:?}", count, value));
+    }
+    Ok(numbers)
+}
+
+fn parse_vector(value: &str) -> Result<Vector3<f64>, String> {
+    let v = parse_numbers(value, 3)?;
+    Ok(Vector3::new(v[0], v[1], v[2]))
+}
+
+fn number_attribute(element: &Element, name: &str) -> Result<Option<f64>, String> {
+    element
+        .attribute(name)
+        .map(|value| {
+            value
+                .trim()
+                .parse::<f64>()
+                .map_err(|_| format!("Unable to parse {} of <{}>", name, element.name()))
+        })
+        .transpose()
+}
+
+fn parse_bool(element: &Element) -> Result<bool, String> {
+    match element.text().trim() {
+        "true" | "1" => Ok(true),
+        "false" | "0" => Ok(false),
+        value => Err(format!(
+            "Invalid boolean {:?} in <{}>",
+            value,
+            element.name()
+        )),
+    }
+}
+
+fn parse_number(element: &Element) -> Result<f64, String> {
+    element
+        .text()
+        .trim()
+        .parse::<f64>()
+        .map_err(|_| format!("Unable to parse <{}> into a float", element.name()))
+}
+
+/// Parses the `<origin>` child of an element, the identity if there is none.
+fn parse_origin(element: &Element) -> Result<Isometry3<f64>, String> {
+    let Some(origin) = element.child("origin") else {
+        return Ok(Isometry3::identity());
+    };
+    let xyz = match origin.attribute("xyz") {
+        Some(xyz) => parse_vector(&xyz)?,
+        None => Vector3::zeros(),
+    };
+    let rpy = match origin.attribute("rpy") {
+        Some(rpy) => parse_vector(&rpy)?,
+        None => Vector3::zeros(),
+    };
+    Ok(Isometry3::from_parts(
+        Translation3::from(xyz),
+        UnitQuaternion::from_euler_angles(rpy.x, rpy.y, rpy.z),
+    ))
+}
+
+fn parse_geometry(element: &Element) -> Result<SdfGeometry, String> {
+    let geometry = element
+        .child("geometry")
+        .ok_or_else(|| format!("Missing <geometry> in <{}>", element.name()))?;
+    let shape = geometry
+        .elements()
+        .next()
+        .ok_or_else(|| "Empty <geometry>".to_string())?;
+    let number = |name| {
+        number_attribute(shape, name)?
+            .ok_or_else(|| format!("Missing attribute {} of <{}>", name, shape.name()))
+    };
+    Ok(match shape.name() {
+        "box" => SdfGeometry::Box(SdfBoxShape {
+            size: Vector3d(parse_vector(&required_attribute(shape, "size")?)?),
+            ..Default::default()
+        }),
+        "cylinder" => SdfGeometry::Cylinder(SdfCylinderShape {
+            radius: number("radius")?,
+            length: number("length")?,
+            ..Default::default()
+        }),
+        "sphere" => SdfGeometry::Sphere(SdfSphereShape {
+            radius: number("radius")?,
+            ..Default::default()
+        }),
+        "mesh" => SdfGeometry::Mesh(SdfMeshShape {
+            uri: required_attribute(shape, "filename")?,
+            scale: shape
+                .attribute("scale")
+                .map(|scale| parse_vector(&scale).map(Vector3d))
+                .transpose()?,
+            ..Default::default()
+        }),
+        other => return Err(format!("Unsupported geometry <{}>", other)),
+    })
+}
+
+fn parse_material(
+    element: &Element,
+    materials: &HashMap<String, SdfMaterial>,
+) -> Result<SdfMaterial, String> {
+    if let Some(color) = element.child("color") {
+        let rgba = required_attribute(color, "rgba")?;
+        parse_numbers(&rgba, 4)?;
+        return Ok(SdfMaterial {
+            ambient: Some(rgba.clone()),
+            diffuse: Some(rgba),
+            ..Default::default()
+        });
+    }
+    let name = element.attribute("name").unwrap_or_default();
+    Ok(materials.get(&name).cloned().unwrap_or_default())
+}
+
+fn parse_link(
+    element: &Element,
+    materials: &HashMap<String, SdfMaterial>,
+) -> Result<UrdfLink, String> {
+    let name = required_attribute(element, "name")?;
+    let mut link = SdfLink {
+        name: name.clone(),
+        ..Default::default()
+    };
+    // Shapes without a name are named after the link, numbered from the second one on
+    let shape_name = |element: &Element, kind: &str, idx: usize| match element.attribute("name") {
+        Some(name) => name,
+        None if idx == 0 => format!("{}_{}", link.name, kind),
+        None => format!("{}_{}_{}", link.name, kind, idx),
+    };
+    let pose = |element: &Element| -> Result<Option<SdfPose>, String> {
+        Ok(element
+            .child("origin")
+            .map(|_| parse_origin(element))
+            .transpose()?
+            .map(|origin| SdfPose::from_isometry(&origin, None)))
+    };
+
+    let mut visuals = vec![];
+    for (idx, visual) in element
+        .elements()
+        .filter(|e| e.name() == "visual")
+        .enumerate()
+    {
+        visuals.push(SdfVisual {
+            name: shape_name(visual, "visual", idx),
+            pose: pose(visual)?,
+            geometry: parse_geometry(visual)?,
+            material: visual
+                .child("material")
+                .map(|material| parse_material(material, materials))
+                .transpose()?,
+            ..Default::default()
+        });
+    }
+    let mut collisions = vec![];
+    for (idx, collision) in element
+        .elements()
+        .filter(|e| e.name() == "collision")
+        .enumerate()
+    {
+        collisions.push(SdfCollision {
+            name: shape_name(collision, "collision", idx),
+            pose: pose(collision)?,
+            geometry: parse_geometry(collision)?,
+            ..Default::default()
+        });
+    }
+    link.visual = visuals;
+    link.collision = collisions;
+
+    let inertial = match element.child("inertial") {
+        Some(inertial) => {
+            let mass = match inertial.child("mass") {
+                Some(mass) => number_attribute(mass, "value")?,
+                None => None,
+            };
+            let inertia = match inertial.child("inertia") {
+                Some(inertia) => {
+                    let value = |name| -> Result<f64, String> {
+                        Ok(number_attribute(inertia, name)?.unwrap_or_default())
+                    };
+                    Some(SdfInertialInertia {
+                        ixx: value("ixx")?,
+                        ixy: Some(value("ixy")?),
+                        ixz: Some(value("ixz")?),
+                        iyy: value("iyy")?,
+                        iyz: Some(value("iyz")?),
+                        izz: value("izz")?,
+                        ..Default::default()
+                    })
+                }
+                None => None,
+            };
+            Some(Inertial::Original(Box::new(SdfInertial {
+                mass,
+                pose: pose(inertial)?.map(|pose| SdfInertialPose {
+                    data: pose.data,
+                    ..Default::default()
+                }),
+                inertia,
+                ..Default::default()
+            })))
+        }
+        None => None,
+    };
+    Ok(UrdfLink {
+        sdf: link,
+        inertial,
+    })
+}
+
+/// Parses a joint, floating joints are skipped since a link without a parent is free already.
+fn parse_joint(element: &Element) -> Result<Option<UrdfJoint>, String> {
+    let name = required_attribute(element, "name")?;
+    let kind = required_attribute(element, "type")?;
+    let link = |tag: &str| {
+        element
+            .child(tag)
+            .and_then(|e| e.attribute("link"))
+            .ok_or_else(|| format!("Missing <{}> in joint {}", tag, name))
+    };
+    let r#type = match kind.as_str() {
+        "revolute" | "continuous" | "prismatic" | "fixed" => kind.clone(),
+        "floating" => return Ok(None),
+        other => return Err(format!("Unsupported type {} of joint {}", other, name)),
+    };
+
+    let axis = match element.child("axis").and_then(|axis| axis.attribute("xyz")) {
+        Some(xyz) => parse_vector(&xyz)?,
+        None => Vector3::x(),
+    };
+    let limit = match element.child("limit") {
+        Some(limit) => {
+            let (lower, upper) = match kind.as_str() {
+                "continuous" => (-CONTINUOUS_LIMIT, CONTINUOUS_LIMIT),
+                _ => (
+                    number_attribute(limit, "lower")?.unwrap_or_default(),
+                    number_attribute(limit, "upper")?.unwrap_or_default(),
+                ),
+            };
+            Some(SdfJointAxisLimit {
+                lower,
+                upper,
+                effort: number_attribute(limit, "effort")?,
+                velocity: number_attribute(limit, "velocity")?,
+                ..Default::default()
+            })
+        }
+        None if kind == "continuous" => Some(SdfJointAxisLimit {
+            lower: -CONTINUOUS_LIMIT,
+            upper: CONTINUOUS_LIMIT,
+            ..Default::default()
+        }),
+        None => None,
+    };
+    let dynamics = match element.child("dynamics") {
+        Some(dynamics) => Some(SdfJointAxisDynamics {
+            damping: number_attribute(dynamics, "damping")?,
+            friction: number_attribute(dynamics, "friction")?,
+            ..Default::default()
+        }),
+        None => None,
+    };
+
+    Ok(Some(UrdfJoint {
+        sdf: SdfJoint {
+            name: name.clone(),
+            axis: (r#type != "fixed").then(|| SdfJointAxis {
+                xyz: Vector3d(axis).into(),
+                dynamics,
+                limit,
+                ..Default::default()
+            }),
+            r#type,
+            parent: link("parent")?,
+            child: link("child")?,
+            ..Default::default()
+        },
+        origin: parse_origin(element)?,
+        preserve: false,
+    }))
+}
+
+/// Converts an element that has no typed equivalent.
+fn xml_element(element: &Element) -> XmlElement {
+    let attributes = element
+        .attribute_names()
+        .filter_map(|name| Some((name.to_string(), element.attribute(name)?)))
+        .collect();
+    let data = match element.elements().next() {
+        Some(_) => {
+            let mut elements = ElementMap::default();
+            for child in element.elements() {
+                elements.push(xml_element(child));
+            }
+            ElementData::Nested(elements)
+        }
+        None => ElementData::String(element.text()),
+    };
+    XmlElement {
+        attributes,
+        name: Arc::from(element.name()),
+        data,
+    }
+}
+
+fn apply_model_extension(model: &mut SdfModel, gazebo: &Element) -> Result<(), String> {
+    for element in gazebo.elements() {
+        match element.name() {
+            "static" => model.r#static = Some(parse_bool(element)?),
+            "self_collide" | "selfCollide" => model.self_collide = Some(parse_bool(element)?),
+            "plugin" => model
+                .plugin
+                .push(yaserde::de::from_str::<SdfPlugin>(&element.to_string())?),
+            _ => model.extra.push(xml_element(element)),
+        }
+    }
+    Ok(())
+}
+
+fn apply_link_extension(
+    link: &mut UrdfLink,
+    model: &mut SdfModel,
+    gazebo: &Element,
+) -> Result<(), String> {
+    let link = &mut link.sdf;
+    for element in gazebo.elements() {
+        match element.name() {
+            "gravity" => link.gravity = Some(parse_bool(element)?),
+            "turnGravityOff" => link.gravity = Some(!parse_bool(element)?),
+            "selfCollide" | "self_collide" => link.self_collide = Some(parse_bool(element)?),
+            "kinematic" => link.kinematic = Some(parse_bool(element)?),
+            "sensor" => link
+                .sensor
+                .push(yaserde::de::from_str::<SdfSensor>(&element.to_string())?),
+            "plugin" => model
+                .plugin
+                .push(yaserde::de::from_str::<SdfPlugin>(&element.to_string())?),
+            "material" => {
+                let script = SdfMaterialScript {
+                    uri: vec!["file://media/materials/scripts/gazebo.material".to_string()],
+                    name: element.text().trim().to_string(),
+                    ..Default::default()
+                };
+                for visual in link.visual.iter_mut() {
+                    visual.material.get_or_insert_with(Default::default).script =
+                        Some(script.clone());
+                }
+            }
+            "mu1" | "mu2" => {
+                let value = parse_number(element)?;
+                for collision in link.collision.iter_mut() {
+                    let ode = friction_ode(collision);
+                    match element.name() {
+                        "mu1" => ode.mu = Some(value),
+                        _ => ode.mu2 = Some(value),
+                    }
+                }
+            }
+            "kp" | "kd" | "maxVel" | "minDepth" => {
+                let value = parse_number(element)?;
+                for collision in link.collision.iter_mut() {
+                    let ode = contact_ode(collision);
+                    match element.name() {
+                        "kp" => ode.kp = Some(value),
+                        "kd" => ode.kd = Some(value),
+                        "maxVel" => ode.max_vel = Some(value),
+                        _ => ode.min_depth = Some(value),
+                    }
+                }
+            }
+            _ => link.extra.push(xml_element(element)),
+        }
+    }
+    Ok(())
+}
+
+fn friction_ode(collision: &mut SdfCollision) -> &mut SdfSurfaceFrictionOde {
+    collision
+        .surface
+        .get_or_insert_with(SdfSurface::default)
+        .friction
+        .get_or_insert_with(SdfSurfaceFriction::default)
+        .ode
+        .get_or_insert_with(Default::default)
+}
+
+fn contact_ode(collision: &mut SdfCollision) -> &mut SdfSurfaceContactOde {
+    collision
+        .surface
+        .get_or_insert_with(SdfSurface::default)
+        .contact
+        .get_or_insert_with(SdfSurfaceContact::default)
+        .ode
+        .get_or_insert_with(Default::default)
+}
+
+fn apply_joint_extension(joint: &mut UrdfJoint, gazebo: &Element) -> Result<(), String> {
+    for element in gazebo.elements() {
+        match element.name() {
+            "preserveFixedJoint" | "disableFixedJointLumping" => {
+                joint.preserve = parse_bool(element)?
+            }
+            "provideFeedback" => {
+                joint
+                    .sdf
+                    .physics
+                    .get_or_insert_with(SdfJointPhysics::default)
+                    .provide_feedback = Some(parse_bool(element)?)
+            }
+            "implicitSpringDamper" => {
+                joint
+                    .sdf
+                    .physics
+                    .get_or_insert_with(SdfJointPhysics::default)
+                    .ode
+                    .get_or_insert_with(SdfJointPhysicsOde::default)
+                    .implicit_spring_damper = Some(parse_bool(element)?)
+            }
+            _ => joint.sdf.extra.push(xml_element(element)),
+        }
+    }
+    Ok(())
+}
+
+/// Creates an `<origin>` element, `None` for the identity.
+fn origin_element(pose: &Isometry3<f64>) -> Option<Element> {
+    if pose.translation.vector.norm() < 1e-12 && pose.rotation.angle() < 1e-12 {
+        return None;
+    }
+    let t = pose.translation.vector;
+    let (roll, pitch, yaw) = pose.rotation.euler_angles();
+    let mut origin = Element::new("origin");
+    origin.set_attribute("xyz", &format_numbers(&[t.x, t.y, t.z]));
+    origin.set_attribute("rpy", &format_numbers(&[roll, pitch, yaw]));
+    Some(origin)
+}
+
+fn geometry_element(geometry: &SdfGeometry, path: &str) -> Result<Element, String> {
+    let shape = match geometry {
+        SdfGeometry::Box(shape) => {
+            let size = shape.size.0;
+            let mut element = Element::new("box");
+            element.set_attribute("size", &format_numbers(&[size.x, size.y, size.z]));
+            element
+        }
+        SdfGeometry::Cylinder(shape) => {
+            let mut element = Element::new("cylinder");
+            element.set_attribute("radius", &format_numbers(&[shape.radius]));
+            element.set_attribute("length", &format_numbers(&[shape.length]));
+            element
+        }
+        SdfGeometry::Sphere(shape) => {
+            let mut element = Element::new("sphere");
+            element.set_attribute("radius", &format_numbers(&[shape.radius]));
+            element
+        }
+        SdfGeometry::Mesh(shape) => {
+            if shape.submesh.is_some() {
+                return Err(format!("{}: URDF meshes cannot select a submesh", path));
+            }
+            let mut element = Element::new("mesh");
+            element.set_attribute("filename", &shape.uri);
+            if let Some(scale) = shape.scale.as_ref() {
+                element.set_attribute("scale", &format_numbers(&[scale.0.x, scale.0.y, scale.0.z]));
+            }
+            element
+        }
+        SdfGeometry::Empty => return Err(format!("{}: missing geometry", path)),
+        other => {
+            let name = match other {
+                SdfGeometry::Capsule(_) => "capsule",
+                SdfGeometry::Ellipsoid(_) => "ellipsoid",
+                SdfGeometry::Heightmap(_) => "heightmap",
+                SdfGeometry::Image(_) => "image",
+                SdfGeometry::Plane(_) => "plane",
+                _ => "polyline",
+            };
+            return Err(format!(
+                "{}: {} geometry cannot be represented in URDF",
+                path, name
+            ));
+        }
+    };
+    let mut element = Element::new("geometry");
+    element.push_element(shape);
+    Ok(element)
+}
+
+fn inertial_element(inertial: &SdfInertial, pose: &Isometry3<f64>) -> Element {
+    let mut element = Element::new("inertial");
+    if let Some(origin) = origin_element(pose) {
+        element.push_element(origin);
+    }
+    let mut mass = Element::new("mass");
+    mass.set_attribute("value", &format_numbers(&[inertial.mass.unwrap_or(1.0)]));
+    element.push_element(mass);
+    // The defaults of the specification are a unit diagonal
+    let inertia = inertial
+        .inertia
+        .clone()
+        .unwrap_or_else(|| SdfInertialInertia {
+            ixx: 1.0,
+            iyy: 1.0,
+            izz: 1.0,
+            ..Default::default()
+        });
+    let mut urdf = Element::new("inertia");
+    for (name, value) in [
+        ("ixx", Some(inertia.ixx)),
+        ("ixy", inertia.ixy),
+        ("ixz", inertia.ixz),
+        ("iyy", Some(inertia.iyy)),
+        ("iyz", inertia.iyz),
+        ("izz", Some(inertia.izz)),
+    ] {
+        urdf.set_attribute(name, &format_numbers(&[value.unwrap_or_default()]));
+    }
+    element.push_element(urdf);
+    element
+}
+
+fn joint_element(
+    model: &SdfModel,
+    joint: &SdfJoint,
+    parent: &str,
+    origin: &Isometry3<f64>,
+) -> Result<Element, String> {
+    let limit = joint.axis.as_ref().and_then(|axis| axis.limit.as_ref());
+    let r#type = match joint.r#type.as_str() {
+        "revolute"
+            if limit
+                .is_some_and(|l| l.lower <= -CONTINUOUS_LIMIT && l.upper >= CONTINUOUS_LIMIT) =>
+        {
+            "continuous"
+        }
+        r#type @ ("revolute" | "continuous" | "prismatic" | "fixed") => r#type,
+        other => {
+            return Err(format!(
+                "Joint {} of type {} cannot be represented in URDF",
+                joint.name, other
+            ))
+        }
+    };
+    let mut element = Element::new("joint");
+    element.set_attribute("name", &joint.name);
+    element.set_attribute("type", r#type);
+    if let Some(origin) = origin_element(origin) {
+        element.push_element(origin);
+    }
+    let mut link = |name: &str, value: &str| {
+        let mut child = Element::new(name);
+        child.set_attribute("link", value);
+        element.push_element(child);
+    };
+    link("parent", parent);
+    link("child", &joint.child);
+    if r#type == "fixed" {
+        return Ok(element);
+    }
+
+    let axis = match joint.axis {
+        Some(_) => joint.axis_in_joint_frame(model)?,
+        None => Vector3::z(),
+    };
+    let mut urdf = Element::new("axis");
+    urdf.set_attribute("xyz", &format_numbers(&[axis.x, axis.y, axis.z]));
+    element.push_element(urdf);
+
+    // URDF requires limits on revolute and prismatic joints, unlimited efforts and velocities,
+    // missing or negative in SDFormat, are written as large values like unlimited positions
+    if r#type != "continuous" || limit.is_some() {
+        let mut urdf = Element::new("limit");
+        if r#type != "continuous" {
+            let (lower, upper) = limit.map_or((-CONTINUOUS_LIMIT, CONTINUOUS_LIMIT), |l| {
+                (l.lower, l.upper)
+            });
+            urdf.set_attribute("lower", &format_numbers(&[lower]));
+            urdf.set_attribute("upper", &format_numbers(&[upper]));
+        }
+        let unlimited = |value: Option<f64>| match value {
+            Some(value) if value >= 0.0 => value,
+            _ => CONTINUOUS_LIMIT,
+        };
+        let effort = unlimited(limit.and_then(|l| l.effort));
+        let velocity = unlimited(limit.and_then(|l| l.velocity));
+        urdf.set_attribute("effort", &format_numbers(&[effort]));
+        urdf.set_attribute("velocity", &format_numbers(&[velocity]));
+        element.push_element(urdf);
+    }
+    let dynamics = joint.axis.as_ref().and_then(|axis| axis.dynamics.as_ref());
+    if let Some(dynamics) = dynamics {
+        let mut urdf = Element::new("dynamics");
+        if let Some(damping) = dynamics.damping {
+            urdf.set_attribute("damping", &format_numbers(&[damping]));
+        }
+        if let Some(friction) = dynamics.friction {
+            urdf.set_attribute("friction", &format_numbers(&[friction]));
+        }
+        element.push_element(urdf);
+    }
+    Ok(element)
+}
+
+/// Converts a model into a URDF robot.
+///
+/// The frame of each URDF link is the frame of the joint connecting it to its parent, the poses
+/// of the content of the links are expressed accordingly. Explicit frames are only used to
+/// resolve poses and model plugins are written in a `<gazebo>` extension. Models with nested
+/// models or includes, links with more than one parent and joint types or geometries that URDF
+/// cannot describe are rejected.
+pub fn to_urdf(model: &SdfModel) -> Result<String, String> {
+    if !model.model.is_empty() {
+        return Err(format!(
+            "Model {} has nested models, they must be flattened first",
+            model.name
+        ));
+    }
+    if !model.include.is_empty() {
+        return Err(format!(
+            "Model {} has includes, they must be resolved first",
+            model.name
+        ));
+    }
+    let graph = FrameGraph::from_model(model)?;
+
+    // Joints by child link, joints whose parent is a frame are attached to its link
+    let mut parent_joints: HashMap<&str, &SdfJoint> = HashMap::new();
+    let mut parents: HashMap<&str, String> = HashMap::new();
+    for joint in model.joint.iter() {
+        if graph.kind(&joint.child) != Some(FrameKind::Link) {
+            return Err(format!(
+                "The child {} of joint {} is not a link",
+                joint.child, joint.name
+            ));
+        }
+        let parent = match joint.parent.as_str() {
+            "world" => "world".to_string(),
+            parent => graph.attached_link(parent)?,
+        };
+        if let Some(other) = parent_joints.insert(&joint.child, joint) {
+            return Err(format!(
+                "Link {} is the child of joints {} and {}, closed kinematic loops cannot be \
+                 represented in URDF",
+                joint.child, other.name, joint.name
+            ));
+        }
+        parents.insert(&joint.child, parent);
+    }
+    let mut roots: Vec<&str> = model
+        .link
+        .iter()
+        .map(|link| link.name.as_str())
+        .filter(|link| !parents.contains_key(link))
+        .collect();
+    if parents.values().any(|parent| parent == "world") {
+        roots.insert(0, "world");
+    }
+    // Every link must lead to the root, otherwise the joints form a loop
+    for link in model.link.iter() {
+        let mut current = link.name.as_str();
+        let mut visited = HashSet::new();
+        while let Some(parent) = parents.get(current) {
+            if !visited.insert(current) {
+                return Err(format!(
+                    "The joints of link {} form a closed kinematic loop",
+                    link.name
+                ));
+            }
+            current = parent;
+        }
+    }
+    if roots.len() != 1 {
+        return Err(format!(
+            "URDF requires a single root link, found {:?}",
+            roots
+        ));
+    }
+
+    // The URDF frame of a link is the frame of its parent joint
+    let urdf_frame = |link: &str| -> String {
+        match (link, parent_joints.get(link)) {
+            ("world", _) => "__model__".to_string(),
+            (_, Some(joint)) => joint.name.clone(),
+            (link, None) => link.to_string(),
+        }
+    };
+
+    let mut robot = Element::new("robot");
+    robot.set_attribute("name", &model.name);
+    if roots[0] == "world" {
+        let mut world = Element::new("link");
+        world.set_attribute("name", "world");
+        robot.push_element(world);
+    }
+    for link in model.link.iter() {
+        let frame_pose = graph.pose_in_root(&urdf_frame(&link.name))?;
+        let in_frame = |pose: Option<&SdfPose>| -> Result<Isometry3<f64>, String> {
+            Ok(frame_pose.inverse() * graph.resolve_pose(pose, "", &link.name)?)
+        };
+        let mut element = Element::new("link");
+        element.set_attribute("name", &link.name);
+
+        if let Some(inertial) = link.inertial.as_ref() {
+            let pose = inertial.pose.as_ref().map(SdfInertialPose::to_pose);
+            element.push_element(inertial_element(inertial, &in_frame(pose.as_ref())?));
+        }
+        for visual in link.visual.iter() {
+            let path = format!("{}/{}", link.name, visual.name);
+            let mut urdf = Element::new("visual");
+            urdf.set_attribute("name", &visual.name);
+            if let Some(origin) = origin_element(&in_frame(visual.pose.as_ref())?) {
+                urdf.push_element(origin);
+            }
+            urdf.push_element(geometry_element(&visual.geometry, &path)?);
+            if let Some(material) = visual.material.as_ref() {
+                let mut element = Element::new("material");
+                let name = material.script.as_ref().map(|script| script.name.clone());
+                element.set_attribute(
+                    "name",
+                    &name.unwrap_or_else(|| format!("{}_material", visual.name)),
+                );
+                if let Some(rgba) = material.diffuse.as_ref().or(material.ambient.as_ref()) {
+                    let mut color = Element::new("color");
+                    color.set_attribute("rgba", rgba);
+                    element.push_element(color);
+                }
+                urdf.push_element(element);
+            }
+            element.push_element(urdf);
+        }
+        for collision in link.collision.iter() {
+            let path = format!("{}/{}", link.name, collision.name);
+            let mut urdf = Element::new("collision");
+            urdf.set_attribute("name", &collision.name);
+            if let Some(origin) = origin_element(&in_frame(collision.pose.as_ref())?) {
+                urdf.push_element(origin);
+            }
+            urdf.push_element(geometry_element(&collision.geometry, &path)?);
+            element.push_element(urdf);
+        }
+        robot.push_element(element);
+    }
+
+    for joint in model.joint.iter() {
+        let parent = &parents[joint.child.as_str()];
+        let origin = graph.pose(&joint.name, &urdf_frame(parent))?;
+        robot.push_element(joint_element(model, joint, parent, &origin)?);
+    }
+
+    if !model.plugin.is_empty() {
+        let mut gazebo = Element::new("gazebo");
+        for plugin in model.plugin.iter() {
+            let plugin = Document::parse(&yaserde::ser::to_string(plugin)?)?;
+            gazebo.push_element(plugin.root().clone());
+        }
+        robot.push_element(gazebo);
+    }
+
+    Ok(write_root(&robot, &WriteOptions::default()))
+}
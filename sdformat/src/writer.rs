@@ -0,0 +1,209 @@
+//! Human readable serialization with stable formatting, suitable for files kept under version
+//! control.
+
+use yaserde::YaSerialize;
+
+use crate::cst::{escape, same_value, Document, Element};
+use crate::spec::{Spec, SpecRow};
+
+/// How the attributes of an element are ordered.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum AttributeOrder {
+    /// The order of the specification, attributes that are not part of it come last sorted by name.
+    #[default]
+    Spec,
+    /// All attributes sorted by name.
+    Alphabetical,
+}
+
+/// Options for [`to_string_pretty`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct WriteOptions {
+    /// The string used for each level of indentation.
+    pub indent: String,
+    /// Whether the output starts with an XML declaration.
+    pub xml_declaration: bool,
+    pub attribute_order: AttributeOrder,
+    /// Maximum number of decimals of floating point values. Trailing zeros are removed.
+    pub float_precision: Option<usize>,
+    /// Skip optional elements and attributes that are equal to their default value.
+    pub omit_defaults: bool,
+}
+
+impl Default for WriteOptions {
+    fn default() -> Self {
+        Self {
+            indent: "  ".to_string(),
+            xml_declaration: true,
+            attribute_order: AttributeOrder::Spec,
+            float_precision: None,
+            omit_defaults: false,
+        }
+    }
+}
+
+/// Serializes a value into an indented document.
+pub fn to_string_pretty<T: YaSerialize>(
+    value: &T,
+    options: &WriteOptions,
+) -> Result<String, String> {
+    let document = Document::parse(&yaserde::ser::to_string(value)?)?;
+    Ok(write_root(document.root(), options))
+}
+
+/// Writes an element as the root of a document with the formatting of the options, the original
+/// formatting is discarded.
+pub(crate) fn write_root(root: &Element, options: &WriteOptions) -> String {
+    let spec = Spec::latest();
+    let mut out = String::new();
+    if options.xml_declaration {
+        out += "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n";
+    }
+    let writer = Writer { spec, options };
+    writer.write_element(root, spec.root(root.name()), 0, &mut out);
+    out
+}
+
+/// Formats computed numbers separated by spaces, rounded to 12 decimals to hide floating point
+/// noise and without negative zeros.
+pub(crate) fn format_numbers(values: &[f64]) -> String {
+    values
+        .iter()
+        .map(|v| {
+            let formatted = format!("{:.12}", v);
+            match formatted.trim_end_matches('0').trim_end_matches('.') {
+                "-0" => "0".to_string(),
+                formatted => formatted.to_string(),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Types of values written as a list of floating point numbers.
+const FLOAT_TYPES: &[&str] = &[
+    "double",
+    "float",
+    "vector2d",
+    "vector3",
+    "pose",
+    "quaternion",
+    "color",
+];
+
+struct Writer<'a> {
+    spec: &'static Spec,
+    options: &'a WriteOptions,
+}
+
+impl Writer<'_> {
+    /// Returns the attributes that are written, formatted and in order.
+    fn attributes(&self, element: &Element, scope: Option<&str>) -> Vec<(String, String)> {
+        let mut attributes = vec![];
+        for name in element.attribute_names() {
+            let value = element.attribute(name).unwrap_or_default();
+            let row = scope.and_then(|scope| self.spec.attribute(scope, name));
+            if let Some(row) = row {
+                if self.options.omit_defaults
+                    && !row.required
+                    && row
+                        .default
+                        .is_some_and(|default| same_value(default, &value))
+                {
+                    continue;
+                }
+            }
+            let value = self.format_value(value, row.map(|row| row.rtype));
+            attributes.push((name.to_string(), value));
+        }
+        if self.options.attribute_order == AttributeOrder::Alphabetical {
+            attributes.sort();
+        }
+        attributes
+    }
+
+    fn format_value(&self, value: String, rtype: Option<&str>) -> String {
+        let Some(precision) = self.options.float_precision else {
+            return value;
+        };
+        if !rtype.is_some_and(|rtype| FLOAT_TYPES.contains(&rtype)) {
+            return value;
+        }
+        let tokens: Option<Vec<_>> = value
+            .split_whitespace()
+            .map(|token| {
+                let number = token.parse::<f64>().ok()?;
+                let formatted = format!("{:.*}", precision, number);
+                let formatted = match formatted.contains('.') {
+                    true => formatted.trim_end_matches('0').trim_end_matches('.'),
+                    false => formatted.as_str(),
+                };
+                Some(match formatted {
+                    "-0" => "0".to_string(),
+                    formatted => formatted.to_string(),
+                })
+            })
+            .collect();
+        tokens.map(|tokens| tokens.join(" ")).unwrap_or(value)
+    }
+
+    /// Returns true if the element can be left out because it matches its default value.
+    fn is_default(&self, element: &Element, scope: &str) -> bool {
+        let Some(row) = self.spec.element(scope, element.name()) else {
+            return false;
+        };
+        let Some(default) = row.default else {
+            return false;
+        };
+        !row.required
+            && element.elements().next().is_none()
+            && self.attributes(element, Some(row.child_scope)).is_empty()
+            && same_value(default, &element.text())
+    }
+
+    fn write_element(
+        &self,
+        element: &Element,
+        row: Option<&SpecRow>,
+        depth: usize,
+        out: &mut String,
+    ) {
+        let scope = row.map(|row| row.child_scope);
+        let indent = self.options.indent.repeat(depth);
+        *out += &indent;
+        *out += "<";
+        *out += element.name();
+        for (name, value) in self.attributes(element, scope) {
+            *out += &format!(" {}=\"{}\"", name, escape(&value, Some('"')));
+        }
+
+        let children: Vec<_> = element
+            .elements()
+            .filter(|child| {
+                !(self.options.omit_defaults
+                    && scope.is_some_and(|scope| self.is_default(child, scope)))
+            })
+            .collect();
+        let text = element.text();
+        let text = text.trim();
+        if children.is_empty() {
+            if text.is_empty() {
+                *out += "/>\n";
+            } else {
+                let text = self.format_value(text.to_string(), row.map(|row| row.rtype));
+                *out += &format!(">{}</{}>\n", escape(&text, None), element.name());
+            }
+            return;
+        }
+
+        *out += ">\n";
+        if !text.is_empty() {
+            *out += &format!("{}{}{}\n", indent, self.options.indent, escape(text, None));
+        }
+        for child in children {
+            let row = scope.and_then(|scope| self.spec.element(scope, child.name()));
+            self.write_element(child, row, depth + 1, out);
+        }
+        *out += &format!("{}</{}>\n", indent, element.name());
+    }
+}
@@ -0,0 +1,148 @@
+use nalgebra::{Isometry3, Vector3};
+use sdformat::bounds::{link_aabb, model_aabb, model_obb, world_aabb, Aabb, Shapes};
+use sdformat::mesh::TriangleMesh;
+use sdformat::{from_str, SdfGeometry, SdfModel, SdfWorld};
+
+fn no_meshes(shape: &sdformat::SdfMeshShape) -> Result<TriangleMesh, String> {
+    Err(format!("No mesh {}", shape.uri))
+}
+
+fn assert_close(actual: Vector3<f64>, expected: [f64; 3]) {
+    assert!(
+        (actual - Vector3::from(expected)).norm() < 1e-9,
+        "{} != {:?}",
+        actual,
+        expected
+    );
+}
+
+const CART: &str = r#"<model name="cart">
+  <pose>0 0 0 0 0 1.5707963267948966</pose>
+  <link name="body">
+    <pose>0 0 0.5 0 0 0</pose>
+    <collision name="chassis">
+      <geometry><box><size>2 1 0.5</size></box></geometry>
+    </collision>
+    <collision name="wheel">
+      <pose>1 0.5 -0.25 1.5707963267948966 0 0</pose>
+      <geometry><cylinder><radius>0.25</radius><length>0.1</length></cylinder></geometry>
+    </collision>
+    <visual name="mast">
+      <pose>0 0 0.25 0 0 0</pose>
+      <geometry><capsule><radius>0.1</radius><length>1</length></capsule></geometry>
+    </visual>
+    <visual name="nothing">
+      <pose>5 5 5 0 0 0</pose>
+      <geometry><empty/></geometry>
+    </visual>
+  </link>
+  <model name="flag">
+    <pose relative_to="body">-1 0 1 0 0 0</pose>
+    <link name="cloth">
+      <collision name="shape">
+        <geometry>
+          <polyline>
+            <point>0 0</point>
+            <point>0.5 0.2</point>
+            <point>0 0.4</point>
+            <height>0.01</height>
+          </polyline>
+        </geometry>
+      </collision>
+    </link>
+  </model>
+</model>"#;
+
+#[test]
+fn test_geometry_bounds() {
+    let model = from_str::<SdfModel>(CART).unwrap();
+    let mut load = no_meshes;
+    let wheel = model.link[0].collision[1].obb(&mut load).unwrap().aabb();
+    assert_close(wheel.min, [0.75, 0.45, -0.5]);
+    assert_close(wheel.max, [1.25, 0.55, 0.0]);
+
+    let plane = from_str::<SdfGeometry>(
+        "<geometry><plane><normal>0 0 1</normal><size>1 1</size></plane></geometry>",
+    )
+    .unwrap();
+    let aabb = plane.aabb(&mut load).unwrap();
+    assert_eq!(aabb.min.x, f64::NEG_INFINITY);
+    assert_eq!((aabb.min.z, aabb.max.z), (0.0, 0.0));
+    // Tilting the plane about x keeps it infinite along x only
+    let tilted = aabb.transformed(&Isometry3::rotation(Vector3::x() * 0.5));
+    assert_eq!(tilted.max.x, f64::INFINITY);
+    assert_eq!(tilted.max.z, f64::INFINITY);
+
+    let mesh = from_str::<SdfGeometry>(
+        "<geometry><mesh><uri>part.stl</uri><scale>2 2 2</scale></mesh></geometry>",
+    )
+    .unwrap();
+    assert!(mesh.aabb(&mut load).unwrap_err().contains("part.stl"));
+    let aabb = mesh
+        .aabb(&mut |_| {
+            Ok(TriangleMesh {
+                vertices: vec![Vector3::new(-1.0, 0.0, 0.0), Vector3::new(1.0, 2.0, 3.0)],
+                triangles: vec![],
+            })
+        })
+        .unwrap();
+    assert_close(aabb.min, [-2.0, 0.0, 0.0]);
+    assert_close(aabb.max, [2.0, 4.0, 6.0]);
+    assert!(SdfGeometry::Empty.aabb(&mut load).unwrap().is_empty());
+}
+
+#[test]
+fn test_model_bounds() {
+    let model = from_str::<SdfModel>(CART).unwrap();
+    let collisions = model_aabb(&model, Shapes::Collisions, no_meshes).unwrap();
+    assert_close(collisions.min, [-1.0, -0.5, 0.0]);
+    assert_close(collisions.max, [1.25, 0.55, 1.51]);
+    let visuals = model_aabb(&model, Shapes::Visuals, no_meshes).unwrap();
+    assert_close(visuals.min, [-0.1, -0.1, 0.15]);
+    assert_close(visuals.max, [0.1, 0.1, 1.35]);
+
+    let link = link_aabb(&model, "flag::cloth", Shapes::Collisions, no_meshes).unwrap();
+    assert_close(link.max, [0.5, 0.4, 0.01]);
+    assert!(link_aabb(&model, "flag::pole", Shapes::Collisions, no_meshes).is_err());
+
+    // The model is turned a quarter turn about z
+    let obb = model_obb(&model, Shapes::Collisions, no_meshes).unwrap();
+    assert_close(obb.half_extents, [1.125, 0.525, 0.755]);
+    let placed = obb.aabb();
+    assert_close(placed.min, [-0.55, -1.0, 0.0]);
+    assert_close(placed.max, [0.5, 1.25, 1.51]);
+
+    let world = SdfWorld {
+        model: vec![model.clone(), {
+            let mut other = model;
+            other.name = "other".to_string();
+            other.pose.as_mut().unwrap().data = "10 0 0 0 0 0".to_string();
+            other
+        }],
+        ..Default::default()
+    };
+    let bounds = world_aabb(&world, Shapes::Collisions, no_meshes).unwrap();
+    assert_close(bounds.min, [-0.55, -1.0, 0.0]);
+    assert_close(bounds.max, [11.25, 1.25, 1.51]);
+    assert_eq!(Aabb::default().merged(&bounds), bounds);
+}
+
+#[test]
+fn test_rotated_link_aabb() {
+    // The link is turned by 45° about z, its box is aligned with it
+    let model = from_str::<SdfModel>(
+        r#"<model name="turned">
+  <link name="body">
+    <pose>1 0 0 0 0 0.7853981633974483</pose>
+    <collision name="box">
+      <pose>0 0 0.5 0 0 0</pose>
+      <geometry><box><size>1 1 1</size></box></geometry>
+    </collision>
+  </link>
+</model>"#,
+    )
+    .unwrap();
+    let link = link_aabb(&model, "body", Shapes::Collisions, no_meshes).unwrap();
+    assert_close(link.min, [-0.5, -0.5, 0.0]);
+    assert_close(link.max, [0.5, 0.5, 1.0]);
+}
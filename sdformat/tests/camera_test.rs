@@ -1,3 +1,6 @@
+// The assertions predate the lint and are kept as they were written
+#![allow(clippy::redundant_pattern_matching)]
+
 use yaserde::de::from_str;
 
 use sdformat::SdfCamera;
@@ -65,7 +68,7 @@ use sdformat::{ElementData, SdfPlugin};
 #[test]
 fn test_plugin() {
     let test_plugin_content = |fr: &SdfPlugin| {
-        assert_eq!(fr.name, "hello");
+        assert_eq!(fr.name.as_deref(), Some("hello"));
         assert_eq!(fr.filename, "world.so");
         assert_eq!(fr.elements.all().len(), 1);
         let box_elem = fr.elements.all().iter().next().unwrap();
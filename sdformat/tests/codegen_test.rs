@@ -0,0 +1,121 @@
+use yaserde::de::from_str;
+
+use sdformat::{
+    SdfAtmosphere, SdfBoxShape, SdfLink, SdfMaterialPbr, SdfModelState, SdfPlugin, SdfPopulation,
+    SdfPopulationRegion, SdfSensor, SdfSensorKind, SdfState,
+};
+
+#[test]
+fn test_nested_model_state() {
+    let test_syntax =
+        "<model name=\"top\"><model name=\"nested\"><model name=\"inner\" /></model></model>";
+    let fr = from_str::<SdfModelState>(test_syntax).unwrap();
+    assert_eq!(fr.model.len(), 1);
+    assert_eq!(fr.model[0].name, "nested");
+    assert_eq!(fr.model[0].model[0].name, "inner");
+}
+
+#[test]
+fn test_referenced_model_state() {
+    let test_syntax = "<state world_name=\"default\"><iterations>0</iterations><model name=\"top\"><model name=\"nested\"><joint name=\"hinge\"><angle axis=\"0\">0.5</angle></joint></model></model></state>";
+    let fr = from_str::<SdfState>(test_syntax).unwrap();
+    // The nested model refers to the type of the model state, not of the state holding it
+    let nested = &fr.model[0].model[0];
+    assert_eq!(nested.name, "nested");
+    assert_eq!(nested.joint[0].name, "hinge");
+
+    let serialized = yaserde::ser::to_string(&fr).unwrap();
+    assert_eq!(from_str::<SdfState>(&serialized).unwrap(), fr);
+}
+
+#[test]
+fn test_nested_plugin() {
+    let test_syntax = "<sensor name=\"cam\" type=\"camera\"><plugin filename=\"cam.so\"><fps>30</fps></plugin><update_rate>10</update_rate></sensor>";
+    let fr = from_str::<SdfSensor>(test_syntax).unwrap();
+    assert_eq!(fr.update_rate, Some(10.0));
+    assert_eq!(fr.plugin.len(), 1);
+    assert_eq!(fr.plugin[0].filename, "cam.so");
+    assert_eq!(fr.plugin[0].name, None);
+    let fps = fr.plugin[0].elements.get("fps").unwrap();
+    assert_eq!(fps.data.clone().try_into(), Ok(30));
+
+    let serialized = yaserde::ser::to_string(&fr).unwrap();
+    let fr_again = from_str::<SdfSensor>(&serialized).unwrap();
+    assert_eq!(fr, fr_again);
+}
+
+#[test]
+fn test_sensor_kind() {
+    let test_syntax = "<sensor name=\"cam\" type=\"camera\"><camera><horizontal_fov>1.047</horizontal_fov><image><width>320</width><height>240</height></image><clip><near>0.1</near><far>100</far></clip></camera></sensor>";
+    let fr = from_str::<SdfSensor>(test_syntax).unwrap();
+    match &fr.kind {
+        Some(SdfSensorKind::Camera(camera)) => assert_eq!(camera.horizontal_fov, 1.047),
+        kind => panic!("Expected a camera, found {:?}", kind),
+    }
+
+    let serialized = yaserde::ser::to_string(&fr).unwrap();
+    let fr_again = from_str::<SdfSensor>(&serialized).unwrap();
+    assert_eq!(fr, fr_again);
+
+    // Only one of the alternatives can be set
+    let test_syntax = "<sensor name=\"cam\" type=\"camera\"><camera></camera><imu></imu></sensor>";
+    assert!(from_str::<SdfSensor>(test_syntax).is_err());
+
+    // The alternative must match the type of the sensor
+    let test_syntax = "<sensor name=\"cam\" type=\"camera\"><imu></imu></sensor>";
+    assert!(from_str::<SdfSensor>(test_syntax).is_err());
+    let test_syntax = "<sensor name=\"depth\" type=\"depth_camera\"><camera><horizontal_fov>1</horizontal_fov><image><width>1</width><height>1</height></image><clip><near>0.1</near><far>1</far></clip></camera></sensor>";
+    assert!(from_str::<SdfSensor>(test_syntax).is_ok());
+    let test_syntax = "<sensor name=\"fix\" type=\"navsat\"><gps></gps></sensor>";
+    let fr = from_str::<SdfSensor>(test_syntax).unwrap();
+    assert_eq!(fr.kind.map(|kind| kind.name()), Some("gps"));
+    // Custom sensors may hold any of the alternatives
+    let test_syntax = "<sensor name=\"mine\" type=\"custom\"><imu></imu></sensor>";
+    assert!(from_str::<SdfSensor>(test_syntax).is_ok());
+}
+
+#[test]
+fn test_choice_enums() {
+    let test_syntax = "<pbr><metal><metalness>0.5</metalness></metal></pbr>";
+    let fr = from_str::<SdfMaterialPbr>(test_syntax).unwrap();
+    match &fr {
+        SdfMaterialPbr::Metal(metal) => assert_eq!(metal.metalness.as_deref(), Some("0.5")),
+        pbr => panic!("Expected a metal workflow, found {:?}", pbr),
+    }
+
+    let test_syntax = "<population name=\"trees\"><model_count>5</model_count><distribution><type>random</type></distribution><model name=\"tree\" /><cylinder><radius>2</radius><length>1</length></cylinder></population>";
+    let fr = from_str::<SdfPopulation>(test_syntax).unwrap();
+    match &fr.region {
+        Some(SdfPopulationRegion::Cylinder(cylinder)) => assert_eq!(cylinder.radius, 2.0),
+        region => panic!("Expected a cylinder, found {:?}", region),
+    }
+    let serialized = yaserde::ser::to_string(&fr).unwrap();
+    assert_eq!(from_str::<SdfPopulation>(&serialized).unwrap(), fr);
+}
+
+#[test]
+fn test_invalid_values() {
+    // Values that can't be parsed are left out of optional fields
+    let test_syntax = "<sensor name=\"cam\" type=\"camera\"><update_rate>fast</update_rate><always_on>1</always_on></sensor>";
+    let fr = from_str::<SdfSensor>(test_syntax).unwrap();
+    assert_eq!(fr.update_rate, None);
+    assert_eq!(fr.always_on, Some(true));
+
+    // Required fields must be present and valid
+    assert!(from_str::<SdfLink>("<link />").is_err());
+    assert!(from_str::<SdfBoxShape>("<box><size>1 1</size></box>").is_err());
+}
+
+#[test]
+fn test_attribute_defaults() {
+    // Required attributes with a default in the spec hold it when they are missing
+    let fr = from_str::<SdfAtmosphere>("<atmosphere />").unwrap();
+    assert_eq!(fr.r#type, "adiabatic");
+    let serialized = yaserde::ser::to_string(&fr).unwrap();
+    assert!(serialized.contains("<atmosphere type=\"adiabatic\""));
+
+    // Optional attributes are left out, required ones without a default must be present
+    let fr = from_str::<SdfPlugin>("<plugin filename=\"a.so\" />").unwrap();
+    assert_eq!(fr.name, None);
+    assert!(from_str::<SdfPlugin>("<plugin name=\"a\" />").is_err());
+}
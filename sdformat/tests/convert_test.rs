@@ -0,0 +1,152 @@
+use sdformat::cst::Document;
+use sdformat::{from_str, to_version, to_version_with_options, ConvertOptions, SdfRoot};
+
+const ROBOT: &str = r#"<sdf version="1.10">
+  <model name="robot" canonical_link="base">
+    <include merge="true">
+      <uri>model://gripper</uri>
+    </include>
+    <link name="base">
+      <pose>0 0 1 0 0 0</pose>
+    </link>
+    <frame name="mount" attached_to="base">
+      <pose>0 1 0 0 0 0</pose>
+    </frame>
+    <link name="arm">
+      <pose relative_to="mount" rotation_format="euler_rpy" degrees="true">0 0 1 0 0 90</pose>
+    </link>
+    <joint name="shoulder" type="revolute">
+      <parent>mount</parent>
+      <child>arm</child>
+      <axis>
+        <xyz>0 0 1</xyz>
+      </axis>
+    </joint>
+    <model name="hand">
+      <link name="palm"/>
+    </model>
+    <joint name="wrist" type="fixed">
+      <parent>arm</parent>
+      <child>hand::palm</child>
+    </joint>
+  </model>
+</sdf>"#;
+
+#[test]
+fn test_down_conversion_report() {
+    let root = from_str::<SdfRoot>(ROBOT).unwrap();
+    let converted = to_version(&root, "1.6").unwrap();
+    let document = Document::parse(&converted.output).unwrap();
+    let sdf = document.root();
+    assert_eq!(sdf.attribute("version").as_deref(), Some("1.6"));
+
+    let model = sdf.child("model").unwrap();
+    assert!(model.attribute("canonical_link").is_none());
+    assert!(model.child("frame").is_none());
+    assert!(model.child("model").is_some());
+    assert!(model.child("include").unwrap().attribute("merge").is_none());
+
+    // Degrees are converted, the frame the pose is relative to is lost
+    let arm = model
+        .elements()
+        .find(|link| link.attribute("name").as_deref() == Some("arm"));
+    let pose = arm.unwrap().child("pose").unwrap();
+    assert!(pose.attribute("relative_to").is_none());
+    assert!(pose.attribute("degrees").is_none());
+    let data: Vec<f64> = pose
+        .text()
+        .split_whitespace()
+        .map(|v| v.parse().unwrap())
+        .collect();
+    assert!((data[5] - std::f64::consts::FRAC_PI_2).abs() < 1e-9);
+
+    // Required in 1.6 but not in 1.10
+    let joint = model.child("joint").unwrap();
+    let axis = joint.child("axis").unwrap();
+    assert_eq!(
+        axis.child("use_parent_model_frame").unwrap().text(),
+        "false"
+    );
+
+    let unresolved: Vec<_> = converted
+        .issues
+        .iter()
+        .filter(|issue| !issue.resolved)
+        .map(|issue| issue.path.as_str())
+        .collect();
+    assert_eq!(
+        unresolved,
+        vec![
+            "/sdf/model[robot]/link[arm]/pose",
+            "/sdf/model[robot]/joint[shoulder]",
+            "/sdf/model[robot]/joint[wrist]",
+            "/sdf/model[robot]",
+            "/sdf/model[robot]",
+            "/sdf/model[robot]/include",
+        ]
+    );
+    assert!(converted
+        .issues
+        .iter()
+        .any(|issue| issue.resolved && issue.message.contains("Euler")));
+
+    // The output can be read back with the types of this crate
+    from_str::<SdfRoot>(&converted.output).unwrap();
+}
+
+#[test]
+fn test_down_conversion_flatten() {
+    let root = from_str::<SdfRoot>(ROBOT).unwrap();
+    let options = ConvertOptions {
+        flatten: true,
+        ..Default::default()
+    };
+    let converted = to_version_with_options(&root, "1.6", &options).unwrap();
+    let converted = from_str::<SdfRoot>(&converted.output).unwrap();
+    let model = converted.model.unwrap();
+    assert!(model.frame.is_empty());
+
+    // The arm is placed relative to the model, through the mount frame on the base
+    let arm = model.link[1].pose.as_ref().unwrap();
+    assert!(arm.relative_to.is_none());
+    let arm = arm.get_pose().unwrap();
+    assert!((arm.translation - nalgebra::Vector3::new(0.0, 1.0, 2.0)).norm() < 1e-9);
+
+    // The joint is attached to the link of the frame and keeps its pose in the arm frame
+    let joint = &model.joint[0];
+    assert_eq!(joint.parent, "base");
+    assert!(joint.pose.is_none());
+}
+
+#[test]
+fn test_conversion_versions() {
+    let root = from_str::<SdfRoot>(ROBOT).unwrap();
+    // 1.9 supports all the constructs of the document
+    let converted = to_version(&root, "1.9").unwrap();
+    assert!(converted.issues.is_empty());
+    assert!(to_version(&root, "1.7").is_err());
+}
+
+#[test]
+fn test_unflatten_conversion() {
+    let legacy = r#"<sdf version="1.7">
+  <world name="default">
+    <gravity>0 0 -9.8</gravity>
+    <magnetic_field>0 0 0</magnetic_field>
+    <atmosphere type="adiabatic"/>
+    <scene><ambient>0.4 0.4 0.4 1</ambient><background>0.7 0.7 0.7 1</background><shadows>true</shadows></scene>
+    <model name="robot">
+      <link name="base"/>
+      <link name="camera::body"/>
+    </model>
+  </world>
+</sdf>"#;
+    let root = from_str::<SdfRoot>(legacy).unwrap();
+    let converted = to_version(&root, "1.10").unwrap();
+    assert!(converted.issues[0].resolved);
+    let converted = from_str::<SdfRoot>(&converted.output).unwrap();
+    let model = &converted.world[0].model[0];
+    assert_eq!(model.link.len(), 1);
+    assert_eq!(model.model[0].name, "camera");
+    assert_eq!(model.model[0].link[0].name, "body");
+}
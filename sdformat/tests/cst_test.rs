@@ -0,0 +1,146 @@
+use sdformat::cst::{Document, Element};
+use sdformat::{SdfLink, SdfModel, SdfPlugin};
+
+const WORLD: &str = r#"<?xml version="1.0" ?>
+<!-- Reviewed world -->
+<model name='robot'   canonical_link="base">
+  <static>false</static>
+  <link name="base">
+    <pose>0 0 0.5 0 0 0</pose>
+    <!-- The wheel goes here -->
+  </link>
+  <link name="arm" >
+    <pose relative_to="base">1.0 0 0 0 0 0</pose>
+  </link>
+  <plugin name="hello" filename="world.so"><box name="boxy"><size>42</size><!-- A comment --></box></plugin>
+</model>
+"#;
+
+#[test]
+fn test_lossless_round_trip() {
+    let document = Document::parse(WORLD).unwrap();
+    assert_eq!(document.to_string(), WORLD);
+
+    let root = document.root();
+    assert_eq!(root.name(), "model");
+    assert_eq!(
+        root.attribute_names().collect::<Vec<_>>(),
+        vec!["name", "canonical_link"]
+    );
+    assert_eq!(root.attribute("name").as_deref(), Some("robot"));
+    assert_eq!(root.elements().count(), 4);
+    assert_eq!(root.child("static").unwrap().text(), "false");
+
+    let plugin: SdfPlugin = Document::parse(
+        "<plugin name=\"hello\" filename=\"world.so\"><!-- A comment --><size>42</size></plugin>",
+    )
+    .unwrap()
+    .to_typed()
+    .unwrap();
+    assert_eq!(plugin.elements.all().len(), 1);
+}
+
+#[test]
+fn test_typed_update() {
+    let mut document = Document::parse(WORLD).unwrap();
+    let mut model: SdfModel = document.to_typed().unwrap();
+    // Only the pose of the arm changes, equivalent values such as `1.0` are left as they are
+    model.link[0].pose.as_mut().unwrap().data = "0 0 0.75 0 0 0".to_string();
+    document.update(&model).unwrap();
+    assert_eq!(
+        document.to_string(),
+        WORLD.replace("0 0 0.5 0 0 0", "0 0 0.75 0 0 0")
+    );
+
+    // Removing and adding elements keeps the formatting of their siblings
+    model.link.remove(1);
+    model.link.push(SdfLink {
+        name: "wheel".to_string(),
+        ..Default::default()
+    });
+    document.update(&model).unwrap();
+    let expected = WORLD
+        .replace("0 0 0.5 0 0 0", "0 0 0.75 0 0 0")
+        .replace(
+            "  <link name=\"arm\" >\n    <pose relative_to=\"base\">1.0 0 0 0 0 0</pose>\n  </link>\n",
+            "",
+        )
+        .replace("  </link>\n", "  </link>\n  <link name=\"wheel\" />\n");
+    assert_eq!(document.to_string(), expected);
+
+    assert!(document.update(&SdfLink::default()).is_err());
+}
+
+#[test]
+fn test_surgical_edits() {
+    let mut document = Document::parse(WORLD).unwrap();
+    let root = document.root_mut();
+    root.set_attribute("name", "robot's");
+    root.remove_attribute("canonical_link");
+    root.child_mut("static").unwrap().set_text("true");
+    let mut frame = Element::new("frame");
+    frame.set_attribute("name", "tool");
+    root.push_element(frame);
+    assert!(root.remove_element("plugin").is_some());
+
+    let expected = WORLD
+        .replace(
+            "<model name='robot'   canonical_link=\"base\">",
+            "<model name='robot&apos;s'>",
+        )
+        .replace("<static>false</static>", "<static>true</static>")
+        .replace(
+            "  <plugin name=\"hello\" filename=\"world.so\"><box name=\"boxy\"><size>42</size><!-- A comment --></box></plugin>\n",
+            "  <frame name=\"tool\"/>\n",
+        );
+    assert_eq!(document.to_string(), expected);
+}
+
+#[test]
+fn test_parse_errors() {
+    assert!(Document::parse("<model><link></model>").is_err());
+    assert!(Document::parse("<model name=robot />").is_err());
+    assert!(Document::parse("<!-- only a comment -->").is_err());
+    assert!(Document::parse("<model/><model/>").is_err());
+}
+
+const VENDOR: &str = r#"<model name="robot" xmlns:mysim="http://example.org/mysim">
+  <link name="base" mysim:color="red">
+    <pose>0 0 0.5 0 0 0</pose>
+    <collision name="body">
+      <geometry mysim:lod="2">
+        <box><size mysim:unit="m">1 1 1</size></box>
+        <mysim:proxy radius="0.5"/>
+      </geometry>
+    </collision>
+  </link>
+</model>"#;
+
+#[test]
+fn test_typed_update_keeps_unknown_content() {
+    let mut document = Document::parse(VENDOR).unwrap();
+    let mut model: SdfModel = document.to_typed().unwrap();
+    model.link[0].pose.as_mut().unwrap().data = "0 0 1 0 0 0".to_string();
+    document.update(&model).unwrap();
+    assert_eq!(
+        document.to_string(),
+        VENDOR.replace("0 0 0.5 0 0 0", "0 0 1 0 0 0")
+    );
+
+    // Content that is not part of the spec is kept even if the typed struct drops it
+    model.link[0].extra_attributes.clear();
+    model.link[0].collision[0].child_extras = Default::default();
+    document.update(&model).unwrap();
+    assert_eq!(
+        document.to_string(),
+        VENDOR.replace("0 0 0.5 0 0 0", "0 0 1 0 0 0")
+    );
+
+    // While elements of the spec are removed
+    model.link[0].pose = None;
+    document.update(&model).unwrap();
+    assert_eq!(
+        document.to_string(),
+        VENDOR.replace("\n    <pose>0 0 0.5 0 0 0</pose>", "")
+    );
+}
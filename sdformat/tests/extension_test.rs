@@ -0,0 +1,67 @@
+use yaserde::de::from_str;
+
+use sdformat::{ElementData, SdfCollision, SdfGeometry, SdfModel};
+
+#[test]
+fn test_custom_elements() {
+    let test_syntax = "<model name=\"robot\" xmlns:mysim=\"http://example.org/mysim\"><mysim:tuning gain=\"2\"><mysim:k>0.5</mysim:k></mysim:tuning><link name=\"base\" mysim:color=\"red\"><other:thing xmlns:other=\"http://example.org/other\">1</other:thing></link></model>";
+    let fr = from_str::<SdfModel>(test_syntax).unwrap();
+    assert_eq!(
+        fr.extra_attributes.get("xmlns:mysim").map(String::as_str),
+        Some("http://example.org/mysim")
+    );
+    let tuning = fr.extra.get("mysim:tuning").unwrap();
+    assert_eq!(tuning.attributes.get("gain").map(String::as_str), Some("2"));
+    match &tuning.data {
+        ElementData::Nested(data) => {
+            assert_eq!(
+                data.get("mysim:k").unwrap().data.clone().try_into(),
+                Ok(0.5)
+            )
+        }
+        _ => panic!("Expected nested element"),
+    }
+    let link = &fr.link[0];
+    assert_eq!(
+        link.extra_attributes.get("mysim:color").map(String::as_str),
+        Some("red")
+    );
+    // Namespaces declared by the parent are not repeated
+    assert!(!link.extra_attributes.contains_key("xmlns:mysim"));
+    let thing = link.extra.get("other:thing").unwrap();
+    assert_eq!(
+        thing.attributes.get("xmlns:other").map(String::as_str),
+        Some("http://example.org/other")
+    );
+
+    let serialized = yaserde::ser::to_string(&fr).unwrap();
+    assert!(serialized.contains("xmlns:mysim=\"http://example.org/mysim\""));
+    assert!(serialized.contains("mysim:color=\"red\""));
+    assert_eq!(from_str::<SdfModel>(&serialized).unwrap(), fr);
+}
+
+#[test]
+fn test_custom_content_of_values_and_choices() {
+    let test_syntax = "<collision name=\"body\" xmlns:mysim=\"http://example.org/mysim\"><geometry mysim:lod=\"2\"><box><size mysim:unit=\"m\">1 2 3</size></box><mysim:proxy radius=\"0.5\">sphere</mysim:proxy></geometry></collision>";
+    let fr = from_str::<SdfCollision>(test_syntax).unwrap();
+    assert!(matches!(fr.geometry, SdfGeometry::Box(_)));
+    let geometry = fr.child_extras.get("geometry").unwrap();
+    assert_eq!(
+        geometry.attributes.get("mysim:lod").map(String::as_str),
+        Some("2")
+    );
+    let SdfGeometry::Box(ref b) = fr.geometry else {
+        unreachable!()
+    };
+    let size = b.child_extras.get("size").unwrap();
+    assert_eq!(
+        size.attributes.get("mysim:unit").map(String::as_str),
+        Some("m")
+    );
+
+    let serialized = yaserde::ser::to_string(&fr).unwrap();
+    assert!(serialized.contains("<geometry mysim:lod=\"2\">"));
+    assert!(serialized.contains("<size mysim:unit=\"m\">1 2 3</size>"));
+    assert!(serialized.contains("<mysim:proxy radius=\"0.5\">sphere</mysim:proxy></geometry>"));
+    assert_eq!(from_str::<SdfCollision>(&serialized).unwrap(), fr);
+}
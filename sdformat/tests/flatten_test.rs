@@ -0,0 +1,322 @@
+use sdformat::flatten::{flatten, unflatten};
+use sdformat::frames::FrameGraph;
+use sdformat::{from_str, SdfModel};
+
+const ROBOT: &str = r#"<model name="robot">
+  <link name="base">
+    <pose>0 0 1 0 0 0</pose>
+  </link>
+  <model name="arm">
+    <pose relative_to="base">1 0 0 0 0 1.5707963267948966</pose>
+    <link name="upper">
+      <pose>0 0 0.5 0 0 0</pose>
+    </link>
+    <frame name="elbow" attached_to="upper">
+      <pose>0 0 0.5 0 0 0</pose>
+    </frame>
+    <link name="lower">
+      <pose relative_to="elbow">0 0 0.25 0 0 0</pose>
+      <visual name="shape">
+        <pose relative_to="elbow">0 0 0 0 0 0</pose>
+        <geometry><sphere><radius>0.1</radius></sphere></geometry>
+      </visual>
+    </link>
+    <joint name="elbow_joint" type="revolute">
+      <pose relative_to="elbow"/>
+      <parent>upper</parent>
+      <child>lower</child>
+      <axis><xyz>0 1 0</xyz></axis>
+    </joint>
+    <model name="hand">
+      <pose relative_to="lower">0 0 0.25 0 0 0</pose>
+      <link name="palm"/>
+      <frame name="tip">
+        <pose>0 0 0.1 0 0 0</pose>
+      </frame>
+    </model>
+    <joint name="wrist" type="fixed">
+      <parent>lower</parent>
+      <child>hand::palm</child>
+    </joint>
+  </model>
+  <joint name="shoulder" type="revolute">
+    <parent>base</parent>
+    <child>arm::upper</child>
+    <axis><xyz>0 0 1</xyz></axis>
+  </joint>
+  <frame name="mount" attached_to="arm::__model__"/>
+</model>"#;
+
+#[test]
+fn test_flatten() {
+    let model = from_str::<SdfModel>(ROBOT).unwrap();
+    let flat = flatten(&model).unwrap();
+    assert!(flat.model.is_empty());
+
+    let links: Vec<_> = flat.link.iter().map(|link| link.name.as_str()).collect();
+    assert_eq!(
+        links,
+        vec!["base", "arm::upper", "arm::lower", "arm::hand::palm"]
+    );
+    let joints: Vec<_> = flat
+        .joint
+        .iter()
+        .map(|joint| {
+            (
+                joint.name.as_str(),
+                joint.parent.as_str(),
+                joint.child.as_str(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        joints,
+        vec![
+            ("shoulder", "base", "arm::upper"),
+            ("arm::elbow_joint", "arm::upper", "arm::lower"),
+            ("arm::wrist", "arm::lower", "arm::hand::palm"),
+        ]
+    );
+    let frames: Vec<_> = flat
+        .frame
+        .iter()
+        .map(|frame| (frame.name.as_str(), frame.attached_to.as_deref()))
+        .collect();
+    assert_eq!(
+        frames,
+        vec![
+            ("mount", Some("arm")),
+            ("arm", Some("arm::upper")),
+            ("arm::elbow", Some("arm::upper")),
+            ("arm::hand", Some("arm::hand::palm")),
+            ("arm::hand::tip", Some("arm::hand")),
+        ]
+    );
+    assert_eq!(
+        flat.link[2].visual[0]
+            .pose
+            .as_ref()
+            .unwrap()
+            .relative_to
+            .as_deref(),
+        Some("arm::elbow")
+    );
+
+    assert!(flat.link[1..]
+        .iter()
+        .all(|link| link.pose.as_ref().unwrap().relative_to.is_none()));
+
+    // Every frame keeps its pose relative to the model
+    let original = FrameGraph::from_model(&model).unwrap();
+    let graph = FrameGraph::from_model(&flat).unwrap();
+    for frame in original.frames() {
+        let expected = original.pose(frame, "__model__").unwrap();
+        let pose = graph.pose(frame, "__model__").unwrap();
+        assert!(
+            (pose.translation.vector - expected.translation.vector).norm() < 1e-9,
+            "{}",
+            frame
+        );
+        assert!(
+            pose.rotation.angle_to(&expected.rotation) < 1e-9,
+            "{}",
+            frame
+        );
+    }
+}
+
+#[test]
+fn test_flatten_canonical_link() {
+    let mut model = from_str::<SdfModel>(
+        r#"<model name="outer">
+  <model name="inner" canonical_link="b">
+    <link name="a"/>
+    <link name="b"/>
+  </model>
+</model>"#,
+    )
+    .unwrap();
+    // The first link of the flattened model is not the canonical link of the original
+    let flat = flatten(&model).unwrap();
+    assert_eq!(flat.canonical_link.as_deref(), Some("inner::b"));
+
+    model.model[0].include.push(Default::default());
+    assert!(flatten(&model).unwrap_err().contains("includes"));
+}
+
+#[test]
+fn test_flatten_model_settings() {
+    let mut model = from_str::<SdfModel>(
+        r#"<model name="outer">
+  <model name="inner">
+    <static>true</static>
+    <self_collide>true</self_collide>
+    <link name="a"/>
+    <link name="b"><self_collide>false</self_collide></link>
+    <model name="tool">
+      <enable_wind>true</enable_wind>
+      <link name="c"/>
+    </model>
+  </model>
+</model>"#,
+    )
+    .unwrap();
+    // A static model cannot be flattened into a model that moves
+    assert!(flatten(&model).unwrap_err().contains("static"));
+
+    // The settings of nested models move to their links
+    model.r#static = Some(true);
+    let flat = flatten(&model).unwrap();
+    let settings: Vec<_> = flat
+        .link
+        .iter()
+        .map(|link| (link.name.as_str(), link.self_collide, link.enable_wind))
+        .collect();
+    assert_eq!(
+        settings,
+        vec![
+            ("inner::a", Some(true), None),
+            ("inner::b", Some(false), None),
+            ("inner::tool::c", Some(true), Some(true)),
+        ]
+    );
+}
+
+#[test]
+fn test_unflatten() {
+    let model = from_str::<SdfModel>(ROBOT).unwrap();
+    let unflattened = unflatten(&flatten(&model).unwrap()).unwrap();
+    assert!(unflattened.frame.iter().all(|frame| frame.name == "mount"));
+    let arm = &unflattened.model[0];
+    assert_eq!(arm.name, "arm");
+    assert_eq!(arm.joint[1].child, "hand::palm");
+    assert_eq!(arm.model[0].name, "hand");
+    assert_eq!(arm.model[0].frame[0].name, "tip");
+
+    let original = FrameGraph::from_model(&model).unwrap();
+    let graph = FrameGraph::from_model(&unflattened).unwrap();
+    for frame in original.frames() {
+        let expected = original.pose(frame, "__model__").unwrap();
+        let pose = graph.pose(frame, "__model__").unwrap();
+        assert!(
+            (pose.translation.vector - expected.translation.vector).norm() < 1e-9,
+            "{}",
+            frame
+        );
+    }
+
+    // Joints between scopes stay in the outer model
+    let legacy = from_str::<SdfModel>(
+        r#"<model name="legacy">
+  <link name="base"/>
+  <link name="gripper::palm"><pose>0 0 1 0 0 0</pose></link>
+  <link name="gripper::finger"/>
+  <joint name="gripper::slide" type="prismatic">
+    <parent>gripper::palm</parent>
+    <child>gripper::finger</child>
+  </joint>
+  <joint name="gripper::mount" type="fixed">
+    <parent>base</parent>
+    <child>gripper::palm</child>
+  </joint>
+</model>"#,
+    )
+    .unwrap();
+    let unflattened = unflatten(&legacy).unwrap();
+    assert_eq!(unflattened.link.len(), 1);
+    assert_eq!(unflattened.joint[0].name, "gripper::mount");
+    let gripper = &unflattened.model[0];
+    assert!(gripper.pose.is_none());
+    assert_eq!(gripper.joint[0].parent, "palm");
+    assert_eq!(gripper.link[0].pose.as_ref().unwrap().data, "0 0 1 0 0 0");
+}
+
+#[test]
+fn test_unflatten_model_frame() {
+    // The frame of the gripper is at its palm, the tip is attached to the outer model
+    let flat = from_str::<SdfModel>(
+        r#"<model name="flat">
+  <link name="gripper::palm"><pose>1 0 0 0 0 1.5707963267948966</pose></link>
+  <link name="gripper::finger"><pose>1 0.5 0 0 0 0</pose></link>
+  <frame name="gripper" attached_to="gripper::palm"/>
+  <frame name="gripper::tip"><pose>1 1 0 0 0 0</pose></frame>
+  <joint name="gripper::slide" type="prismatic">
+    <parent>gripper::palm</parent>
+    <child>gripper::finger</child>
+    <axis><xyz expressed_in="gripper::palm">1 0 0</xyz></axis>
+  </joint>
+</model>"#,
+    )
+    .unwrap();
+    let unflattened = unflatten(&flat).unwrap();
+    let gripper = &unflattened.model[0];
+    assert_eq!(gripper.canonical_link.as_deref(), Some("palm"));
+    let axis = gripper.joint[0].axis.as_ref().unwrap();
+    assert_eq!(axis.xyz.expressed_in.as_deref(), Some("palm"));
+
+    let original = FrameGraph::from_model(&flat).unwrap();
+    let graph = FrameGraph::from_model(&unflattened).unwrap();
+    for frame in original.frames() {
+        let expected = original.pose(frame, "__model__").unwrap();
+        let pose = graph.pose(frame, "__model__").unwrap();
+        assert!(
+            (pose.translation.vector - expected.translation.vector).norm() < 1e-9,
+            "{}",
+            frame
+        );
+        assert!(
+            pose.rotation.angle_to(&expected.rotation) < 1e-9,
+            "{}",
+            frame
+        );
+    }
+    let expected = flat.joint[0].axis_in(&flat, "__model__").unwrap();
+    let axis = gripper.joint[0].axis_in(gripper, "__model__").unwrap();
+    let placement = graph.pose("gripper", "__model__").unwrap();
+    assert!((placement.rotation * axis - expected).norm() < 1e-9);
+}
+
+#[test]
+fn test_flatten_round_trip() {
+    let model = from_str::<SdfModel>(
+        r#"<model name="robot">
+  <link name="base"/>
+  <model name="hand">
+    <pose>0 0 1 0 0 1.5707963267948966</pose>
+    <link name="palm"><pose>0 0 0 0 0 1.5707963267948966</pose></link>
+    <link name="finger"><pose>0.1 0 0 0 0 0</pose></link>
+    <joint name="slide" type="prismatic">
+      <parent>palm</parent>
+      <child>finger</child>
+      <axis><xyz expressed_in="__model__">1 0 0</xyz></axis>
+    </joint>
+    <gripper name="grasp">
+      <gripper_link>finger</gripper_link>
+      <palm_link>palm</palm_link>
+    </gripper>
+  </model>
+</model>"#,
+    )
+    .unwrap();
+    let flat = flatten(&model).unwrap();
+    let axis = flat.joint[0].axis.as_ref().unwrap();
+    assert_eq!(axis.xyz.expressed_in.as_deref(), Some("hand"));
+    let gripper = &flat.gripper[0];
+    assert_eq!(gripper.name, "hand::grasp");
+    assert_eq!(gripper.palm_link, "hand::palm");
+    assert_eq!(gripper.gripper_link, vec!["hand::finger"]);
+    let expected = model.model[0].joint[0]
+        .axis_in(&model.model[0], "palm")
+        .unwrap();
+    let axis = flat.joint[0].axis_in(&flat, "hand::palm").unwrap();
+    assert!((axis - expected).norm() < 1e-9);
+
+    let unflattened = unflatten(&flat).unwrap();
+    let hand = &unflattened.model[0];
+    assert!(unflattened.gripper.is_empty());
+    assert_eq!(hand.gripper, model.model[0].gripper);
+    let axis = hand.joint[0].axis.as_ref().unwrap();
+    assert_eq!(axis.xyz.expressed_in.as_deref(), Some("__model__"));
+    let axis = hand.joint[0].axis_in(hand, "palm").unwrap();
+    assert!((axis - expected).norm() < 1e-9);
+}
@@ -0,0 +1,146 @@
+use sdformat::heightmap::HeightGrid;
+use sdformat::{from_str, SdfGeometry, SdfHeightmapShape};
+
+fn shape(xml: &str) -> SdfHeightmapShape {
+    match from_str::<SdfGeometry>(&format!(
+        "<geometry><heightmap>{}</heightmap></geometry>",
+        xml
+    ))
+    .unwrap()
+    {
+        SdfGeometry::Heightmap(shape) => shape,
+        other => panic!("Not a heightmap: {:?}", other),
+    }
+}
+
+fn assert_close(value: f64, expected: f64) {
+    assert!(
+        (value - expected).abs() < 1e-9,
+        "{} is not {}",
+        value,
+        expected
+    );
+}
+
+#[test]
+fn test_height_grid() {
+    let shape =
+        shape("<uri>terrain.png</uri><size>4 2 3</size><pos>1 0 -1</pos><sampling>2</sampling>");
+    // The top row is the edge at y = 1
+    let grid = HeightGrid::from_shape(&shape, 3, 2, vec![0.0, 0.5, 1.0, 0.0, 0.0, 0.0]).unwrap();
+    assert_eq!(grid.sampling, 2);
+
+    assert_close(grid.height_at(-1.0, 1.0).unwrap(), -1.0);
+    assert_close(grid.height_at(1.0, 1.0).unwrap(), 0.5);
+    assert_close(grid.height_at(3.0, 1.0).unwrap(), 2.0);
+    assert_close(grid.height_at(3.0, -1.0).unwrap(), -1.0);
+    // Interpolated between the four values around the point
+    assert_close(grid.height_at(2.0, 0.0).unwrap(), -1.0 + 0.75 * 0.5 * 3.0);
+    assert!(grid.height_at(3.5, 0.0).is_none());
+    assert!(grid.height_at(0.0, -1.5).is_none());
+
+    // Sampling 2 doubles the vertices along each axis
+    let mesh = grid.to_mesh();
+    assert_eq!(mesh.vertices.len(), 5 * 3);
+    assert_eq!(mesh.triangles.len(), 4 * 2 * 2);
+    for vertex in mesh.vertices.iter() {
+        assert_close(vertex.z, grid.height_at(vertex.x, vertex.y).unwrap());
+    }
+    for triangle in mesh.triangles.iter() {
+        let [a, b, c] = triangle.map(|index| mesh.vertices[index]);
+        assert!((b - a).cross(&(c - a)).z > 0.0);
+    }
+
+    assert!(HeightGrid::from_shape(&shape, 3, 2, vec![0.0; 5]).is_err());
+    assert!(HeightGrid::from_shape(&shape, 1, 6, vec![0.0; 6]).is_err());
+    let shape = self::shape("<uri>terrain.png</uri><sampling>0</sampling>");
+    assert!(HeightGrid::from_shape(&shape, 2, 2, vec![0.0; 4]).is_err());
+}
+
+#[cfg(feature = "heightmap")]
+mod loading {
+    use super::*;
+    use sdformat::heightmap::load_heightmap;
+    use sdformat::mesh::PathResolver;
+
+    /// A 3 by 3 grayscale PNG with the values 50 × (row + column).
+    const FIXED: &[u8] = b"\x89PNG\r\n\x1a\n\x00\x00\x00\x0dIHDR\x00\x00\x00\x03\x00\x00\x00\x03\
+        \x08\x00\x00\x00\x00\x73\x43\xea\x63\x00\x00\x00\x12IDAT\x78\xda\x63\x60\x30\x4a\x61\x34\
+        \x32\x32\x62\x01\x62\x00\x0a\x4c\x01\xc8\x03\x0d\x8a\x66\x00\x00\x00\x00IEND\xae\x42\x60\x82";
+
+    /// An 8 by 8 grayscale PNG with the values (row² + column²) / 3.
+    const DYNAMIC: &[u8] = b"\x89PNG\r\n\x1a\n\x00\x00\x00\x0dIHDR\x00\x00\x00\x08\x00\x00\x00\x08\
+        \x08\x00\x00\x00\x00\xe1\x64\xe1\x57\x00\x00\x00\x43IDAT\x78\xda\x3d\xc6\x3b\x0e\x00\x21\
+        \x08\x05\xc0\xe7\x2a\x04\x3f\x44\x88\x9a\x50\x58\xec\xfd\x4f\xb9\xdd\x4e\x35\x00\x52\x26\
+        \x19\x86\x3f\x29\x3d\x85\xab\x3a\x72\x2e\x2c\x7d\x2e\x10\xb1\x34\xf5\x03\x91\xda\xd5\x76\
+        \x60\x0c\x9d\xbe\xe3\xc2\xcc\xd7\x89\xfb\x7e\x47\x10\x02\xd1\x5a\x0f\xa2\x8d\x00\x00\x00\
+        \x00IEND\xae\x42\x60\x82";
+
+    /// A 4 by 2 grayscale PNG with 1 bit per sample.
+    const BITS: &[u8] = b"\x89PNG\r\n\x1a\n\x00\x00\x00\x0dIHDR\x00\x00\x00\x04\x00\x00\x00\x02\
+        \x01\x00\x00\x00\x00\x57\xd3\x40\xce\x00\x00\x00\x0cIDAT\x78\xda\x63\x58\xc0\x60\x00\x00\
+        \x02\x14\x00\xd1\xc7\xb2\xd7\x90\x00\x00\x00\x00IEND\xae\x42\x60\x82";
+
+    #[test]
+    fn test_load_heightmap() {
+        // Concurrent runs of the test do not share the directory
+        let directory =
+            std::env::temp_dir().join(format!("sdformat_heightmap_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&directory);
+        std::fs::create_dir_all(&directory).unwrap();
+        std::fs::write(directory.join("fixed.png"), FIXED).unwrap();
+        std::fs::write(directory.join("dynamic.png"), DYNAMIC).unwrap();
+        std::fs::write(directory.join("bits.png"), BITS).unwrap();
+        let mut corrupt = FIXED.to_vec();
+        corrupt[29] ^= 1;
+        std::fs::write(directory.join("corrupt.png"), corrupt).unwrap();
+        let mut pgm = b"P5\n# A comment\n3 2\n65535\n".to_vec();
+        for value in [0u16, 1000, 2000, 3000, 4000, 6000] {
+            pgm.extend(value.to_be_bytes());
+        }
+        std::fs::write(directory.join("binary.pgm"), pgm).unwrap();
+        std::fs::write(directory.join("ascii.pgm"), "P2 2 2 15\n0 5\n10 0\n").unwrap();
+        std::fs::write(directory.join("terrain.tif"), "").unwrap();
+        let resolver = PathResolver::new(&directory);
+
+        // Values are relative to the largest value of the samples, not to the brightest pixel
+        let grid = load_heightmap(&shape("<uri>fixed.png</uri>"), &resolver).unwrap();
+        assert_eq!((grid.columns, grid.rows), (3, 3));
+        let expected = [0.0, 50.0, 100.0, 50.0, 100.0, 150.0, 100.0, 150.0, 200.0];
+        for (value, expected) in grid.values.iter().zip(expected) {
+            assert_close(*value, expected / 255.0);
+        }
+        assert_close(grid.height_at(0.5, -0.5).unwrap(), 200.0 / 255.0);
+
+        let grid = load_heightmap(
+            &shape("<uri>dynamic.png</uri><size>7 7 32</size>"),
+            &resolver,
+        )
+        .unwrap();
+        assert_eq!((grid.columns, grid.rows), (8, 8));
+        for row in 0..8 {
+            for column in 0..8 {
+                let (x, y) = (column as f64 - 3.5, 3.5 - row as f64);
+                let expected = ((row * row + column * column) / 3) as f64;
+                assert_close(grid.height_at(x, y).unwrap(), expected / 255.0 * 32.0);
+            }
+        }
+
+        // Samples of 1 bit are scaled to bytes, set bits are white
+        let grid = load_heightmap(&shape("<uri>bits.png</uri>"), &resolver).unwrap();
+        assert_eq!(grid.values, vec![1.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 1.0]);
+        // The checksum of the header does not match
+        assert!(load_heightmap(&shape("<uri>corrupt.png</uri>"), &resolver).is_err());
+
+        let grid = load_heightmap(&shape("<uri>binary.pgm</uri>"), &resolver).unwrap();
+        let expected = [0.0, 1.0, 2.0, 3.0, 4.0, 6.0];
+        for (value, expected) in grid.values.iter().zip(expected) {
+            assert_close(*value, expected * 1000.0 / 65535.0);
+        }
+        let grid = load_heightmap(&shape("<uri>ascii.pgm</uri>"), &resolver).unwrap();
+        assert_eq!(grid.values, vec![0.0, 5.0 / 15.0, 10.0 / 15.0, 0.0]);
+
+        assert!(load_heightmap(&shape("<uri>terrain.tif</uri>"), &resolver).is_err());
+        assert!(load_heightmap(&shape("<uri>missing.png</uri>"), &resolver).is_err());
+    }
+}
@@ -0,0 +1,275 @@
+use std::collections::HashMap;
+use std::f64::consts::{FRAC_PI_2, PI};
+
+use nalgebra::{Matrix3, Vector3};
+use sdformat::inertial::{
+    check_inertial, check_model, check_world, model_mass_properties, subtree_mass_properties,
+    MassProperties,
+};
+use sdformat::kinematics::JointPosition;
+use sdformat::mesh::TriangleMesh;
+use sdformat::{
+    from_str, SdfBoxShape, SdfGeometry, SdfInertial, SdfInertialInertia, SdfLink, SdfModel,
+    SdfWorld, Vector3d,
+};
+
+fn inertial(mass: f64, moments: [f64; 3]) -> SdfInertial {
+    SdfInertial {
+        mass: Some(mass),
+        inertia: Some(SdfInertialInertia {
+            ixx: moments[0],
+            iyy: moments[1],
+            izz: moments[2],
+            ..Default::default()
+        }),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_check_inertial() {
+    assert!(check_inertial(&inertial(1.0, [1.0, 1.0, 1.0]), "box").is_empty());
+    // Rods have a zero principal moment, plates sit on the edge of the triangle inequality
+    assert_eq!(
+        check_inertial(&inertial(1.0, [1.0, 1.0, 0.0]), "rod").len(),
+        1
+    );
+    assert!(check_inertial(&inertial(1.0, [1.0, 1.0, 2.0]), "plate").is_empty());
+
+    let issues = check_inertial(&inertial(0.0, [1.0, 1.0, 1.0]), "box");
+    assert_eq!(issues.len(), 1);
+    assert!(issues[0].message.contains("mass of 0"));
+    assert!(issues[0].fix.is_none());
+
+    let issues = check_inertial(&inertial(1.0, [1.0, 1.0, 3.0]), "box");
+    assert_eq!(issues.len(), 1);
+    assert!(issues[0].message.contains("triangle inequality"));
+    let fix = issues[0].fix.as_ref().unwrap();
+    assert!((fix.inertia.as_ref().unwrap().izz - 2.0).abs() < 1e-9);
+    assert!(check_inertial(fix, "box").is_empty());
+
+    // Products of inertia larger than the moments
+    let mut skewed = inertial(2.0, [1.0, 1.0, 1.0]);
+    skewed.inertia.as_mut().unwrap().ixy = Some(2.0);
+    let issues = check_inertial(&skewed, "box");
+    assert!(issues[0].message.contains("not positive definite"));
+    let fix = issues[0].fix.as_ref().unwrap();
+    assert_eq!(fix.mass, Some(2.0));
+    assert!(check_inertial(fix, "box").is_empty());
+
+    let issues = check_inertial(&inertial(-1.0, [-1.0, -1.0, -1.0]), "box");
+    assert_eq!(issues.len(), 2);
+    assert!(issues[1].fix.is_none());
+}
+
+#[test]
+fn test_check_scoped_links() {
+    let model = from_str::<SdfModel>(
+        r#"<model name="robot">
+  <link name="base">
+    <inertial><mass>-2</mass></inertial>
+  </link>
+  <link name="empty"/>
+  <model name="arm">
+    <link name="upper">
+      <inertial>
+        <inertia><ixx>1</ixx><iyy>1</iyy><izz>5</izz></inertia>
+      </inertial>
+    </link>
+  </model>
+</model>"#,
+    )
+    .unwrap();
+    let links: Vec<_> = check_model(&model)
+        .into_iter()
+        .map(|issue| issue.link)
+        .collect();
+    assert_eq!(links, vec!["base", "arm::upper"]);
+
+    let world = SdfWorld {
+        model: vec![model],
+        ..Default::default()
+    };
+    assert_eq!(check_world(&world)[1].link, "robot::arm::upper");
+}
+
+/// A box centered on `center`, with outward triangles.
+fn box_mesh(size: [f64; 3], center: [f64; 3]) -> TriangleMesh {
+    let vertices = (0..8)
+        .map(|corner| {
+            Vector3::from_fn(|axis, _| {
+                let sign = if corner >> axis & 1 == 1 { 0.5 } else { -0.5 };
+                center[axis] + sign * size[axis]
+            })
+        })
+        .collect();
+    let triangles = vec![
+        [0, 2, 1],
+        [1, 2, 3],
+        [4, 5, 6],
+        [5, 7, 6],
+        [0, 1, 4],
+        [1, 5, 4],
+        [2, 6, 3],
+        [3, 6, 7],
+        [0, 4, 2],
+        [2, 4, 6],
+        [1, 3, 5],
+        [3, 7, 5],
+    ];
+    TriangleMesh {
+        vertices,
+        triangles,
+    }
+}
+
+#[test]
+fn test_mesh_mass_properties() {
+    let geometry = SdfGeometry::Box(SdfBoxShape {
+        size: Vector3d::new(1.0, 2.0, 3.0),
+        ..Default::default()
+    });
+    let expected = MassProperties::of_geometry(&geometry, 10.0).unwrap();
+    assert!((expected.mass - 60.0).abs() < 1e-9);
+    assert!((expected.inertia[(0, 0)] - 65.0).abs() < 1e-9);
+
+    let mut mesh = box_mesh([1.0, 2.0, 3.0], [1.0, -1.0, 2.0]);
+    let properties = MassProperties::of_mesh(&mesh, 10.0).unwrap();
+    assert!((properties.mass - expected.mass).abs() < 1e-9);
+    assert!((properties.center - Vector3::new(1.0, -1.0, 2.0)).norm() < 1e-9);
+    assert!((properties.inertia - expected.inertia).norm() < 1e-9);
+
+    // Inward triangles describe the same solid
+    for triangle in mesh.triangles.iter_mut() {
+        triangle.swap(1, 2);
+    }
+    let inverted = MassProperties::of_mesh(&mesh, 10.0).unwrap();
+    assert!((inverted.inertia - expected.inertia).norm() < 1e-9);
+
+    mesh.triangles.pop();
+    assert!(MassProperties::of_mesh(&mesh, 10.0)
+        .unwrap_err()
+        .contains("not closed"));
+}
+
+#[test]
+fn test_auto_inertial() {
+    let link = from_str::<SdfLink>(
+        r#"<link name="dumbbell">
+  <collision name="bar">
+    <pose>0 0 0 0 1.5707963267948966 0</pose>
+    <geometry><cylinder><radius>0.1</radius><length>2</length></cylinder></geometry>
+  </collision>
+  <collision name="left">
+    <pose>-1 0 0 0 0 0</pose>
+    <geometry><sphere><radius>0.5</radius></sphere></geometry>
+  </collision>
+  <collision name="right">
+    <pose>1 0 0 0 0 0</pose>
+    <geometry><mesh><uri>cube.stl</uri><scale>1 1 2</scale></mesh></geometry>
+  </collision>
+</link>"#,
+    )
+    .unwrap();
+    assert!(link.auto_inertial(1000.0).unwrap_err().contains("cube.stl"));
+
+    let inertial = link
+        .auto_inertial_with_meshes(1000.0, |_| Ok(box_mesh([0.5, 0.5, 0.5], [0.0; 3])))
+        .unwrap();
+    let (bar, sphere, cube) = (PI * 0.01 * 2.0, 4.0 / 3.0 * PI * 0.125, 0.25);
+    let mass = 1000.0 * (bar + sphere + cube);
+    assert!((inertial.mass.unwrap() - mass).abs() < 1e-9);
+    let properties = MassProperties::from_inertial(&inertial).unwrap();
+    let center = 1000.0 * (cube - sphere) / mass;
+    assert!((properties.center - Vector3::new(center, 0.0, 0.0)).norm() < 1e-9);
+    // The bar lies along x, so its axial moment is about x
+    let ixx = 1000.0 * (bar * 0.01 / 2.0 + 0.4 * sphere * 0.25 + cube * (0.25 + 1.0) / 12.0);
+    assert!((properties.inertia[(0, 0)] - ixx).abs() < 1e-9);
+    let diagonal = Matrix3::from_diagonal(&properties.inertia.diagonal());
+    assert!((properties.inertia - diagonal).norm() < 1e-9);
+    assert!(check_inertial(&inertial, "dumbbell").is_empty());
+
+    let capsule = from_str::<SdfLink>(
+        r#"<link name="pill">
+  <collision name="shape">
+    <geometry><capsule><radius>0.5</radius><length>1</length></capsule></geometry>
+  </collision>
+</link>"#,
+    )
+    .unwrap();
+    let inertial = capsule.auto_inertial(1.0).unwrap();
+    assert!((inertial.mass.unwrap() - PI * (0.25 + 0.5 / 3.0)).abs() < 1e-9);
+    let inertia = MassProperties::from_inertial(&inertial).unwrap().inertia;
+    assert!(inertia[(0, 0)] > inertia[(2, 2)]);
+    assert_eq!(inertia, Matrix3::from_diagonal(&inertia.diagonal()));
+
+    assert!(SdfLink::default().auto_inertial(1.0).is_err());
+}
+
+const PAYLOAD: &str = r#"<model name="crane">
+  <pose>0 0 1 0 0 0</pose>
+  <link name="base">
+    <inertial>
+      <mass>10</mass>
+      <inertia><ixx>1</ixx><iyy>1</iyy><izz>1</izz></inertia>
+    </inertial>
+  </link>
+  <frame name="hook" attached_to="base">
+    <pose>2 0 0 0 0 0</pose>
+  </frame>
+  <model name="boom">
+    <pose relative_to="hook">0 0 0 0 0 0</pose>
+    <link name="arm">
+      <inertial>
+        <pose>1 0 0 0 0 0</pose>
+        <mass>2</mass>
+        <inertia><ixx>0.1</ixx><iyy>0.2</iyy><izz>0.3</izz></inertia>
+      </inertial>
+    </link>
+    <link name="load">
+      <pose>2 0 0 0 0 0</pose>
+      <inertial>
+        <mass>3</mass>
+        <inertia><ixx>0.5</ixx><iyy>0.5</iyy><izz>0.5</izz></inertia>
+      </inertial>
+    </link>
+    <joint name="cable" type="fixed">
+      <parent>arm</parent>
+      <child>load</child>
+    </joint>
+  </model>
+  <joint name="slew" type="revolute">
+    <parent>hook</parent>
+    <child>boom::arm</child>
+    <axis><xyz>0 0 1</xyz></axis>
+  </joint>
+</model>"#;
+
+#[test]
+fn test_aggregate_mass_properties() {
+    let model = from_str::<SdfModel>(PAYLOAD).unwrap();
+    let zero = HashMap::new();
+    let total = model_mass_properties(&model, &zero, "__model__").unwrap();
+    assert!((total.mass - 15.0).abs() < 1e-9);
+    // The arm has its center at x = 3 and the load at x = 4
+    assert!((total.center - Vector3::new(1.2, 0.0, 0.0)).norm() < 1e-9);
+    let spread = 10.0 * 1.2f64.powi(2) + 2.0 * 1.8f64.powi(2) + 3.0 * 2.8f64.powi(2);
+    assert!((total.inertia[(2, 2)] - (1.0 + 0.3 + 0.5 + spread)).abs() < 1e-9);
+    assert!((total.inertia[(0, 0)] - 1.6).abs() < 1e-9);
+
+    // The model pose places it in the world
+    let world = model_mass_properties(&model, &zero, "world").unwrap();
+    assert!((world.center - Vector3::new(1.2, 0.0, 1.0)).norm() < 1e-9);
+
+    let positions = HashMap::from([("slew".to_string(), JointPosition::from(FRAC_PI_2))]);
+    let payload = subtree_mass_properties(&model, &positions, "slew", "hook").unwrap();
+    assert!((payload.mass - 5.0).abs() < 1e-9);
+    assert!((payload.center - Vector3::new(0.0, 1.6, 0.0)).norm() < 1e-9);
+    let payload = subtree_mass_properties(&model, &positions, "slew", "boom::load").unwrap();
+    assert!((payload.center - Vector3::new(-0.4, 0.0, 0.0)).norm() < 1e-9);
+
+    assert!(subtree_mass_properties(&model, &zero, "missing", "hook")
+        .unwrap_err()
+        .contains("missing"));
+    assert!(model_mass_properties(&model, &zero, "missing").is_err());
+}
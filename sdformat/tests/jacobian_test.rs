@@ -0,0 +1,175 @@
+use std::collections::HashMap;
+use std::f64::consts::FRAC_PI_2;
+
+use nalgebra::{UnitQuaternion, Vector3, Vector6};
+use sdformat::jacobian::jacobian;
+use sdformat::kinematics::{forward_kinematics, JointPosition, KinematicTree};
+use sdformat::{from_str, SdfModel};
+
+const ARM: &str = r#"<model name="arm">
+  <link name="base"/>
+  <link name="upper"/>
+  <link name="lower">
+    <pose>1 0 0 0 0 0</pose>
+  </link>
+  <link name="slider">
+    <pose>2 0 0 0 0 0</pose>
+  </link>
+  <frame name="tool" attached_to="slider">
+    <pose>0.5 0 0 0 0 0</pose>
+  </frame>
+  <link name="gimbal">
+    <pose>2 0 0 0 0 0</pose>
+  </link>
+  <frame name="camera" attached_to="gimbal">
+    <pose>0 0 0.5 0 0 0</pose>
+  </frame>
+  <joint name="anchor" type="fixed">
+    <parent>world</parent>
+    <child>base</child>
+  </joint>
+  <joint name="shoulder" type="revolute">
+    <parent>base</parent>
+    <child>upper</child>
+    <axis>
+      <xyz>0 0 1</xyz>
+      <limit><lower>-1</lower><upper>1</upper></limit>
+    </axis>
+  </joint>
+  <joint name="elbow" type="continuous">
+    <parent>upper</parent>
+    <child>lower</child>
+    <axis>
+      <xyz>0 0 1</xyz>
+      <limit><lower>-1</lower><upper>1</upper></limit>
+    </axis>
+  </joint>
+  <joint name="rail" type="prismatic">
+    <parent>lower</parent>
+    <child>slider</child>
+    <axis>
+      <xyz>1 0 0</xyz>
+      <limit><lower>0</lower><upper>0.5</upper></limit>
+    </axis>
+  </joint>
+  <joint name="cardan" type="universal">
+    <parent>lower</parent>
+    <child>gimbal</child>
+    <axis><xyz>1 0 0</xyz></axis>
+    <axis2><xyz>0 1 0</xyz><limit><lower>-0.5</lower><upper>0.5</upper></limit></axis2>
+  </joint>
+</model>"#;
+
+fn positions(values: &[(&str, JointPosition)]) -> HashMap<String, JointPosition> {
+    values
+        .iter()
+        .map(|(name, position)| (name.to_string(), *position))
+        .collect()
+}
+
+#[test]
+fn test_planar_jacobian() {
+    let model = from_str::<SdfModel>(ARM).unwrap();
+    let positions = positions(&[("shoulder", FRAC_PI_2.into())]);
+    let jacobian = jacobian(&model, &positions, "tool").unwrap();
+    let columns: Vec<_> = jacobian
+        .columns
+        .iter()
+        .map(|dof| dof.joint.as_str())
+        .collect();
+    assert_eq!(columns, vec!["shoulder", "elbow", "rail"]);
+    assert!((jacobian.pose.translation.vector - Vector3::new(0.0, 2.5, 0.0)).norm() < 1e-9);
+
+    // The tool is 2.5 m along y, turning the shoulder moves it along -x
+    let expected = [
+        Vector6::new(-2.5, 0.0, 0.0, 0.0, 0.0, 1.0),
+        Vector6::new(-1.5, 0.0, 0.0, 0.0, 0.0, 1.0),
+        Vector6::new(0.0, 1.0, 0.0, 0.0, 0.0, 0.0),
+    ];
+    for (column, expected) in jacobian.matrix.column_iter().zip(expected.iter()) {
+        assert!((column - expected).norm() < 1e-9, "{}", column);
+    }
+    // Points on the axis of the shoulder do not move in the spatial Jacobian
+    let spatial = jacobian.spatial();
+    assert!((spatial.column(0) - Vector6::new(0.0, 0.0, 0.0, 0.0, 0.0, 1.0)).norm() < 1e-9);
+    assert!((spatial.column(1) - Vector6::new(1.0, 0.0, 0.0, 0.0, 0.0, 1.0)).norm() < 1e-9);
+
+    assert!(jacobian
+        .velocity(&[1.0])
+        .unwrap_err()
+        .contains("Expected 3"));
+    let velocity = jacobian.velocity(&[1.0, 0.0, 2.0]).unwrap();
+    assert!((velocity - Vector6::new(-2.5, 2.0, 0.0, 0.0, 0.0, 1.0)).norm() < 1e-9);
+}
+
+#[test]
+fn test_jacobian_finite_differences() {
+    let model = from_str::<SdfModel>(ARM).unwrap();
+    let base = [0.3, -0.4, 0.7, 0.2];
+    let at = |values: [f64; 4]| {
+        positions(&[
+            ("shoulder", values[0].into()),
+            ("elbow", values[1].into()),
+            ("cardan", JointPosition::Pair(values[2], values[3])),
+        ])
+    };
+    let camera = jacobian(&model, &at(base), "camera").unwrap();
+    assert_eq!(camera.columns.len(), 4);
+    assert_eq!(camera.columns[3].joint, "cardan");
+    assert_eq!(camera.columns[3].axis, 1);
+
+    let pose = |values| forward_kinematics(&model, &at(values)).unwrap().frames["camera"];
+    let step = 1e-6;
+    for index in 0..4 {
+        let mut moved = base;
+        moved[index] += step;
+        let (before, after) = (pose(base), pose(moved));
+        let linear = (after.translation.vector - before.translation.vector) / step;
+        let angular = (after.rotation * before.rotation.inverse()).scaled_axis() / step;
+        let column = camera.matrix.column(index);
+        assert!(
+            (column.fixed_rows::<3>(0) - linear).norm() < 1e-5,
+            "{}",
+            index
+        );
+        assert!(
+            (column.fixed_rows::<3>(3) - angular).norm() < 1e-5,
+            "{}",
+            index
+        );
+    }
+
+    assert!(jacobian(&model, &HashMap::new(), "missing")
+        .unwrap_err()
+        .contains("missing"));
+}
+
+#[test]
+fn test_clamp_positions() {
+    let model = from_str::<SdfModel>(ARM).unwrap();
+    let tree = KinematicTree::from_model(&model).unwrap();
+    let mut positions = positions(&[
+        ("shoulder", 2.0.into()),
+        ("elbow", 2.0.into()),
+        ("rail", (-1.0).into()),
+        ("cardan", JointPosition::Pair(5.0, -1.0)),
+        ("unknown", 7.0.into()),
+    ]);
+    assert!(!tree
+        .joint("shoulder")
+        .unwrap()
+        .within_limits(&positions["shoulder"]));
+    tree.clamp_positions(&mut positions);
+    assert_eq!(positions["shoulder"], JointPosition::Scalar(1.0));
+    // Continuous joints ignore their limits
+    assert_eq!(positions["elbow"], JointPosition::Scalar(2.0));
+    assert_eq!(positions["rail"], JointPosition::Scalar(0.0));
+    assert_eq!(positions["cardan"], JointPosition::Pair(5.0, -0.5));
+    assert_eq!(positions["unknown"], JointPosition::Scalar(7.0));
+    let rotation = JointPosition::Rotation(UnitQuaternion::from_euler_angles(3.0, 0.0, 0.0));
+    assert_eq!(tree.joint("anchor").unwrap().clamp(rotation), rotation);
+    assert!(tree
+        .joint("shoulder")
+        .unwrap()
+        .within_limits(&positions["shoulder"]));
+}
@@ -0,0 +1,269 @@
+use std::collections::HashMap;
+use std::f64::consts::{FRAC_PI_2, PI};
+
+use nalgebra::{Isometry3, UnitQuaternion, Vector3};
+use sdformat::kinematics::{forward_kinematics, JointPosition, KinematicTree};
+use sdformat::{from_str, SdfModel};
+
+const ROBOT: &str = r#"<model name="robot">
+  <link name="base"/>
+  <link name="lower"/>
+  <link name="upper"/>
+  <link name="box"/>
+  <frame name="mount" attached_to="lower"/>
+  <joint name="anchor" type="fixed">
+    <parent>world</parent>
+    <child>base</child>
+  </joint>
+  <joint name="elbow" type="revolute">
+    <parent>mount</parent>
+    <child>upper</child>
+    <axis>
+      <xyz>0 1 0</xyz>
+      <limit><lower>-1</lower><upper>2</upper></limit>
+    </axis>
+  </joint>
+  <joint name="shoulder" type="revolute">
+    <parent>base</parent>
+    <child>lower</child>
+    <axis><xyz>0 0 1</xyz></axis>
+  </joint>
+  <model name="hand">
+    <link name="palm"/>
+    <link name="finger"/>
+    <joint name="knuckle" type="ball">
+      <parent>palm</parent>
+      <child>finger</child>
+    </joint>
+  </model>
+  <joint name="wrist" type="fixed">
+    <parent>upper</parent>
+    <child>hand::__model__</child>
+  </joint>
+  <joint name="brace" type="fixed">
+    <parent>base</parent>
+    <child>upper</child>
+  </joint>
+</model>"#;
+
+#[test]
+fn test_kinematic_tree() {
+    let model = from_str::<SdfModel>(ROBOT).unwrap();
+    let tree = KinematicTree::from_model(&model).unwrap();
+    assert_eq!(
+        tree.links(),
+        [
+            "base",
+            "lower",
+            "upper",
+            "box",
+            "hand::palm",
+            "hand::finger"
+        ]
+    );
+    assert_eq!(
+        tree.order(),
+        [
+            "base",
+            "lower",
+            "upper",
+            "hand::palm",
+            "hand::finger",
+            "box"
+        ]
+    );
+    assert_eq!(tree.roots().collect::<Vec<_>>(), vec!["base", "box"]);
+    assert_eq!(tree.floating().collect::<Vec<_>>(), vec!["box"]);
+    assert_eq!(tree.children("world").collect::<Vec<_>>(), vec!["base"]);
+
+    // Joints to frames are connected to the link the frame is attached to
+    let elbow = tree.parent_joint("upper").unwrap();
+    assert_eq!(elbow.parent, "lower");
+    let axis = elbow.axis.as_ref().unwrap();
+    assert_eq!(axis.xyz, nalgebra::Vector3::y());
+    assert_eq!(axis.limit.as_ref().unwrap().upper, 2.0);
+    assert_eq!(tree.children("lower").collect::<Vec<_>>(), vec!["upper"]);
+
+    // Joints to models are connected to their canonical link
+    assert_eq!(tree.parent_joint("hand::palm").unwrap().name, "wrist");
+    assert_eq!(tree.joint("hand::knuckle").unwrap().r#type, "ball");
+
+    // The brace gives the upper link a second parent
+    assert!(tree.has_loops());
+    let loops: Vec<_> = tree
+        .loop_joints()
+        .map(|joint| joint.name.as_str())
+        .collect();
+    assert_eq!(loops, vec!["brace"]);
+}
+
+#[test]
+fn test_kinematic_cycle() {
+    let model = from_str::<SdfModel>(
+        r#"<model name="cycle">
+  <link name="a"/>
+  <link name="b"/>
+  <joint name="ab" type="fixed"><parent>a</parent><child>b</child></joint>
+  <joint name="ba" type="fixed"><parent>b</parent><child>a</child></joint>
+</model>"#,
+    )
+    .unwrap();
+    let tree = KinematicTree::from_model(&model).unwrap();
+    assert_eq!(tree.order(), ["a", "b"]);
+    let loops: Vec<_> = tree
+        .loop_joints()
+        .map(|joint| joint.name.as_str())
+        .collect();
+    assert_eq!(loops, vec!["ba"]);
+
+    let mut model = model;
+    model.joint[0].parent = "missing".to_string();
+    assert!(KinematicTree::from_model(&model)
+        .unwrap_err()
+        .contains("missing"));
+}
+
+const MECHANISM: &str = r#"<model name="mechanism">
+  <pose>1 0 0 0 0 0</pose>
+  <link name="base"/>
+  <link name="upper">
+    <pose>0 0 1 0 0 0</pose>
+  </link>
+  <frame name="tip" attached_to="upper">
+    <pose>1 0 0 0 0 0</pose>
+  </frame>
+  <link name="slider">
+    <pose>2 0 1 0 0 0</pose>
+  </link>
+  <link name="nut">
+    <pose>0 0 3 0 0 0</pose>
+  </link>
+  <link name="gimbal">
+    <pose>0 0 5 0 0 0</pose>
+    <sensor name="imu" type="imu">
+      <pose>0 0 1 0 0 0</pose>
+    </sensor>
+  </link>
+  <link name="head">
+    <pose>0 0 7 0 0 0</pose>
+  </link>
+  <frame name="nose" attached_to="head">
+    <pose>1 0 0 0 0 0</pose>
+  </frame>
+  <link name="box">
+    <pose>0 5 0 0 0 0</pose>
+  </link>
+  <joint name="anchor" type="fixed">
+    <parent>world</parent>
+    <child>base</child>
+  </joint>
+  <joint name="shoulder" type="revolute">
+    <parent>base</parent>
+    <child>upper</child>
+    <axis><xyz>0 0 1</xyz></axis>
+  </joint>
+  <joint name="rail" type="prismatic">
+    <parent>upper</parent>
+    <child>slider</child>
+    <axis><xyz>1 0 0</xyz></axis>
+  </joint>
+  <joint name="screw" type="screw">
+    <parent>base</parent>
+    <child>nut</child>
+    <screw_thread_pitch>0.2</screw_thread_pitch>
+    <axis><xyz>0 0 1</xyz></axis>
+  </joint>
+  <joint name="cardan" type="universal">
+    <parent>base</parent>
+    <child>gimbal</child>
+    <axis><xyz>1 0 0</xyz></axis>
+    <axis2><xyz>0 1 0</xyz><limit/></axis2>
+  </joint>
+  <joint name="neck" type="ball">
+    <parent>base</parent>
+    <child>head</child>
+  </joint>
+</model>"#;
+
+fn assert_position(poses: &HashMap<String, Isometry3<f64>>, name: &str, expected: [f64; 3]) {
+    let position = poses[name].translation.vector;
+    assert!(
+        (position - Vector3::from(expected)).norm() < 1e-9,
+        "{}: {}",
+        name,
+        position
+    );
+}
+
+#[test]
+fn test_forward_kinematics() {
+    let model = from_str::<SdfModel>(MECHANISM).unwrap();
+    let positions = HashMap::from([
+        ("shoulder".to_string(), JointPosition::from(FRAC_PI_2)),
+        ("rail".to_string(), JointPosition::from(0.5)),
+        ("screw".to_string(), JointPosition::from(2.0 * PI)),
+        ("cardan".to_string(), JointPosition::Pair(FRAC_PI_2, 0.0)),
+        (
+            "neck".to_string(),
+            JointPosition::Rotation(UnitQuaternion::from_axis_angle(&Vector3::z_axis(), PI)),
+        ),
+    ]);
+    let poses = forward_kinematics(&model, &positions).unwrap();
+    assert_position(&poses.links, "base", [1.0, 0.0, 0.0]);
+    assert_position(&poses.links, "upper", [1.0, 0.0, 1.0]);
+    assert_position(&poses.frames, "tip", [1.0, 1.0, 1.0]);
+    // The rail moves along its axis, rotated with the upper link
+    assert_position(&poses.links, "slider", [1.0, 2.5, 1.0]);
+    assert_position(&poses.links, "nut", [1.0, 0.0, 3.2]);
+    assert_position(&poses.sensors, "gimbal::imu", [1.0, -1.0, 5.0]);
+    assert_position(&poses.frames, "nose", [0.0, 0.0, 7.0]);
+    assert_position(&poses.links, "box", [1.0, 5.0, 0.0]);
+    assert_position(&poses.frames, "shoulder", [1.0, 0.0, 1.0]);
+
+    let positions = HashMap::from([("cardan".to_string(), JointPosition::from(1.0))]);
+    assert!(forward_kinematics(&model, &positions)
+        .unwrap_err()
+        .contains("two positions"));
+}
+
+#[test]
+fn test_axis_expressed_in() {
+    let mut model = from_str::<SdfModel>(
+        r#"<model name="turret">
+  <link name="base"/>
+  <link name="barrel"/>
+  <frame name="tilted" attached_to="base">
+    <pose>0 0 0 1.5707963267948966 0 0</pose>
+  </frame>
+  <joint name="pivot" type="revolute">
+    <pose>0 0 0 0 0 1.5707963267948966</pose>
+    <parent>base</parent>
+    <child>barrel</child>
+    <axis><xyz expressed_in="__model__">2 0 0</xyz></axis>
+    <axis2><xyz expressed_in="tilted">0 0 1</xyz><limit/></axis2>
+  </joint>
+</model>"#,
+    )
+    .unwrap();
+    let joint = &model.joint[0];
+    let close = |a: Vector3<f64>, b: Vector3<f64>| (a - b).norm() < 1e-9;
+    // The model x axis is the negative y axis of the joint, rotated about z
+    let axis = joint.axis_in_joint_frame(&model).unwrap();
+    assert!(close(axis, -Vector3::y()), "{}", axis);
+    assert!(close(
+        joint.axis_in(&model, "__model__").unwrap(),
+        Vector3::x()
+    ));
+    let axis2 = joint.axis2_in(&model, "__model__").unwrap();
+    assert!(close(axis2, -Vector3::y()), "{}", axis2);
+
+    let tree = KinematicTree::from_model(&model).unwrap();
+    let pivot = tree.joint("pivot").unwrap();
+    assert!(close(pivot.axis.as_ref().unwrap().xyz, -Vector3::y() * 2.0));
+
+    model.joint[0].axis.as_mut().unwrap().xyz.data.0 = Vector3::zeros();
+    assert!(model.joint[0]
+        .axis_in_joint_frame(&model)
+        .unwrap_err()
+        .contains("zero length"));
+}
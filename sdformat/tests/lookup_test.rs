@@ -0,0 +1,65 @@
+use sdformat::{from_str, SdfWorld};
+
+const WORLD: &str = r#"<world name="lab">
+  <gravity>0 0 -9.8</gravity>
+  <magnetic_field>0 0 0</magnetic_field>
+  <atmosphere type="adiabatic"/>
+  <scene><ambient>0.4 0.4 0.4 1</ambient><background>0.7 0.7 0.7 1</background><shadows>true</shadows></scene>
+  <frame name="table"/>
+  <model name="robot">
+    <link name="base"/>
+    <model name="arm">
+      <link name="link3">
+        <sensor name="camera" type="camera"/>
+      </link>
+      <frame name="tool" attached_to="link3"/>
+      <joint name="elbow" type="revolute">
+        <parent>link3</parent>
+        <child>link3</child>
+        <axis><xyz>0 0 1</xyz></axis>
+        <sensor name="torque" type="force_torque"/>
+      </joint>
+    </model>
+  </model>
+</world>"#;
+
+#[test]
+fn test_scoped_lookup() {
+    let world = from_str::<SdfWorld>(WORLD).unwrap();
+
+    let link = world.find_link("robot::arm::link3").unwrap();
+    assert_eq!(link.element.name, "link3");
+    assert_eq!(link.scope, "robot::arm");
+    assert_eq!(world.find_link("robot::base").unwrap().scope, "robot");
+
+    let arm = world.find_model("robot::arm").unwrap();
+    assert_eq!(arm.element.link.len(), 1);
+    assert_eq!(arm.scope, "robot");
+    assert_eq!(world.find_model("robot").unwrap().scope, "");
+
+    assert_eq!(
+        world.find_joint("robot::arm::elbow").unwrap().scope,
+        "robot::arm"
+    );
+    assert_eq!(world.find_frame("table").unwrap().scope, "");
+    let tool = world.find_frame("robot::arm::tool").unwrap();
+    assert_eq!(tool.element.attached_to.as_deref(), Some("link3"));
+
+    let camera = world.find_sensor("robot::arm::link3::camera").unwrap();
+    assert_eq!(camera.element.r#type, "camera");
+    assert_eq!(camera.scope, "robot::arm::link3");
+    let torque = world.find_sensor("robot::arm::elbow::torque").unwrap();
+    assert_eq!(torque.scope, "robot::arm::elbow");
+}
+
+#[test]
+fn test_scoped_lookup_missing() {
+    let world = from_str::<SdfWorld>(WORLD).unwrap();
+    assert!(world.find_link("base").is_none());
+    assert!(world.find_link("robot::link3").is_none());
+    assert!(world.find_link("robot::arm::base").is_none());
+    assert!(world.find_model("robot::hand").is_none());
+    assert!(world.find_joint("elbow").is_none());
+    assert!(world.find_sensor("robot::arm::link3::lidar").is_none());
+    assert!(world.find_sensor("camera").is_none());
+}
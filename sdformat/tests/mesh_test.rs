@@ -0,0 +1,311 @@
+#![cfg(feature = "mesh")]
+
+use std::fmt::Write;
+use std::path::PathBuf;
+
+use nalgebra::Vector3;
+use sdformat::inertial::MassProperties;
+use sdformat::mesh::{load_mesh, load_submesh, read_meshes, MeshResolver, PathResolver};
+use sdformat::{from_str, SdfGeometry, SdfMeshShape};
+
+/// The corners and outward triangles of a unit cube centered on the origin.
+fn cube() -> (Vec<[f64; 3]>, Vec<[usize; 3]>) {
+    let corners = (0..8)
+        .map(|corner| [0, 1, 2].map(|axis| if corner >> axis & 1 == 1 { 0.5 } else { -0.5 }))
+        .collect();
+    let triangles = vec![
+        [0, 2, 1],
+        [1, 2, 3],
+        [4, 5, 6],
+        [5, 7, 6],
+        [0, 1, 4],
+        [1, 5, 4],
+        [2, 6, 3],
+        [3, 6, 7],
+        [0, 4, 2],
+        [2, 4, 6],
+        [1, 3, 5],
+        [3, 7, 5],
+    ];
+    (corners, triangles)
+}
+
+/// An empty directory of the test, which concurrent runs of the tests do not share.
+fn directory(name: &str) -> PathBuf {
+    let directory =
+        std::env::temp_dir().join(format!("sdformat_mesh_{}_{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&directory);
+    std::fs::create_dir_all(&directory).unwrap();
+    directory
+}
+
+fn shape(xml: &str) -> SdfMeshShape {
+    match from_str::<SdfGeometry>(&format!("<geometry>{}</geometry>", xml)).unwrap() {
+        SdfGeometry::Mesh(shape) => shape,
+        other => panic!("Not a mesh: {:?}", other),
+    }
+}
+
+#[test]
+fn test_stl() {
+    let directory = directory("stl");
+    let (corners, triangles) = cube();
+    let mut binary = vec![0u8; 80];
+    binary.extend((triangles.len() as u32).to_le_bytes());
+    for triangle in triangles.iter() {
+        binary.extend([0u8; 12]);
+        for corner in triangle {
+            for value in corners[*corner] {
+                binary.extend((value as f32).to_le_bytes());
+            }
+        }
+        binary.extend([0u8; 2]);
+    }
+    std::fs::write(directory.join("cube.stl"), binary).unwrap();
+
+    let mut ascii = String::new();
+    for (name, offset) in [("left", -2.0), ("right", 2.0)] {
+        writeln!(ascii, "solid {}", name).unwrap();
+        for triangle in triangles.iter() {
+            writeln!(ascii, "facet normal 0 0 0\nouter loop").unwrap();
+            for corner in triangle {
+                let [x, y, z] = corners[*corner];
+                writeln!(ascii, "vertex {} {} {}", x + offset, y, z).unwrap();
+            }
+            writeln!(ascii, "endloop\nendfacet").unwrap();
+        }
+        writeln!(ascii, "endsolid {}", name).unwrap();
+    }
+    std::fs::write(directory.join("pair.STL"), ascii).unwrap();
+
+    let resolver = PathResolver::new(&directory);
+    let mesh = load_mesh(
+        &shape("<mesh><uri>cube.stl</uri><scale>2 1 1</scale></mesh>"),
+        &resolver,
+    )
+    .unwrap();
+    // Corners shared by triangles are merged, which closes the surface
+    assert_eq!(mesh.vertices.len(), 8);
+    assert!(mesh.is_closed());
+    let properties = MassProperties::of_mesh(&mesh, 1.0).unwrap();
+    assert!((properties.mass - 2.0).abs() < 1e-9);
+
+    let meshes = read_meshes(&directory.join("pair.STL")).unwrap();
+    let names: Vec<_> = meshes.iter().map(|(name, _)| name.as_str()).collect();
+    assert_eq!(names, vec!["left", "right"]);
+    let right = shape("<mesh><uri>pair.STL</uri><submesh><name>right</name></submesh></mesh>");
+    let mesh = load_submesh(&right, &resolver).unwrap();
+    assert!((mesh.vertices[0].x - 1.5).abs() < 1e-9);
+    let centered = shape(
+        "<mesh><uri>pair.STL</uri><submesh><name>right</name><center>true</center></submesh>\
+         <scale>3 3 3</scale></mesh>",
+    );
+    let mesh = load_mesh(&centered, &resolver).unwrap();
+    assert!((mesh.vertices[0].x + 1.5).abs() < 1e-9);
+    let missing = shape("<mesh><uri>pair.STL</uri><submesh><name>top</name></submesh></mesh>");
+    assert!(load_mesh(&missing, &resolver).unwrap_err().contains("top"));
+}
+
+#[test]
+fn test_obj_and_collada() {
+    let directory = directory("obj");
+    std::fs::write(
+        directory.join("parts.obj"),
+        "# A square and a triangle\n\
+         v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\n\
+         o square\nf 1/1/1 2/2/1 3/3/1 4/4/1\n\
+         v 5 5 5\n\
+         g tip\nf -1 -4 -3\n",
+    )
+    .unwrap();
+    let meshes = read_meshes(&directory.join("parts.obj")).unwrap();
+    assert_eq!(meshes[0].0, "square");
+    assert_eq!(meshes[0].1.triangles, vec![[0, 1, 2], [0, 2, 3]]);
+    assert_eq!(meshes[1].0, "tip");
+    assert_eq!(meshes[1].1.vertices.len(), 3);
+    assert_eq!(meshes[1].1.vertices[0], Vector3::new(5.0, 5.0, 5.0));
+
+    let (corners, triangles) = cube();
+    let positions: Vec<_> = corners
+        .iter()
+        .flatten()
+        .map(|value| value.to_string())
+        .collect();
+    let indices: Vec<_> = triangles
+        .iter()
+        .flatten()
+        .map(|index| format!("{} 0", index))
+        .collect();
+    let collada = format!(
+        r##"<?xml version="1.0"?>
+<COLLADA xmlns="http://www.collada.org/2005/11/COLLADASchema" version="1.4.1">
+  <asset><unit meter="0.1"/><up_axis>Z_UP</up_axis></asset>
+  <library_geometries>
+    <geometry id="cube-mesh" name="Cube">
+      <mesh>
+        <source id="cube-positions">
+          <float_array id="cube-array" count="24">{}</float_array>
+          <technique_common><accessor source="#cube-array" count="8" stride="3"/></technique_common>
+        </source>
+        <source id="cube-normals">
+          <float_array id="normals-array" count="3">0 0 1</float_array>
+        </source>
+        <vertices id="cube-vertices">
+          <input semantic="POSITION" source="#cube-positions"/>
+        </vertices>
+        <polylist count="12">
+          <input semantic="VERTEX" source="#cube-vertices" offset="0"/>
+          <input semantic="NORMAL" source="#cube-normals" offset="1"/>
+          <vcount>{}</vcount>
+          <p>{}</p>
+        </polylist>
+      </mesh>
+    </geometry>
+  </library_geometries>
+  <library_visual_scenes>
+    <visual_scene id="scene">
+      <node id="parent">
+        <translate>10 0 0</translate>
+        <node id="child">
+          <scale>2 2 2</scale>
+          <instance_geometry url="#cube-mesh"/>
+        </node>
+      </node>
+    </visual_scene>
+  </library_visual_scenes>
+  <scene><instance_visual_scene url="#scene"/></scene>
+</COLLADA>"##,
+        positions.join(" "),
+        ["3"; 12].join(" "),
+        indices.join(" ")
+    );
+    std::fs::write(directory.join("cube.dae"), collada).unwrap();
+    let resolver = |uri: &str| Ok(directory.join(uri));
+    let mesh = load_submesh(
+        &shape("<mesh><uri>cube.dae</uri><submesh><name>Cube</name></submesh></mesh>"),
+        &resolver,
+    )
+    .unwrap();
+    assert!(mesh.is_closed());
+    // Placed by the nodes, then converted from decimeters
+    let properties = MassProperties::of_mesh(&mesh, 1.0).unwrap();
+    assert!((properties.mass - 0.008).abs() < 1e-9);
+    assert!((properties.center - Vector3::new(1.0, 0.0, 0.0)).norm() < 1e-9);
+}
+
+fn base64(bytes: &[u8]) -> String {
+    let alphabet = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut text = String::new();
+    for chunk in bytes.chunks(3) {
+        let bits = chunk.iter().enumerate().fold(0u32, |bits, (index, byte)| {
+            bits | (*byte as u32) << (16 - 8 * index)
+        });
+        for index in 0..=chunk.len() {
+            text.push(alphabet[(bits >> (18 - 6 * index) & 63) as usize] as char);
+        }
+    }
+    while !text.len().is_multiple_of(4) {
+        text.push('=');
+    }
+    text
+}
+
+#[test]
+fn test_gltf() {
+    let directory = directory("gltf");
+    let (corners, triangles) = cube();
+    let mut buffer = vec![];
+    for value in corners.iter().flatten() {
+        buffer.extend((*value as f32).to_le_bytes());
+    }
+    for index in triangles.iter().flatten() {
+        buffer.extend((*index as u16).to_le_bytes());
+    }
+    let json = |uri: &str| {
+        format!(
+            r#"{{
+  "asset": {{"version": "2.0"}},
+  "scene": 0,
+  "scenes": [{{"nodes": [0]}}],
+  "nodes": [{{"name": "root", "translation": [0, 0, 3], "children": [1]}},
+            {{"mesh": 0, "rotation": [0, 0, 0.7071067811865476, 0.7071067811865476],
+              "scale": [1, 2, 1]}}],
+  "meshes": [{{"name": "box A", "primitives": [{{"attributes": {{"POSITION": 0}},
+                                               "indices": 1, "mode": 4}}]}}],
+  "accessors": [
+    {{"bufferView": 0, "componentType": 5126, "count": 8, "type": "VEC3",
+     "min": [-0.5, -0.5, -0.5], "max": [0.5, 0.5, 0.5]}},
+    {{"bufferView": 1, "componentType": 5123, "count": 36, "type": "SCALAR"}}
+  ],
+  "bufferViews": [
+    {{"buffer": 0, "byteOffset": 0, "byteLength": 96}},
+    {{"buffer": 0, "byteOffset": 96, "byteLength": 72}}
+  ],
+  "buffers": [{{{}"byteLength": 168}}]
+}}"#,
+            uri
+        )
+    };
+    let embedded = json(&format!(
+        r#""uri": "data:application/octet-stream;base64,{}", "#,
+        base64(&buffer)
+    ));
+    std::fs::write(directory.join("box.gltf"), embedded).unwrap();
+
+    let mut glb = json("").into_bytes();
+    glb.resize(glb.len().div_ceil(4) * 4, b' ');
+    let length = 12 + 8 + glb.len() + 8 + buffer.len();
+    let mut file = b"glTF".to_vec();
+    for word in [2, length, glb.len(), 0x4E4F534A] {
+        file.extend((word as u32).to_le_bytes());
+    }
+    file.extend(glb);
+    file.extend((buffer.len() as u32).to_le_bytes());
+    file.extend(0x004E4942u32.to_le_bytes());
+    file.extend(&buffer);
+    std::fs::write(directory.join("box.glb"), file).unwrap();
+
+    for name in ["box.gltf", "box.glb"] {
+        let meshes = read_meshes(&directory.join(name)).unwrap();
+        assert_eq!(meshes.len(), 1);
+        assert_eq!(meshes[0].0, "box A");
+        let properties = MassProperties::of_mesh(&meshes[0].1, 1.0).unwrap();
+        assert!((properties.mass - 2.0).abs() < 1e-6, "{}", name);
+        assert!((properties.center - Vector3::new(0.0, 0.0, 3.0)).norm() < 1e-6);
+        // The box is stretched along y, then turned a quarter turn about z
+        assert!(properties.inertia[(1, 1)] > properties.inertia[(0, 0)]);
+    }
+}
+
+#[test]
+fn test_path_resolver() {
+    let directory = directory("resolver");
+    std::fs::create_dir_all(directory.join("models/robot/meshes")).unwrap();
+    std::fs::write(
+        directory.join("models/robot/meshes/arm.stl"),
+        "solid arm\nendsolid arm\n",
+    )
+    .unwrap();
+    let mut resolver = PathResolver::new(directory.join("worlds"));
+    resolver.model_paths = vec![directory.join("missing"), directory.join("models")];
+
+    assert_eq!(
+        resolver.resolve("model://robot/meshes/arm.stl").unwrap(),
+        directory.join("models/robot/meshes/arm.stl")
+    );
+    assert!(resolver.resolve("model://robot/arm.stl").is_err());
+    assert_eq!(
+        resolver.resolve("meshes/arm.stl").unwrap(),
+        directory.join("worlds/meshes/arm.stl")
+    );
+    assert_eq!(
+        resolver.resolve("file:///opt/arm.stl").unwrap(),
+        PathBuf::from("/opt/arm.stl")
+    );
+    assert!(resolver
+        .resolve("package://robot/arm.stl")
+        .unwrap_err()
+        .contains("package"));
+    let empty = shape("<mesh><uri>model://robot/meshes/arm.stl</uri></mesh>");
+    assert!(load_mesh(&empty, &resolver).unwrap().triangles.is_empty());
+}
@@ -0,0 +1,369 @@
+use std::collections::HashMap;
+
+use sdformat::cst::{Document, Element};
+use sdformat::frames::FrameGraph;
+use sdformat::inertial::model_mass_properties;
+use sdformat::mjcf::{from_mjcf, model_to_mjcf, world_to_mjcf};
+use sdformat::{from_str, SdfGeometry, SdfModel, SdfWorld};
+
+const PENDULUM: &str = r#"<model name="pendulum">
+  <link name="base">
+    <collision name="box">
+      <geometry><box><size>0.2 0.4 0.6</size></box></geometry>
+    </collision>
+  </link>
+  <link name="arm">
+    <pose>0 0 1 0 0 0</pose>
+    <inertial>
+      <mass>2</mass>
+      <inertia><ixx>0.1</ixx><iyy>0.2</iyy><izz>0.3</izz></inertia>
+    </inertial>
+    <visual name="rod">
+      <pose>0 0 -0.5 0 0 0</pose>
+      <geometry><cylinder><radius>0.05</radius><length>1</length></cylinder></geometry>
+      <material><diffuse>1 0 0 1</diffuse></material>
+    </visual>
+    <collision name="mesh">
+      <geometry><mesh><uri>meshes/arm.stl</uri></mesh></geometry>
+    </collision>
+  </link>
+  <joint name="anchor" type="fixed">
+    <parent>world</parent>
+    <child>base</child>
+  </joint>
+  <joint name="hinge" type="revolute">
+    <pose>0 0 0.1 0 0 0</pose>
+    <parent>base</parent>
+    <child>arm</child>
+    <axis>
+      <xyz>0 1 0</xyz>
+      <limit><lower>-1</lower><upper>1</upper></limit>
+      <dynamics><damping>0.3</damping></dynamics>
+    </axis>
+  </joint>
+</model>"#;
+
+fn find<'a>(element: &'a Element, name: &str, value: &str) -> &'a Element {
+    element
+        .elements()
+        .find(|e| e.name() == name && e.attribute("name").as_deref() == Some(value))
+        .unwrap()
+}
+
+#[test]
+fn test_model_to_mjcf() {
+    let model = from_str::<SdfModel>(PENDULUM).unwrap();
+    let document = Document::parse(&model_to_mjcf(&model).unwrap()).unwrap();
+    let mujoco = document.root();
+    assert_eq!(mujoco.attribute("model").as_deref(), Some("pendulum"));
+    assert_eq!(
+        mujoco
+            .child("compiler")
+            .unwrap()
+            .attribute("angle")
+            .as_deref(),
+        Some("radian")
+    );
+    let mesh = mujoco.child("asset").unwrap().child("mesh").unwrap();
+    assert_eq!(mesh.attribute("file").as_deref(), Some("meshes/arm.stl"));
+
+    // The base is welded to the world, the arm is nested in it
+    let base = find(mujoco.child("worldbody").unwrap(), "body", "base");
+    assert!(base.child("joint").is_none() && base.child("freejoint").is_none());
+    let geom = find(base, "geom", "base::box");
+    assert_eq!(geom.attribute("size").as_deref(), Some("0.1 0.2 0.3"));
+    // Links without an inertial get the default one of SDFormat
+    let inertial = base.child("inertial").unwrap();
+    assert_eq!(inertial.attribute("mass").as_deref(), Some("1"));
+    assert_eq!(
+        inertial.attribute("fullinertia").as_deref(),
+        Some("1 1 1 0 0 0")
+    );
+
+    let arm = find(base, "body", "arm");
+    assert_eq!(arm.attribute("pos").as_deref(), Some("0 0 1"));
+    let inertial = arm.child("inertial").unwrap();
+    assert_eq!(inertial.attribute("mass").as_deref(), Some("2"));
+    assert_eq!(
+        inertial.attribute("fullinertia").as_deref(),
+        Some("0.1 0.2 0.3 0 0 0")
+    );
+    let joint = arm.child("joint").unwrap();
+    assert_eq!(joint.attribute("type").as_deref(), Some("hinge"));
+    assert_eq!(joint.attribute("pos").as_deref(), Some("0 0 0.1"));
+    assert_eq!(joint.attribute("axis").as_deref(), Some("0 1 0"));
+    assert_eq!(joint.attribute("range").as_deref(), Some("-1 1"));
+    assert_eq!(joint.attribute("damping").as_deref(), Some("0.3"));
+
+    let rod = find(arm, "geom", "arm::rod");
+    assert_eq!(rod.attribute("type").as_deref(), Some("cylinder"));
+    assert_eq!(rod.attribute("size").as_deref(), Some("0.05 0.5"));
+    assert_eq!(rod.attribute("pos").as_deref(), Some("0 0 -0.5"));
+    assert_eq!(rod.attribute("contype").as_deref(), Some("0"));
+    assert_eq!(rod.attribute("rgba").as_deref(), Some("1 0 0 1"));
+}
+
+#[test]
+fn test_world_to_mjcf() {
+    let world = format!(
+        r#"<world name="lab">
+  <gravity>0 0 -9.8</gravity>
+  <magnetic_field>0 0 0</magnetic_field>
+  <atmosphere type="adiabatic"/>
+  <scene><ambient>0.4 0.4 0.4 1</ambient><background>0.7 0.7 0.7 1</background><shadows>true</shadows></scene>
+  <physics type="ode"><max_step_size>0.002</max_step_size><real_time_factor>1</real_time_factor><real_time_update_rate>500</real_time_update_rate></physics>
+  <light name="sun" type="directional">
+    <pose>0 0 10 0 0 0</pose>
+    <direction>0 0 -1</direction>
+  </light>
+  {}
+  <model name="crate">
+    <pose>2 0 0 0 0 0</pose>
+    <link name="body"/>
+  </model>
+</world>"#,
+        PENDULUM.replace(
+            "<model name=\"pendulum\">",
+            "<model name=\"pendulum\"><static>true</static>"
+        )
+    );
+    let world = from_str::<SdfWorld>(&world).unwrap();
+    let document = Document::parse(&world_to_mjcf(&world).unwrap()).unwrap();
+    let mujoco = document.root();
+    let option = mujoco.child("option").unwrap();
+    assert_eq!(option.attribute("timestep").as_deref(), Some("0.002"));
+    assert_eq!(option.attribute("gravity").as_deref(), Some("0 0 -9.8"));
+
+    let worldbody = mujoco.child("worldbody").unwrap();
+    let light = find(worldbody, "light", "sun");
+    assert_eq!(light.attribute("dir").as_deref(), Some("0 0 -1"));
+
+    // Static models have no joints, free links get a free joint
+    let arm = find(
+        find(worldbody, "body", "pendulum::base"),
+        "body",
+        "pendulum::arm",
+    );
+    assert!(arm.child("joint").is_none());
+    let body = find(worldbody, "body", "crate::body");
+    assert_eq!(body.attribute("pos").as_deref(), Some("2 0 0"));
+    assert!(body.child("freejoint").is_some());
+}
+
+#[test]
+fn test_mjcf_errors() {
+    let mut model = from_str::<SdfModel>(PENDULUM).unwrap();
+    model.joint[1].r#type = "screw".to_string();
+    assert!(model_to_mjcf(&model).is_err());
+    let mut model = from_str::<SdfModel>(PENDULUM).unwrap();
+    model.model.push(Default::default());
+    assert!(model_to_mjcf(&model).unwrap_err().contains("flattened"));
+
+    // Meshes must be files MuJoCo can load
+    for uri in ["model://pendulum/meshes/arm.stl", "meshes/arm.dae"] {
+        let mut model = from_str::<SdfModel>(PENDULUM).unwrap();
+        let SdfGeometry::Mesh(mesh) = &mut model.link[1].collision[0].geometry else {
+            unreachable!()
+        };
+        mesh.uri = uri.to_string();
+        assert!(model_to_mjcf(&model).unwrap_err().contains(uri));
+    }
+}
+
+const HUMANOID: &str = r#"<mujoco model="walker">
+  <compiler angle="degree" meshdir="assets"/>
+  <default>
+    <joint damping="0.5"/>
+    <geom rgba="0 1 0 1"/>
+    <default class="limb">
+      <joint range="-90 90"/>
+      <geom type="capsule" size="0.05"/>
+    </default>
+  </default>
+  <asset>
+    <mesh file="foot.stl" scale="0.001 0.001 0.001"/>
+  </asset>
+  <worldbody>
+    <geom name="floor" type="plane" size="5 5 0.1" contype="1"/>
+    <body name="torso" pos="0 0 1">
+      <freejoint/>
+      <geom name="chest" type="box" size="0.1 0.2 0.3"/>
+      <site name="imu" pos="0 0 0.2"/>
+      <body name="thigh" pos="0 0 -0.3" euler="0 0 90" childclass="limb">
+        <inertial pos="0 0 -0.2" mass="3" diaginertia="0.1 0.1 0.01"/>
+        <joint name="hip_x" axis="1 0 0"/>
+        <joint name="hip_y" axis="0 1 0" limited="false"/>
+        <geom name="bone" fromto="0 0 0 0 0 -0.4"/>
+        <body name="foot" pos="0 0 -0.4">
+          <geom type="mesh" mesh="foot" contype="0" conaffinity="0"/>
+        </body>
+      </body>
+    </body>
+  </worldbody>
+</mujoco>"#;
+
+#[test]
+fn test_mjcf_import() {
+    let model = from_mjcf(HUMANOID).unwrap();
+    assert_eq!(model.name, "walker");
+    let links: Vec<_> = model.link.iter().map(|link| link.name.as_str()).collect();
+    assert_eq!(
+        links,
+        vec!["world_body", "torso", "thigh_hip_x", "thigh", "foot"]
+    );
+    let joints: Vec<_> = model
+        .joint
+        .iter()
+        .map(|joint| {
+            (
+                joint.name.as_str(),
+                joint.parent.as_str(),
+                joint.r#type.as_str(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        joints,
+        vec![
+            ("world_body_fixed", "world", "fixed"),
+            ("hip_x", "torso", "revolute"),
+            ("hip_y", "thigh_hip_x", "continuous"),
+            ("foot_fixed", "thigh", "fixed"),
+        ]
+    );
+
+    // Ranges of the class are converted from degrees, damping comes from the parent class
+    let axis = model.joint[1].axis.as_ref().unwrap();
+    let limit = axis.limit.as_ref().unwrap();
+    assert!((limit.upper - std::f64::consts::FRAC_PI_2).abs() < 1e-9);
+    assert_eq!(axis.dynamics.as_ref().unwrap().damping, Some(0.5));
+
+    // Bodies without an inertial get the one of their geoms, at a density of 1000
+    let torso = model.link[1].inertial.as_ref().unwrap();
+    assert!((torso.mass.unwrap() - 48.0).abs() < 1e-9);
+    let inertia = torso.inertia.as_ref().unwrap();
+    assert!((inertia.ixx - 48.0 * (0.16 + 0.36) / 12.0).abs() < 1e-9);
+
+    let thigh = &model.link[3];
+    assert_eq!(thigh.inertial.as_ref().unwrap().mass, Some(3.0));
+    let SdfGeometry::Capsule(bone) = &thigh.collision[0].geometry else {
+        panic!("expected a capsule");
+    };
+    assert!((bone.length - 0.4).abs() < 1e-9);
+    assert_eq!(
+        thigh.visual[0]
+            .material
+            .as_ref()
+            .unwrap()
+            .diffuse
+            .as_deref(),
+        Some("0 1 0 1")
+    );
+
+    // Meshes are not loaded, the foot keeps the default inertial
+    let foot = &model.link[4];
+    assert!(foot.inertial.is_none());
+    assert!(foot.collision.is_empty());
+    let SdfGeometry::Mesh(mesh) = &foot.visual[0].geometry else {
+        panic!("expected a mesh");
+    };
+    assert_eq!(mesh.uri, "assets/foot.stl");
+    assert_eq!(mesh.scale.as_ref().unwrap().0.x, 0.001);
+
+    // Poses of bodies are relative to their parent, sites become frames
+    let graph = FrameGraph::from_model(&model).unwrap();
+    let foot = graph.pose("foot", "__model__").unwrap();
+    assert!((foot.translation.vector - nalgebra::Vector3::new(0.0, 0.0, 0.3)).norm() < 1e-9);
+    assert!((foot.rotation.angle() - std::f64::consts::FRAC_PI_2).abs() < 1e-9);
+    assert_eq!(graph.attached_link("imu").unwrap(), "torso");
+}
+
+#[test]
+fn test_mjcf_intermediate_mass() {
+    // The link between the two hinges does not add to the mass of the body
+    let model = from_mjcf(
+        r#"<mujoco model="gimbal">
+  <worldbody>
+    <body name="ball">
+      <joint name="x" axis="1 0 0"/>
+      <joint name="y" axis="0 1 0"/>
+      <geom type="sphere" size="0.1" mass="2"/>
+    </body>
+  </worldbody>
+</mujoco>"#,
+    )
+    .unwrap();
+    assert_eq!(model.link.len(), 2);
+    let total = model_mass_properties(&model, &HashMap::new(), "__model__").unwrap();
+    assert!((total.mass - 2.0).abs() < 1e-6);
+}
+
+#[test]
+fn test_mjcf_mesh_assets() {
+    // Meshes are named after their file without the last extension and found in the asset
+    // directory when there is no mesh directory
+    let model = from_mjcf(
+        r#"<mujoco model="robot">
+  <compiler assetdir="assets"/>
+  <asset>
+    <mesh file="arm.v2.stl"/>
+  </asset>
+  <worldbody>
+    <body name="arm">
+      <geom type="mesh" mesh="arm.v2"/>
+    </body>
+  </worldbody>
+</mujoco>"#,
+    )
+    .unwrap();
+    let SdfGeometry::Mesh(mesh) = &model.link[0].visual[0].geometry else {
+        panic!("expected a mesh");
+    };
+    assert_eq!(mesh.uri, "assets/arm.v2.stl");
+}
+
+#[test]
+fn test_mjcf_round_trip() {
+    let model = from_str::<SdfModel>(PENDULUM).unwrap();
+    let imported = from_mjcf(&model_to_mjcf(&model).unwrap()).unwrap();
+    let original = FrameGraph::from_model(&model).unwrap();
+    let graph = FrameGraph::from_model(&imported).unwrap();
+    for link in ["base", "arm"] {
+        let expected = original.pose(link, "__model__").unwrap();
+        let pose = graph.pose(link, "__model__").unwrap();
+        assert!((pose.translation.vector - expected.translation.vector).norm() < 1e-9);
+    }
+    let hinge = imported.joint.iter().find(|j| j.name == "hinge").unwrap();
+    assert_eq!(hinge.r#type, "revolute");
+    assert_eq!(hinge.parent, "base");
+    assert_eq!(
+        hinge.axis.as_ref().unwrap().limit.as_ref().unwrap().upper,
+        1.0
+    );
+    assert!(from_mjcf("<robot name=\"r\"/>").is_err());
+}
+
+#[test]
+fn test_model_to_mjcf_expressed_in() {
+    // The child link is turned by 90° about Z, the axes are expressed in the model frame
+    let model = from_str::<SdfModel>(
+        r#"<model name="turned">
+  <link name="base"/>
+  <link name="arm"><pose>0 0 1 0 0 1.5707963267948966</pose></link>
+  <joint name="cross" type="universal">
+    <parent>base</parent>
+    <child>arm</child>
+    <axis><xyz expressed_in="__model__">1 0 0</xyz></axis>
+    <axis2><xyz expressed_in="__model__">0 1 0</xyz><limit/></axis2>
+  </joint>
+</model>"#,
+    )
+    .unwrap();
+    let document = Document::parse(&model_to_mjcf(&model).unwrap()).unwrap();
+    let worldbody = document.root().child("worldbody").unwrap();
+    let base = find(worldbody, "body", "base");
+    let arm = find(base, "body", "arm");
+    let axis = |name| find(arm, "joint", name).attribute("axis");
+    assert_eq!(axis("cross").as_deref(), Some("0 -1 0"));
+    assert_eq!(axis("cross_2").as_deref(), Some("1 0 0"));
+}
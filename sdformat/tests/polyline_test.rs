@@ -0,0 +1,177 @@
+use nalgebra::{Vector2, Vector3};
+use sdformat::inertial::MassProperties;
+use sdformat::polyline::{extrude, triangulate};
+use sdformat::{from_str, SdfGeometry, SdfPolylineShape};
+use yaserde::ser::to_string;
+
+fn polyline(points: &[[f64; 2]], height: f64) -> SdfPolylineShape {
+    let points: String = points
+        .iter()
+        .map(|[x, y]| format!("<point>{} {}</point>", x, y))
+        .collect();
+    match from_str::<SdfGeometry>(&format!(
+        "<geometry><polyline>{}<height>{}</height></polyline></geometry>",
+        points, height
+    ))
+    .unwrap()
+    {
+        SdfGeometry::Polyline(mut shapes) if shapes.len() == 1 => shapes.remove(0),
+        other => panic!("Not a polyline: {:?}", other),
+    }
+}
+
+fn square(center: [f64; 2], side: f64) -> Vec<[f64; 2]> {
+    let half = side / 2.0;
+    vec![
+        [center[0] - half, center[1] - half],
+        [center[0] + half, center[1] - half],
+        [center[0] + half, center[1] + half],
+        [center[0] - half, center[1] + half],
+    ]
+}
+
+fn assert_close(value: f64, expected: f64) {
+    assert!(
+        (value - expected).abs() < 1e-9,
+        "{} is not {}",
+        value,
+        expected
+    );
+}
+
+#[test]
+fn test_concave_polyline() {
+    // An L, clockwise and closed by repeating its first point, with a collinear point
+    let shape = polyline(
+        &[
+            [0.0, 0.0],
+            [0.0, 2.0],
+            [1.0, 2.0],
+            [1.0, 1.0],
+            [2.0, 1.0],
+            [2.0, 0.5],
+            [2.0, 0.0],
+            [0.0, 0.0],
+        ],
+        0.5,
+    );
+    let mesh = shape.to_mesh().unwrap();
+    assert!(mesh.is_closed());
+    // The repeated and collinear points are dropped
+    assert_eq!(mesh.vertices.len(), 12);
+    let properties = MassProperties::of_mesh(&mesh, 1.0).unwrap();
+    assert_close(properties.mass, 1.5);
+    assert_close(
+        MassProperties::of_geometry(&SdfGeometry::Polyline(vec![shape]), 2.0)
+            .unwrap()
+            .mass,
+        3.0,
+    );
+
+    // Caps face down and up, walls face sideways and away from the solid
+    for (triangle, normal) in mesh.triangles.iter().zip(mesh.normals()) {
+        let heights = triangle.map(|index| mesh.vertices[index].z);
+        if heights.iter().all(|height| *height == 0.0) {
+            assert_eq!(normal, -Vector3::z());
+        } else if heights.iter().all(|height| *height == 0.5) {
+            assert_eq!(normal, Vector3::z());
+        } else {
+            assert_close(normal.z, 0.0);
+            let center = triangle
+                .iter()
+                .map(|index| mesh.vertices[*index])
+                .sum::<Vector3<f64>>()
+                / 3.0;
+            let outside = center + normal * 1e-3;
+            let inside = center - normal * 1e-3;
+            let in_l = |point: Vector3<f64>| {
+                (0.0..=2.0).contains(&point.x)
+                    && (0.0..=2.0).contains(&point.y)
+                    && (point.x <= 1.0 || point.y <= 1.0)
+            };
+            assert!(in_l(inside) && !in_l(outside));
+        }
+    }
+
+    assert!(polyline(&[[0.0, 0.0], [1.0, 0.0], [2.0, 0.0]], 1.0)
+        .to_mesh()
+        .is_err());
+    assert!(polyline(&[[0.0, 0.0], [1.0, 0.0]], 1.0).to_mesh().is_err());
+}
+
+#[test]
+fn test_polylines_with_holes() {
+    // A square with two square holes, one of them around an island
+    let polylines = [
+        polyline(&square([0.0, 0.0], 10.0), 2.0),
+        polyline(&square([-2.5, 0.0], 4.0), 1.0),
+        polyline(&square([2.5, 0.0], 4.0), 1.0),
+        polyline(&square([2.5, 0.0], 2.0), 3.0),
+    ];
+    let mesh = extrude(&polylines).unwrap();
+    assert!(mesh.is_closed());
+    // Holes go through the height of the square around them
+    for vertex in mesh.vertices[16..24].iter() {
+        assert_eq!(vertex.z, 2.0);
+    }
+    let properties = MassProperties::of_mesh(&mesh, 1.0).unwrap();
+    assert_close(properties.mass, (100.0 - 2.0 * 16.0) * 2.0 + 4.0 * 3.0);
+    // The holes are symmetric in y
+    assert_close(properties.center.y, 0.0);
+
+    // Triangles cover the area inside the polygons and are counterclockwise
+    let polygons: Vec<Vec<_>> = polylines[..3]
+        .iter()
+        .map(|polyline| polyline.points().unwrap())
+        .collect();
+    let points: Vec<Vector2<f64>> = polygons.iter().flatten().copied().collect();
+    let area: f64 = triangulate(&polygons)
+        .unwrap()
+        .iter()
+        .map(|[a, b, c]| {
+            let (ab, ac) = (points[*b] - points[*a], points[*c] - points[*a]);
+            let area = (ab.x * ac.y - ab.y * ac.x) / 2.0;
+            assert!(area > 0.0);
+            area
+        })
+        .sum();
+    assert_close(area, 100.0 - 2.0 * 16.0);
+}
+
+#[test]
+fn test_geometry_with_holes() {
+    // The polylines of a geometry are extruded together, the inner square is a hole
+    let element = |points: Vec<[f64; 2]>, height: f64| -> String {
+        let points: String = points
+            .iter()
+            .map(|[x, y]| format!("<point>{} {}</point>", x, y))
+            .collect();
+        format!("<polyline>{}<height>{}</height></polyline>", points, height)
+    };
+    let xml = format!(
+        "<geometry>{}{}</geometry>",
+        element(square([0.0, 0.0], 4.0), 2.0),
+        element(square([0.0, 0.0], 2.0), 1.0)
+    );
+    let geometry = from_str::<SdfGeometry>(&xml).unwrap();
+    let SdfGeometry::Polyline(shapes) = &geometry else {
+        panic!("Not a polyline: {:?}", geometry);
+    };
+    assert_eq!(shapes.len(), 2);
+    let properties = MassProperties::of_geometry(&geometry, 1.0).unwrap();
+    assert_close(properties.mass, (16.0 - 4.0) * 2.0);
+    let obb = geometry.obb(&mut |_| unreachable!()).unwrap();
+    assert_close(obb.aabb().size().z, 2.0);
+
+    // Both polylines are written back
+    let written = to_string(&geometry).unwrap();
+    assert_eq!(from_str::<SdfGeometry>(&written).unwrap(), geometry);
+
+    // Other alternatives still can't be repeated or mixed with polylines
+    let mixed = format!(
+        "<geometry>{}<box/></geometry>",
+        element(square([0.0, 0.0], 4.0), 1.0)
+    );
+    assert!(from_str::<SdfGeometry>(&mixed).is_err());
+    assert!(from_str::<SdfGeometry>("<geometry><box/><box/></geometry>").is_err());
+}
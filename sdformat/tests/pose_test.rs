@@ -0,0 +1,60 @@
+use nalgebra::{Rotation3, Vector3};
+use yaserde::de::from_str;
+
+use sdformat::{Pose, SdfPose};
+
+fn pose(xml: &str) -> Result<Pose, String> {
+    from_str::<SdfPose>(xml).unwrap().get_pose()
+}
+
+fn assert_quarter_turn(rotation: &Rotation3<f64>) {
+    let quarter_turn = Rotation3::from_euler_angles(0.0, 0.0, std::f64::consts::FRAC_PI_2);
+    assert!(
+        (rotation.matrix() - quarter_turn.matrix()).norm() < 1e-9,
+        "{} is not a quarter turn about z",
+        rotation
+    );
+}
+
+#[test]
+fn test_euler_angles() {
+    let radians = pose("<pose>1 2 3 0 0 1.5707963267948966</pose>").unwrap();
+    assert_eq!(radians.translation, Vector3::new(1.0, 2.0, 3.0));
+    assert_quarter_turn(&radians.rotation);
+
+    let degrees = pose(r#"<pose degrees="true">0 0 0 0 0 90</pose>"#).unwrap();
+    assert_quarter_turn(&degrees.rotation);
+    let radians = pose(r#"<pose degrees="false">0 0 0 0 0 1.5707963267948966</pose>"#).unwrap();
+    assert_quarter_turn(&radians.rotation);
+}
+
+#[test]
+fn test_quaternion() {
+    // Quaternions are written x y z w
+    let quaternion = pose(
+        r#"<pose rotation_format="quat_xyzw">1 2 3 0 0 0.7071067811865476 0.7071067811865476</pose>"#,
+    )
+    .unwrap();
+    assert_eq!(quaternion.translation, Vector3::new(1.0, 2.0, 3.0));
+    assert_quarter_turn(&quaternion.rotation);
+
+    // The quaternion is normalized, a zero quaternion has no rotation
+    let scaled = pose(r#"<pose rotation_format="quat_xyzw">0 0 0 0 0 2 2</pose>"#).unwrap();
+    assert_quarter_turn(&scaled.rotation);
+    assert!(pose(r#"<pose rotation_format="quat_xyzw">0 0 0 0 0 0 0</pose>"#).is_err());
+}
+
+#[test]
+fn test_pose_values() {
+    // An empty pose is the identity, as the default value of the element
+    let empty = pose(r#"<pose relative_to="base"></pose>"#).unwrap();
+    assert_eq!(empty.translation, Vector3::zeros());
+    assert_eq!(empty.rotation, Rotation3::identity());
+    assert_eq!(empty.relative_to, "base");
+
+    // The number of values must match the rotation format
+    assert!(pose("<pose>0 0 0 0 0 0 1</pose>").is_err());
+    assert!(pose(r#"<pose rotation_format="quat_xyzw">0 0 0 0 0 0</pose>"#).is_err());
+    assert!(pose("<pose>0 0 0 0 0</pose>").is_err());
+    assert!(pose("<pose>0 0 0 0 0 a</pose>").is_err());
+}
@@ -0,0 +1,282 @@
+use sdformat::frames::FrameGraph;
+use sdformat::urdf::{from_urdf, from_urdf_with_options, to_urdf, UrdfOptions};
+use sdformat::{from_str, SdfGeometry, SdfModel};
+
+const ROBOT: &str = r#"<?xml version="1.0"?>
+<robot name="arm">
+  <material name="blue">
+    <color rgba="0 0 1 1"/>
+  </material>
+  <link name="world"/>
+  <link name="base">
+    <inertial>
+      <origin xyz="0 0 0.5"/>
+      <mass value="2"/>
+      <inertia ixx="1" ixy="0" ixz="0" iyy="1" iyz="0" izz="1"/>
+    </inertial>
+    <visual>
+      <geometry><box size="1 1 1"/></geometry>
+      <material name="blue"/>
+    </visual>
+    <collision>
+      <geometry><box size="1 1 1"/></geometry>
+    </collision>
+  </link>
+  <link name="upper">
+    <visual>
+      <origin xyz="0 0 0.25" rpy="0 0 0"/>
+      <geometry><cylinder radius="0.1" length="0.5"/></geometry>
+    </visual>
+  </link>
+  <link name="tool">
+    <inertial>
+      <mass value="1"/>
+      <inertia ixx="0.1" ixy="0" ixz="0" iyy="0.1" iyz="0" izz="0.1"/>
+    </inertial>
+    <collision>
+      <geometry><sphere radius="0.05"/></geometry>
+    </collision>
+  </link>
+  <joint name="anchor" type="fixed">
+    <parent link="world"/>
+    <child link="base"/>
+  </joint>
+  <joint name="shoulder" type="revolute">
+    <origin xyz="0 0 1" rpy="0 0 1.5707963267948966"/>
+    <parent link="base"/>
+    <child link="upper"/>
+    <axis xyz="0 1 0"/>
+    <limit lower="-1" upper="1" effort="10" velocity="2"/>
+    <dynamics damping="0.5"/>
+  </joint>
+  <joint name="flange" type="fixed">
+    <origin xyz="0 0 0.5"/>
+    <parent link="upper"/>
+    <child link="tool"/>
+  </joint>
+  <gazebo>
+    <static>false</static>
+    <plugin name="control" filename="libcontrol.so"><rate>100</rate></plugin>
+  </gazebo>
+  <gazebo reference="tool">
+    <mu1>0.8</mu1>
+    <custom>kept</custom>
+  </gazebo>
+</robot>
+"#;
+
+#[test]
+fn test_urdf_import() {
+    let model = from_urdf(ROBOT).unwrap();
+    assert_eq!(model.name, "arm");
+    assert_eq!(model.r#static, Some(false));
+    assert_eq!(model.plugin[0].filename, "libcontrol.so");
+
+    // The tool is lumped into the upper link
+    let names: Vec<_> = model.link.iter().map(|link| link.name.as_str()).collect();
+    assert_eq!(names, vec!["base", "upper"]);
+    let joints: Vec<_> = model
+        .joint
+        .iter()
+        .map(|joint| joint.name.as_str())
+        .collect();
+    assert_eq!(joints, vec!["anchor", "shoulder"]);
+    assert_eq!(model.joint[0].parent, "world");
+
+    let base = &model.link[0];
+    assert_eq!(base.visual[0].name, "base_visual");
+    let material = base.visual[0].material.as_ref().unwrap();
+    assert_eq!(material.diffuse.as_deref(), Some("0 0 1 1"));
+    assert_eq!(base.inertial.as_ref().unwrap().mass, Some(2.0));
+
+    let shoulder = &model.joint[1];
+    assert_eq!(shoulder.r#type, "revolute");
+    let axis = shoulder.axis.as_ref().unwrap();
+    assert_eq!(axis.xyz.data.0, nalgebra::Vector3::y());
+    let limit = axis.limit.as_ref().unwrap();
+    assert_eq!(
+        (limit.lower, limit.upper, limit.effort),
+        (-1.0, 1.0, Some(10.0))
+    );
+    assert_eq!(axis.dynamics.as_ref().unwrap().damping, Some(0.5));
+
+    let upper = &model.link[1];
+    let collision = &upper.collision[0];
+    assert_eq!(collision.name, "upper_fixed_joint_lump__tool_collision");
+    assert!(matches!(collision.geometry, SdfGeometry::Sphere(_)));
+    let friction = collision
+        .surface
+        .as_ref()
+        .unwrap()
+        .friction
+        .as_ref()
+        .unwrap();
+    assert_eq!(friction.ode.as_ref().unwrap().mu, Some(0.8));
+    assert_eq!(upper.inertial.as_ref().unwrap().mass, Some(1.0));
+
+    // Frames keep the lumped link available, with the same pose as the original link
+    let graph = FrameGraph::from_model(&model).unwrap();
+    let tool = graph.pose("tool", "__model__").unwrap();
+    let expected = nalgebra::Vector3::new(0.0, 0.0, 1.5);
+    assert!((tool.translation.vector - expected).norm() < 1e-9);
+    assert_eq!(graph.attached_link("tool").unwrap(), "upper");
+    let upper = graph.pose("upper", "__model__").unwrap();
+    assert!((upper.rotation.angle() - std::f64::consts::FRAC_PI_2).abs() < 1e-9);
+}
+
+#[test]
+fn test_urdf_without_lumping() {
+    let options = UrdfOptions {
+        lump_fixed_joints: false,
+    };
+    let model = from_urdf_with_options(ROBOT, &options).unwrap();
+    assert_eq!(model.link.len(), 3);
+    assert!(model.frame.is_empty());
+    let tool = &model.link[2];
+    assert_eq!(tool.extra.get("custom").unwrap().name.as_ref(), "custom");
+    let flange = &model.joint[2];
+    assert_eq!(flange.r#type, "fixed");
+    assert_eq!(
+        flange.pose.as_ref().unwrap().relative_to.as_deref(),
+        Some("upper")
+    );
+}
+
+/// A robot with two links that are lumped together, `settings` are the extensions of the tool.
+fn lumped_settings(settings: &str) -> Result<SdfModel, String> {
+    from_urdf(&format!(
+        r#"<robot name="r">
+  <link name="base"/>
+  <link name="tool"/>
+  <joint name="flange" type="fixed">
+    <parent link="base"/>
+    <child link="tool"/>
+  </joint>
+  <gazebo reference="base"><self_collide>true</self_collide></gazebo>
+  <gazebo reference="tool">{}</gazebo>
+</robot>"#,
+        settings
+    ))
+}
+
+#[test]
+fn test_urdf_lumped_settings() {
+    // The settings and extensions of the lumped link are moved to the parent link
+    let model = lumped_settings(
+        "<turnGravityOff>true</turnGravityOff><kinematic>true</kinematic><custom/>",
+    )
+    .unwrap();
+    assert_eq!(model.link.len(), 1);
+    let base = &model.link[0];
+    assert_eq!(base.gravity, Some(false));
+    assert_eq!(base.kinematic, Some(true));
+    assert_eq!(base.self_collide, Some(true));
+    assert!(base.extra.get("custom").is_some());
+
+    // Settings that agree with the parent can be repeated, conflicting ones are an error
+    assert!(lumped_settings("<selfCollide>true</selfCollide>").is_ok());
+    let error = lumped_settings("<selfCollide>false</selfCollide>").unwrap_err();
+    assert!(error.contains("self_collide"));
+}
+
+#[test]
+fn test_urdf_errors() {
+    assert!(from_urdf("<model name=\"m\"/>").is_err());
+    let unknown = r#"<robot name="r"><link name="a"/>
+        <joint name="j" type="fixed"><parent link="a"/><child link="b"/></joint></robot>"#;
+    assert!(from_urdf(unknown).is_err());
+    let planar = r#"<robot name="r"><link name="a"/><link name="b"/>
+        <joint name="j" type="planar"><parent link="a"/><child link="b"/></joint></robot>"#;
+    assert!(from_urdf(planar).is_err());
+}
+
+#[test]
+fn test_urdf_export() {
+    let model = from_urdf(ROBOT).unwrap();
+    let urdf = to_urdf(&model).unwrap();
+    assert!(urdf.contains("<link name=\"world\"/>"));
+    assert!(urdf.contains("<plugin name=\"control\" filename=\"libcontrol.so\">"));
+
+    // Links and shapes keep their poses when read back
+    let exported = from_urdf_with_options(
+        &urdf,
+        &UrdfOptions {
+            lump_fixed_joints: false,
+        },
+    )
+    .unwrap();
+    let original = FrameGraph::from_model(&model).unwrap();
+    let graph = FrameGraph::from_model(&exported).unwrap();
+    for link in ["base", "upper"] {
+        let expected = original.pose(link, "__model__").unwrap();
+        let pose = graph.pose(link, "__model__").unwrap();
+        assert!((pose.translation.vector - expected.translation.vector).norm() < 1e-9);
+        assert!(pose.rotation.angle_to(&expected.rotation) < 1e-9);
+    }
+    let upper = &exported.link[1];
+    assert_eq!(
+        upper.collision[0].name,
+        "upper_fixed_joint_lump__tool_collision"
+    );
+    let axis = exported.joint[1].axis.as_ref().unwrap();
+    assert_eq!(axis.limit.as_ref().unwrap().upper, 1.0);
+}
+
+#[test]
+fn test_urdf_export_errors() {
+    let mut model = from_urdf(ROBOT).unwrap();
+    model.link[1].visual[0].geometry = SdfGeometry::Capsule(Default::default());
+    assert!(to_urdf(&model).unwrap_err().contains("capsule"));
+
+    // A second parent closes a loop
+    let mut model = from_urdf(ROBOT).unwrap();
+    let mut joint = model.joint[1].clone();
+    joint.name = "second".to_string();
+    model.joint.push(joint);
+    assert!(to_urdf(&model).unwrap_err().contains("loop"));
+
+    let mut model = from_urdf(ROBOT).unwrap();
+    model.model.push(Default::default());
+    assert!(to_urdf(&model).unwrap_err().contains("flattened"));
+
+    let mut model = from_urdf(ROBOT).unwrap();
+    model.joint[1].r#type = "ball".to_string();
+    assert!(to_urdf(&model).is_err());
+}
+
+/// The axis of the joint is expressed in the model frame, the child link is turned by 90° about Z.
+const EXPRESSED_IN: &str = r#"<model name="turned">
+  <link name="base"/>
+  <link name="arm"><pose>0 0 1 0 0 1.5707963267948966</pose></link>
+  <joint name="hinge" type="revolute">
+    <parent>base</parent>
+    <child>arm</child>
+    <axis><xyz expressed_in="__model__">1 0 0</xyz></axis>
+  </joint>
+</model>"#;
+
+#[test]
+fn test_urdf_export_expressed_in() {
+    let model = from_str::<SdfModel>(EXPRESSED_IN).unwrap();
+    let urdf = to_urdf(&model).unwrap();
+    assert!(urdf.contains("<axis xyz=\"0 -1 0\"/>"));
+}
+
+#[test]
+fn test_urdf_export_unlimited() {
+    // Joints without effort or velocity limits are exported with large positive limits
+    let model = from_str::<SdfModel>(EXPRESSED_IN).unwrap();
+    let urdf = to_urdf(&model).unwrap();
+    assert!(urdf.contains("effort=\"10000000000000000\" velocity=\"10000000000000000\""));
+
+    // Negative limits mean unlimited in SDFormat
+    let mut model = model;
+    let axis = model.joint[0].axis.as_mut().unwrap();
+    axis.limit = Some(sdformat::SdfJointAxisLimit {
+        effort: Some(-1.0),
+        velocity: Some(2.0),
+        ..Default::default()
+    });
+    let urdf = to_urdf(&model).unwrap();
+    assert!(urdf.contains("effort=\"10000000000000000\" velocity=\"2\""));
+}
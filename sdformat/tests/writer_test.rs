@@ -0,0 +1,47 @@
+use sdformat::{from_str, to_string_pretty, AttributeOrder, SdfModel, WriteOptions};
+
+const MODEL: &str = "<model name=\"robot\"><static>false</static><link name=\"base\"><pose relative_to=\"__model__\">0.1000000001 0 0.5 0 0 0</pose><visual name=\"shape\"><geometry><box><size>1 1 1</size></box></geometry></visual></link></model>";
+
+#[test]
+fn test_pretty_print() {
+    let model = from_str::<SdfModel>(MODEL).unwrap();
+    let pretty = to_string_pretty(&model, &WriteOptions::default()).unwrap();
+    assert_eq!(
+        pretty,
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<model name="robot">
+  <static>false</static>
+  <link name="base">
+    <pose relative_to="__model__">0.1000000001 0 0.5 0 0 0</pose>
+    <visual name="shape">
+      <geometry>
+        <box>
+          <size>1 1 1</size>
+        </box>
+      </geometry>
+    </visual>
+  </link>
+</model>
+"#
+    );
+    // The output can be read back
+    assert_eq!(from_str::<SdfModel>(&pretty).unwrap(), model);
+}
+
+#[test]
+fn test_write_options() {
+    let mut model = from_str::<SdfModel>(MODEL).unwrap();
+    model.extra_attributes.insert("custom".into(), "1".into());
+    let options = WriteOptions {
+        indent: "\t".to_string(),
+        xml_declaration: false,
+        attribute_order: AttributeOrder::Alphabetical,
+        float_precision: Some(3),
+        omit_defaults: true,
+    };
+    let pretty = to_string_pretty(&model, &options).unwrap();
+    assert_eq!(
+        pretty,
+        "<model custom=\"1\" name=\"robot\">\n\t<link name=\"base\">\n\t\t<pose relative_to=\"__model__\">0.1 0 0.5 0 0 0</pose>\n\t\t<visual name=\"shape\">\n\t\t\t<geometry>\n\t\t\t\t<box>\n\t\t\t\t\t<size>1 1 1</size>\n\t\t\t\t</box>\n\t\t\t</geometry>\n\t\t</visual>\n\t</link>\n</model>\n"
+    );
+}